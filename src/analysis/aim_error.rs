@@ -0,0 +1,207 @@
+use crate::beatmap::Beatmap;
+use crate::data::Mode;
+use crate::math::Point;
+use crate::replay::{Replay, ReplayActionData, Timeline};
+use crate::sim::{OsuSimulator, SimulationResult};
+
+/// Where the cursor was when a single object was hit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AimOffset {
+  /// The index of the hit object in `Beatmap::hit_objects`.
+  pub object_index: usize,
+
+  /// The time of the key press, in milliseconds of map time.
+  pub time: f64,
+
+  /// The position of the cursor relative to the center of the object, in osu!pixels.
+  pub offset: Point<f64>,
+
+  /// The offset divided by the circle radius, so the cursor was inside of the circle if the
+  /// length of this is at most 1.
+  pub normalized: Point<f64>,
+
+  /// How far the cursor was past (positive) or short of (negative) the center of the object, in
+  /// the direction of the jump from the previous object. Not present for the first object or
+  /// if both objects are at the same position.
+  pub along: Option<f64>,
+
+  /// How far the cursor was to the right (positive) or left (negative) of the jump from the
+  /// previous object.
+  pub perpendicular: Option<f64>,
+}
+
+/// The mean and standard deviation of a set of values.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AxisStats {
+  /// The mean of the values.
+  pub mean: f64,
+
+  /// The standard deviation of the values.
+  pub std_dev: f64,
+}
+
+impl AxisStats {
+  /// Computes the statistics of the given values, or `None` if there aren't any.
+  pub fn from_values(
+    values: impl Iterator<Item = f64> + Clone,
+  ) -> Option<AxisStats> {
+    let count = values.clone().count();
+    if count == 0 {
+      return None;
+    }
+
+    let mean = values.clone().sum::<f64>() / count as f64;
+    let variance =
+      values.map(|value| (value - mean).powi(2)).sum::<f64>() / count as f64;
+    Some(AxisStats {
+      mean,
+      std_dev: variance.sqrt(),
+    })
+  }
+}
+
+/// Aim error statistics of an osu!standard play.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AimErrorStats {
+  /// The aim offset of every circle and slider head that was hit, in chronological order.
+  pub offsets: Vec<AimOffset>,
+
+  /// The radius of the circles in osu!pixels, after mods have been applied.
+  pub radius: f64,
+
+  /// The average offset, in osu!pixels.
+  pub mean: Point<f64>,
+
+  /// The average distance between the cursor and the center of the object, in osu!pixels.
+  pub mean_distance: f64,
+
+  /// Statistics of the offsets along the incoming jumps, in osu!pixels.
+  pub along: Option<AxisStats>,
+
+  /// Statistics of the offsets perpendicular to the incoming jumps, in osu!pixels.
+  pub perpendicular: Option<AxisStats>,
+}
+
+/// A two dimensional histogram of aim offsets, normalized to the circle radius.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AimDistribution {
+  /// The grid covers offsets from `-extent` to `extent` circle radii on both axes.
+  pub extent: f64,
+
+  /// The number of offsets in each cell, indexed by row (y) and then by column (x).
+  pub counts: Vec<Vec<u32>>,
+
+  /// The number of offsets that didn't fit on the grid.
+  pub outside: u32,
+}
+
+impl AimErrorStats {
+  /// Simulates the replay on the given beatmap and computes its aim error statistics. Returns
+  /// `None` if the replay isn't an osu!standard replay.
+  pub fn from_replay(
+    beatmap: &Beatmap,
+    replay: &Replay,
+    actions: &ReplayActionData,
+  ) -> Option<AimErrorStats> {
+    if replay.mode != Mode::Osu {
+      return None;
+    }
+
+    let sim = OsuSimulator::new(beatmap, replay.mods);
+    let result = sim.simulate(actions);
+    Some(AimErrorStats::from_simulation(&sim, &result, actions))
+  }
+
+  /// Computes the aim error statistics of replay actions that were simulated by the given
+  /// simulator.
+  pub fn from_simulation(
+    sim: &OsuSimulator,
+    result: &SimulationResult,
+    actions: &ReplayActionData,
+  ) -> AimErrorStats {
+    let timeline = Timeline::new(actions);
+    let radius = sim.circle_radius();
+
+    let offsets = result
+      .judgements
+      .iter()
+      .filter(|judgement| judgement.is_hit_error())
+      .filter_map(|judgement| {
+        let index = judgement.object_index;
+        let center = sim.object_position(index)?;
+
+        // judgements happen on the frame of the key press, so this isn't interpolated
+        let offset = timeline.position_at(judgement.time)? - center;
+
+        let jump = index
+          .checked_sub(1)
+          .and_then(|previous| sim.object_end_position(previous))
+          .map(|previous| center - previous)
+          .filter(|jump| jump.length() >= 1.0)
+          .map(|jump| jump.norm());
+
+        Some(AimOffset {
+          object_index: index,
+          time: judgement.time,
+          offset,
+          normalized: offset * (1.0 / radius),
+          along: jump.map(|jump| offset.dot(jump)),
+          perpendicular: jump.map(|jump| jump.x * offset.y - jump.y * offset.x),
+        })
+      })
+      .collect::<Vec<_>>();
+
+    let count = offsets.len().max(1) as f64;
+    let sum = offsets
+      .iter()
+      .fold(Point::new(0.0, 0.0), |sum, hit| sum + hit.offset);
+    let mean_distance =
+      offsets.iter().map(|hit| hit.offset.length()).sum::<f64>() / count;
+    let along =
+      AxisStats::from_values(offsets.iter().filter_map(|hit| hit.along));
+    let perpendicular = AxisStats::from_values(
+      offsets.iter().filter_map(|hit| hit.perpendicular),
+    );
+
+    AimErrorStats {
+      radius,
+      mean: sum * (1.0 / count),
+      mean_distance,
+      along,
+      perpendicular,
+      offsets,
+    }
+  }
+
+  /// Puts the normalized offsets on a `bins` by `bins` grid covering `-extent` to `extent`
+  /// circle radii on both axes.
+  pub fn distribution(&self, bins: usize, extent: f64) -> AimDistribution {
+    let mut counts = vec![vec![0; bins]; bins];
+    let mut outside = 0;
+    let cell = |value: f64| {
+      let cell = ((value + extent) / (2.0 * extent) * bins as f64).floor();
+      if cell >= 0.0 && cell < bins as f64 {
+        Some(cell as usize)
+      } else {
+        None
+      }
+    };
+
+    for hit in self.offsets.iter() {
+      match (cell(hit.normalized.x), cell(hit.normalized.y)) {
+        (Some(x), Some(y)) => counts[y][x] += 1,
+        _ => outside += 1,
+      }
+    }
+
+    AimDistribution {
+      extent,
+      counts,
+      outside,
+    }
+  }
+}
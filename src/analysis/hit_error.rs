@@ -0,0 +1,172 @@
+use crate::beatmap::Beatmap;
+use crate::data::Mods;
+use crate::replay::{Replay, ReplayActionData};
+use crate::sim::{simulate_replay, SimulationResult};
+
+/// The offset of a single hit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HitOffset {
+  /// The index of the hit object in `Beatmap::hit_objects`.
+  pub object_index: usize,
+
+  /// The time of the key press, in milliseconds of map time.
+  pub time: f64,
+
+  /// How early (negative) or late (positive) the key press was, in milliseconds of real time.
+  pub offset: f64,
+}
+
+/// Hit error statistics of a play, like the ones shown on the results screen.
+///
+/// All offsets are in milliseconds of real time, so with Double Time a hit that was 15ms late in
+/// the map is 10ms late.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HitErrorStats {
+  /// The offset of every hit, in chronological order.
+  pub offsets: Vec<HitOffset>,
+
+  /// The average offset, or 0 if nothing was hit.
+  pub mean: f64,
+
+  /// The average offset of the hits that were early, if there are any.
+  pub early: Option<f64>,
+
+  /// The average offset of the hits that were on time or late, if there are any.
+  pub late: Option<f64>,
+
+  /// Ten times the standard deviation of the offsets, or 0 if nothing was hit.
+  pub unstable_rate: f64,
+}
+
+/// Hit errors grouped into bins of equal width.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Histogram {
+  /// The lower bound of the first bin.
+  pub start: f64,
+
+  /// The width of every bin, in milliseconds.
+  pub bin_width: f64,
+
+  /// The number of hits in each bin.
+  pub counts: Vec<u32>,
+}
+
+impl Histogram {
+  /// Iterates over the lower bound of every bin together with its count.
+  pub fn bins(&self) -> impl Iterator<Item = (f64, u32)> + '_ {
+    self
+      .counts
+      .iter()
+      .enumerate()
+      .map(move |(i, count)| (self.start + i as f64 * self.bin_width, *count))
+  }
+}
+
+impl HitErrorStats {
+  /// Simulates the replay on the given beatmap and computes the statistics of its hits.
+  pub fn from_replay(
+    beatmap: &Beatmap,
+    replay: &Replay,
+    actions: &ReplayActionData,
+  ) -> HitErrorStats {
+    let result = simulate_replay(beatmap, replay, actions);
+    HitErrorStats::from_simulation(&result, replay.mods)
+  }
+
+  /// Computes the statistics of the hits in a simulated replay that was played with the given
+  /// mods.
+  pub fn from_simulation(
+    result: &SimulationResult,
+    mods: Mods,
+  ) -> HitErrorStats {
+    let speed = mods.speed_multiplier();
+    let offsets = result
+      .judgements
+      .iter()
+      .filter(|judgement| judgement.is_hit_error())
+      .filter_map(|judgement| {
+        Some(HitOffset {
+          object_index: judgement.object_index,
+          time: judgement.time,
+          offset: judgement.offset? / speed,
+        })
+      })
+      .collect();
+    HitErrorStats::from_offsets(offsets)
+  }
+
+  /// Computes the statistics of the given hits.
+  pub fn from_offsets(offsets: Vec<HitOffset>) -> HitErrorStats {
+    let mean_of = |values: &mut dyn Iterator<Item = f64>| {
+      let (sum, count) =
+        values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+      if count > 0 {
+        Some(sum / count as f64)
+      } else {
+        None
+      }
+    };
+
+    let mean =
+      mean_of(&mut offsets.iter().map(|hit| hit.offset)).unwrap_or(0.0);
+    let early = mean_of(
+      &mut offsets
+        .iter()
+        .map(|hit| hit.offset)
+        .filter(|offset| *offset < 0.0),
+    );
+    let late = mean_of(
+      &mut offsets
+        .iter()
+        .map(|hit| hit.offset)
+        .filter(|offset| *offset >= 0.0),
+    );
+    let variance =
+      mean_of(&mut offsets.iter().map(|hit| (hit.offset - mean).powi(2)))
+        .unwrap_or(0.0);
+
+    HitErrorStats {
+      offsets,
+      mean,
+      early,
+      late,
+      unstable_rate: variance.sqrt() * 10.0,
+    }
+  }
+
+  /// Groups the offsets into bins that are `bin_width` milliseconds wide. Bins are aligned to
+  /// multiples of the width, so one of them always starts at 0.
+  pub fn histogram(&self, bin_width: f64) -> Histogram {
+    let min = self
+      .offsets
+      .iter()
+      .map(|hit| hit.offset)
+      .fold(f64::INFINITY, f64::min);
+    if !min.is_finite() || bin_width <= 0.0 {
+      return Histogram {
+        start: 0.0,
+        bin_width,
+        counts: Vec::new(),
+      };
+    }
+
+    let start = (min / bin_width).floor() * bin_width;
+    let mut counts = Vec::new();
+    for hit in self.offsets.iter() {
+      let bin = ((hit.offset - start) / bin_width).floor() as usize;
+      if counts.len() <= bin {
+        counts.resize(bin + 1, 0);
+      }
+      counts[bin] += 1;
+    }
+
+    Histogram {
+      start,
+      bin_width,
+      counts,
+    }
+  }
+}
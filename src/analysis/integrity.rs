@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::beatmap::Beatmap;
+use crate::data::{Mode, Mods};
+use crate::replay::{KeyEventKind, Replay, ReplayActionData, Timeline};
+use crate::sim::{simulate_replay, OsuSimulator, SimulationResult};
+
+use super::{AxisStats, HitErrorStats};
+
+/// The time between frames that stable records while the map plays at normal speed.
+const STABLE_FRAME_INTERVAL: f64 = 1000.0 / 60.0;
+
+/// How the time between the frames of a replay compares to the speed the map was played at, for
+/// finding replays that were recorded with the game clock slowed down (timewarp).
+///
+/// osu!stable records a frame about every 16.67ms of real time, so frame times (which are in
+/// map time) are about 25ms apart with DoubleTime and 12.5ms apart with HalfTime. Slowing the game
+/// down makes them closer together than the mods can explain. Frames that are only added for key
+/// presses are rarer than the regular ones, and are left out by looking at the most common frame
+/// interval instead of the average. This doesn't apply to osu!lazer replays, which are recorded
+/// differently.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimewarpStats {
+  /// The most common time between frames, in milliseconds of map time.
+  pub frame_interval: f64,
+
+  /// The speed the map seems to have been played at according to the frame interval, where 1 is
+  /// normal speed.
+  pub rate: f64,
+
+  /// The speed the map should have been played at with the mods of the replay.
+  pub expected_rate: f64,
+}
+
+impl TimewarpStats {
+  /// Measures the frame interval of the given replay actions that were played with the given
+  /// mods. Returns `None` if there aren't any frames that are apart in time.
+  pub fn from_actions(
+    actions: &ReplayActionData,
+    mods: Mods,
+  ) -> Option<TimewarpStats> {
+    let timeline = Timeline::new(actions);
+    let mut counts = BTreeMap::<i32, u32>::new();
+    for frames in timeline.frames().windows(2) {
+      let delta = frames[1].time - frames[0].time;
+      if delta > 0 {
+        *counts.entry(delta).or_default() += 1;
+      }
+    }
+
+    // the interval jumps around by a millisecond because of rounding, so neighbours are counted
+    // together
+    let count = |delta: i32| counts.get(&delta).copied().unwrap_or(0);
+    let peak = counts.keys().copied().max_by_key(|delta| {
+      count(delta - 1) + count(*delta) + count(delta + 1)
+    })?;
+    let (sum, total) =
+      (peak - 1..=peak + 1).fold((0, 0), |(sum, total), delta| {
+        (
+          sum + delta * count(delta) as i32,
+          total + count(delta) as i32,
+        )
+      });
+
+    let frame_interval = sum as f64 / total as f64;
+    Some(TimewarpStats {
+      frame_interval,
+      rate: frame_interval / STABLE_FRAME_INTERVAL,
+      expected_rate: mods.speed_multiplier(),
+    })
+  }
+
+  /// Whether the replay seems to have been played more than `tolerance` (such as 0.1 for 10%)
+  /// slower than its mods allow.
+  pub fn is_slowed_down(&self, tolerance: f64) -> bool {
+    self.rate < self.expected_rate * (1.0 - tolerance)
+  }
+}
+
+/// How consistently keys were pressed, for finding replays where a program did the tapping
+/// (relax).
+///
+/// Relax programs press keys right on time and for the same amount of time on every object, and
+/// don't press keys when there's nothing to hit.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RelaxStats {
+  /// The unstable rate of the hits, in real time. See [`HitErrorStats`].
+  pub unstable_rate: f64,
+
+  /// How long keys were held for each press, in milliseconds of real time.
+  pub hold_time: Option<AxisStats>,
+
+  /// The number of times a key was pressed.
+  pub presses: u32,
+
+  /// The number of key presses that hit an object.
+  pub hits: u32,
+}
+
+impl RelaxStats {
+  /// Simulates the replay on the given beatmap and analyses its key presses.
+  pub fn from_replay(
+    beatmap: &Beatmap,
+    replay: &Replay,
+    actions: &ReplayActionData,
+  ) -> RelaxStats {
+    let result = simulate_replay(beatmap, replay, actions);
+    RelaxStats::from_simulation(&result, actions, replay.mods)
+  }
+
+  /// Analyses the key presses of replay actions that were simulated with the given mods.
+  pub fn from_simulation(
+    result: &SimulationResult,
+    actions: &ReplayActionData,
+    mods: Mods,
+  ) -> RelaxStats {
+    let speed = mods.speed_multiplier();
+    let hit_errors = HitErrorStats::from_simulation(result, mods);
+
+    let mut pressed_at = HashMap::new();
+    let mut hold_times = Vec::new();
+    let mut presses = 0;
+    for event in Timeline::new(actions).key_events() {
+      match event.kind {
+        KeyEventKind::Press => {
+          pressed_at.insert(event.key, event.time);
+          presses += 1;
+        }
+        KeyEventKind::Release => {
+          if let Some(start) = pressed_at.remove(&event.key) {
+            hold_times.push((event.time - start) as f64 / speed);
+          }
+        }
+      }
+    }
+
+    RelaxStats {
+      unstable_rate: hit_errors.unstable_rate,
+      hold_time: AxisStats::from_values(hold_times.iter().copied()),
+      presses,
+      hits: hit_errors.offsets.len() as u32,
+    }
+  }
+
+  /// Whether the key presses are more consistent than a person can manage: an unstable rate
+  /// below 40, key presses that all last within a few milliseconds of each other, and hardly any
+  /// presses that didn't hit anything. Needs at least 50 hits to say anything.
+  pub fn is_suspicious(&self) -> bool {
+    let consistent_holds = self
+      .hold_time
+      .is_some_and(|hold_time| hold_time.std_dev < 3.0);
+    let extra_presses = self.presses.saturating_sub(self.hits);
+    self.hits >= 50
+      && self.unstable_rate < 40.0
+      && consistent_holds
+      && (extra_presses as f64) < self.hits as f64 * 0.02
+  }
+}
+
+/// How the cursor moved onto the objects it hit, for finding replays where a program helped
+/// with aiming (aim assist).
+///
+/// Aim assist typically pulls the cursor onto an object at the last moment, so that the cursor
+/// changes direction and jumps into the circle on the frame right before the key press. People
+/// can do this as well, but rarely.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AimAssistStats {
+  /// The number of circles and slider heads that were hit.
+  pub hits: u32,
+
+  /// The indices of the objects in `Beatmap::hit_objects` that the cursor snapped onto.
+  pub snaps: Vec<usize>,
+}
+
+impl AimAssistStats {
+  /// Simulates the replay on the given beatmap and analyses how the cursor moved onto objects.
+  /// Returns `None` if the replay isn't an osu!standard replay.
+  pub fn from_replay(
+    beatmap: &Beatmap,
+    replay: &Replay,
+    actions: &ReplayActionData,
+  ) -> Option<AimAssistStats> {
+    if replay.mode != Mode::Osu {
+      return None;
+    }
+
+    let sim = OsuSimulator::new(beatmap, replay.mods);
+    let result = sim.simulate(actions);
+    Some(AimAssistStats::from_simulation(&sim, &result, actions))
+  }
+
+  /// Analyses how the cursor moved onto the objects in replay actions that were simulated by the
+  /// given simulator.
+  ///
+  /// A hit counts as a snap if the cursor was outside of the circle on the frame before the key
+  /// press and inside of it on the frame of the key press, the last movement was more than twice
+  /// as far as the two before it together, and it went off in a direction more than 45° away from
+  /// them.
+  pub fn from_simulation(
+    sim: &OsuSimulator,
+    result: &SimulationResult,
+    actions: &ReplayActionData,
+  ) -> AimAssistStats {
+    let timeline = Timeline::new(actions);
+    let frames = timeline.frames();
+    let radius = sim.circle_radius();
+
+    let mut hits = 0;
+    let mut snaps = Vec::new();
+    for judgement in result.judgements.iter() {
+      if !judgement.is_hit_error() {
+        continue;
+      }
+      let center = match sim.object_position(judgement.object_index) {
+        Some(center) => center,
+        None => continue,
+      };
+      hits += 1;
+
+      // the index of the frame of the key press
+      let index = frames
+        .partition_point(|frame| frame.time as f64 <= judgement.time)
+        .saturating_sub(1);
+      if index < 3 {
+        continue;
+      }
+
+      let [before, previous, current] =
+        [index - 3, index - 1, index].map(|i| frames[i].pos);
+      let last_move = current - previous;
+      let approach = previous - before;
+
+      let jumped_in = previous.distance(center) > radius
+        && current.distance(center) <= radius;
+      let sudden = last_move.length() > 2.0 * approach.length();
+      let turned = approach.length() > 0.0
+        && last_move.length() > 0.0
+        && last_move.norm().dot(approach.norm())
+          < std::f64::consts::FRAC_1_SQRT_2;
+      if jumped_in && sudden && turned {
+        snaps.push(judgement.object_index);
+      }
+    }
+
+    AimAssistStats { hits, snaps }
+  }
+
+  /// The share of hits where the cursor snapped onto the object.
+  pub fn snap_ratio(&self) -> f64 {
+    if self.hits == 0 {
+      0.0
+    } else {
+      self.snaps.len() as f64 / self.hits as f64
+    }
+  }
+
+  /// Whether the cursor snapped onto objects more often than people do, which is taken to be on
+  /// more than 5% of at least 50 hits.
+  pub fn is_suspicious(&self) -> bool {
+    self.hits >= 50 && self.snap_ratio() > 0.05
+  }
+}
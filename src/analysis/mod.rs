@@ -0,0 +1,36 @@
+//! Statistics about how a replay was played
+//!
+//! The analyses in this module build on the judgements produced by the simulators in
+//! [`sim`][crate::sim], so they need the [`Beatmap`][crate::beatmap::Beatmap] the replay was
+//! played on. [`ReplaySimilarity`], [`TimewarpStats`] and [`SpinnerStats`] only look at the replay
+//! actions.
+//!
+//! Example
+//! -------
+//!
+//! ```no_run
+//! # use libosu::{analysis::HitErrorStats, beatmap::Beatmap, replay::Replay};
+//! # #[cfg(feature = "replay-data")]
+//! # fn invisible(beatmap: Beatmap, replay: Replay) -> anyhow::Result<()> {
+//! #
+//! let action_data = replay.parse_action_data()?;
+//! let stats = HitErrorStats::from_replay(&beatmap, &replay, &action_data);
+//! println!("{:.2} UR", stats.unstable_rate);
+//!
+//! # Ok(())
+//! # }
+//! ```
+
+mod aim_error;
+mod hit_error;
+mod integrity;
+mod similarity;
+mod spinner;
+
+pub use self::aim_error::{
+  AimDistribution, AimErrorStats, AimOffset, AxisStats,
+};
+pub use self::hit_error::{Histogram, HitErrorStats, HitOffset};
+pub use self::integrity::{AimAssistStats, RelaxStats, TimewarpStats};
+pub use self::similarity::ReplaySimilarity;
+pub use self::spinner::{SpinnerSample, SpinnerStats};
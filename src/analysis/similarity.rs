@@ -0,0 +1,170 @@
+use crate::replay::{ReplayActionData, Timeline};
+
+/// The time between the points at which two cursor paths are compared, in milliseconds.
+const SAMPLE_INTERVAL: i32 = 10;
+
+/// The step used when searching for the best alignment, before refining it to the millisecond.
+const COARSE_STEP: i32 = 5;
+
+/// The height of the playfield, which HardRock flips the cursor across.
+const PLAYFIELD_HEIGHT: f64 = 384.0;
+
+/// How closely the cursor movement of two replays of the same map matches, for finding replays
+/// that were copied from another player.
+///
+/// The replays are lined up by moving the second one in time by up to `max_offset` milliseconds
+/// (see [`compare_with`][Self::compare_with]) and by flipping it vertically, in case HardRock was
+/// added or removed, picking whatever correlates the best. Copied replays usually correlate
+/// almost perfectly, even if some noise was added to them, while two legitimate plays of the same
+/// map rarely go above 0.9.
+///
+/// ```no_run
+/// # use libosu::{analysis::ReplaySimilarity, replay::Replay};
+/// # #[cfg(feature = "replay-data")]
+/// # fn invisible(a: Replay, b: Replay) -> anyhow::Result<()> {
+/// #
+/// let similarity =
+///     ReplaySimilarity::compare(&a.parse_action_data()?, &b.parse_action_data()?);
+/// if let Some(similarity) = similarity {
+///     println!("{:.3} correlation, {:.1}px apart", similarity.correlation, similarity.mean_distance);
+/// }
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplaySimilarity {
+  /// The correlation between the cursor positions of the two replays, averaged over both axes.
+  /// Goes from -1 to 1, where 1 means they moved exactly the same way.
+  pub correlation: f64,
+
+  /// The average distance between the two cursors, in osu!pixels.
+  pub mean_distance: f64,
+
+  /// How many milliseconds later the second replay had to be moved to line up with the first.
+  pub offset: i32,
+
+  /// Whether the second replay lined up best after flipping it vertically.
+  pub mirrored: bool,
+
+  /// The number of points at which the cursors were compared.
+  pub samples: usize,
+}
+
+impl ReplaySimilarity {
+  /// Compares the cursor movement of two replays, allowing them to be up to 50ms apart. Returns
+  /// `None` if they don't overlap in time.
+  pub fn compare(
+    a: &ReplayActionData,
+    b: &ReplayActionData,
+  ) -> Option<ReplaySimilarity> {
+    ReplaySimilarity::compare_with(a, b, 50)
+  }
+
+  /// Compares the cursor movement of two replays, allowing them to be up to `max_offset`
+  /// milliseconds apart. Returns `None` if they don't overlap in time.
+  pub fn compare_with(
+    a: &ReplayActionData,
+    b: &ReplayActionData,
+    max_offset: i32,
+  ) -> Option<ReplaySimilarity> {
+    let a = Timeline::new(a);
+    let b = Timeline::new(b);
+    let max_offset = max_offset.max(0);
+
+    let mut best: Option<ReplaySimilarity> = None;
+    let mut consider = |offset: i32, mirrored: bool| {
+      let similarity = ReplaySimilarity::at_offset(&a, &b, offset, mirrored);
+      if let Some(similarity) = similarity {
+        // `Option::is_none_or` would need Rust 1.82
+        #[allow(clippy::unnecessary_map_or)]
+        let better =
+          best.map_or(true, |best| similarity.correlation > best.correlation);
+        if better {
+          best = Some(similarity);
+        }
+      }
+      best
+    };
+
+    for mirrored in [false, true] {
+      let mut coarse = None;
+      let mut offset = -max_offset;
+      while offset <= max_offset {
+        coarse = consider(offset, mirrored);
+        offset += COARSE_STEP;
+      }
+
+      // refine around the best coarse offset if it came from this orientation
+      if let Some(coarse) = coarse.filter(|best| best.mirrored == mirrored) {
+        let start = (coarse.offset - COARSE_STEP + 1).max(-max_offset);
+        let end = (coarse.offset + COARSE_STEP - 1).min(max_offset);
+        for offset in start..=end {
+          consider(offset, mirrored);
+        }
+      }
+    }
+    best
+  }
+
+  /// Compares `a` to `b` moved `offset` milliseconds later, flipping `b` if `mirrored` is set.
+  fn at_offset(
+    a: &Timeline,
+    b: &Timeline,
+    offset: i32,
+    mirrored: bool,
+  ) -> Option<ReplaySimilarity> {
+    let (a_frames, b_frames) = (a.frames(), b.frames());
+    let start = a_frames.first()?.time.max(b_frames.first()?.time + offset);
+    let end = a_frames.last()?.time.min(b_frames.last()?.time + offset);
+
+    let mut pairs = Vec::new();
+    let mut time = start;
+    while time <= end {
+      let pos_a = a.position_at(time as f64)?;
+      let mut pos_b = b.position_at((time - offset) as f64)?;
+      if mirrored {
+        pos_b.y = PLAYFIELD_HEIGHT - pos_b.y;
+      }
+      pairs.push((pos_a, pos_b));
+      time += SAMPLE_INTERVAL;
+    }
+    if pairs.len() < 2 {
+      return None;
+    }
+
+    let correlation_x = correlation(pairs.iter().map(|(a, b)| (a.x, b.x)));
+    let correlation_y = correlation(pairs.iter().map(|(a, b)| (a.y, b.y)));
+    let mean_distance = pairs.iter().map(|(a, b)| a.distance(*b)).sum::<f64>()
+      / pairs.len() as f64;
+
+    Some(ReplaySimilarity {
+      correlation: (correlation_x + correlation_y) / 2.0,
+      mean_distance,
+      offset,
+      mirrored,
+      samples: pairs.len(),
+    })
+  }
+}
+
+/// The Pearson correlation of pairs of values, or 0 if either side doesn't vary at all.
+fn correlation(pairs: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+  let count = pairs.clone().count() as f64;
+  let (sum_a, sum_b) = pairs
+    .clone()
+    .fold((0.0, 0.0), |(sum_a, sum_b), (a, b)| (sum_a + a, sum_b + b));
+  let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+
+  let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+  for (a, b) in pairs {
+    covariance += (a - mean_a) * (b - mean_b);
+    variance_a += (a - mean_a).powi(2);
+    variance_b += (b - mean_b).powi(2);
+  }
+  if variance_a == 0.0 || variance_b == 0.0 {
+    return 0.0;
+  }
+  covariance / (variance_a * variance_b).sqrt()
+}
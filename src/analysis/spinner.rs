@@ -0,0 +1,173 @@
+use std::f64::consts::TAU;
+
+use crate::beatmap::Difficulty;
+use crate::data::Mods;
+use crate::hitobject::{HitObject, HitObjectKind};
+use crate::replay::{ReplayActionData, Timeline};
+use crate::sim::osu::{
+  required_spinner_rotations, spinner_angle, spinner_result, spinner_rotation,
+  BONUS_SPIN_GAP,
+};
+use crate::sim::{left_pressed, right_pressed, HitResult};
+
+/// The RPM counter of osu!stable shows how far the spinner turned during this many milliseconds.
+const RPM_WINDOW: i32 = 595;
+
+/// How far a spinner was turned at one frame of the replay.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpinnerSample {
+  /// The time of the frame, in milliseconds since the start of the map.
+  pub time: i32,
+
+  /// The number of rotations since the start of the spinner.
+  pub rotations: f64,
+
+  /// The spinning speed in rotations per minute, smoothed the same way as the RPM counter in the
+  /// game.
+  pub rpm: f64,
+}
+
+/// How a spinner was spun in an osu!standard replay.
+///
+/// The rotation is counted the same way as [`OsuSimulator`][crate::sim::OsuSimulator] does: the
+/// cursor turns the spinner around the center of the playfield while a key is held, at up to 477
+/// RPM. Times and speeds are in map time, so with Double Time the spinner has to turn as far in
+/// less real time. Like the simulator, this doesn't support Relax, Autopilot or Spun Out.
+///
+/// ```no_run
+/// # use libosu::{analysis::SpinnerStats, beatmap::Beatmap, replay::Replay};
+/// # #[cfg(feature = "replay-data")]
+/// # fn invisible(beatmap: Beatmap, replay: Replay) -> anyhow::Result<()> {
+/// #
+/// let action_data = replay.parse_action_data()?;
+/// for spinner in beatmap.hit_objects.iter().filter(|ho| ho.kind.is_spinner()) {
+///     let stats = SpinnerStats::new(spinner, &beatmap.difficulty, replay.mods, &action_data)
+///         .unwrap();
+///     println!("{:.0} RPM at most, {} bonus spins", stats.peak_rpm, stats.bonus_spins);
+/// }
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpinnerStats {
+  /// The rotation and speed at every frame during the spinner, in chronological order.
+  pub samples: Vec<SpinnerSample>,
+
+  /// The number of rotations by the end of the spinner.
+  pub rotations: f64,
+
+  /// The number of rotations needed to clear the spinner.
+  pub required_rotations: u32,
+
+  /// The highest smoothed RPM reached.
+  pub peak_rpm: f64,
+
+  /// Whether the spinner was turned enough to be cleared.
+  pub cleared: bool,
+
+  /// The number of whole rotations that gave bonus score. These start two rotations after the
+  /// spinner was cleared.
+  pub bonus_spins: u32,
+
+  /// The judgement of the spinner.
+  pub result: HitResult,
+}
+
+impl SpinnerStats {
+  /// Works out how the given spinner was spun in the given replay actions, played on a map with
+  /// the given difficulty (before mods are applied) and mods. Returns `None` if the hit object
+  /// isn't a spinner.
+  pub fn new(
+    spinner: &HitObject,
+    difficulty: &Difficulty,
+    mods: Mods,
+    actions: &ReplayActionData,
+  ) -> Option<SpinnerStats> {
+    let start_time = spinner.start_time.0;
+    let end_time = match &spinner.kind {
+      HitObjectKind::Spinner(info) => info.end_time.0,
+      _ => return None,
+    };
+    let overall_difficulty = difficulty.apply_mods(mods).overall_difficulty;
+    let required_rotations = required_spinner_rotations(
+      (end_time - start_time) as f64,
+      overall_difficulty,
+    );
+
+    let mut samples = Vec::<SpinnerSample>::new();
+    let mut rotation = 0.0;
+    let mut last_angle = None;
+    let mut last_time = start_time;
+    for frame in Timeline::new(actions).frames() {
+      if frame.time < start_time {
+        continue;
+      }
+
+      let time = frame.time.min(end_time);
+      if left_pressed(&frame.buttons) || right_pressed(&frame.buttons) {
+        let angle = spinner_angle(frame.pos);
+        if let Some(last_angle) = last_angle {
+          rotation +=
+            spinner_rotation(last_angle, angle, (time - last_time) as f64);
+        }
+        last_angle = Some(angle);
+      } else {
+        last_angle = None;
+      }
+      last_time = time;
+
+      let rotations = rotation / TAU;
+      let oldest = samples
+        [samples.partition_point(|sample| sample.time < time - RPM_WINDOW)..]
+        .first()
+        .copied();
+      let rpm = match oldest {
+        Some(oldest) if oldest.time < time => {
+          (rotations - oldest.rotations) / (time - oldest.time) as f64
+            * 60_000.0
+        }
+        _ => 0.0,
+      };
+      samples.push(SpinnerSample {
+        time,
+        rotations,
+        rpm,
+      });
+
+      if frame.time >= end_time {
+        break;
+      }
+    }
+
+    let rotations = rotation / TAU;
+    let cleared = rotations >= required_rotations;
+    let bonus_spins =
+      (rotations.floor() - required_rotations - BONUS_SPIN_GAP as f64).max(0.0)
+        as u32;
+
+    Some(SpinnerStats {
+      peak_rpm: samples.iter().map(|sample| sample.rpm).fold(0.0, f64::max),
+      samples,
+      rotations,
+      required_rotations: required_rotations as u32,
+      cleared,
+      bonus_spins,
+      result: spinner_result(rotations, required_rotations),
+    })
+  }
+
+  /// The smoothed RPM at the given time, taken from the last frame at or before it. Before the
+  /// first frame, this is 0.
+  pub fn rpm_at(&self, time: f64) -> f64 {
+    let index = self
+      .samples
+      .partition_point(|sample| sample.time as f64 <= time);
+    match index.checked_sub(1) {
+      Some(index) => self.samples[index].rpm,
+      None => 0.0,
+    }
+  }
+}
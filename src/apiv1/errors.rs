@@ -5,12 +5,18 @@ pub enum Error {
   #[error("hyper error: {0}")]
   Hyper(#[from] hyper::Error),
 
+  #[error("invalid grade: {0}")]
+  Grade(#[from] crate::score::ParseGradeError),
+
   #[error("invalid uri: {0}")]
   InvalidUri(#[from] http::uri::InvalidUri),
 
   #[error("serde_json error: {0}")]
   Json(#[from] serde_json::Error),
 
+  #[error("integer parse error: {0}")]
+  ParseInt(#[from] std::num::ParseIntError),
+
   #[error("reqwest error: {0}")]
   Reqwest(#[from] reqwest::Error),
 
@@ -1,3 +1,8 @@
+use crate::data::{Grade, HitCounts, Mode, Mods};
+use crate::score::ScoreStatistics;
+
+use super::Result;
+
 /// The approved status of a beatmap.
 #[allow(missing_docs)]
 #[derive(Debug)]
@@ -30,3 +35,33 @@ pub struct UserScore {
   pub date: String,
   pub rank: String,
 }
+
+impl UserScore {
+  /// Parses the judgement counts of this score.
+  pub fn hit_counts(&self) -> Result<HitCounts> {
+    Ok(HitCounts {
+      count_300: self.count_300.parse()?,
+      count_100: self.count_100.parse()?,
+      count_50: self.count_50.parse()?,
+      count_geki: self.count_geki.parse()?,
+      count_katu: self.count_katu.parse()?,
+      count_miss: self.count_miss.parse()?,
+    })
+  }
+
+  /// Parses the mods this score was set with. Mods this library doesn't know about are dropped.
+  pub fn mods(&self) -> Result<Mods> {
+    Ok(Mods::from_bits_truncate(self.enabled_mods.parse()?))
+  }
+
+  /// Parses the grade the API gave this score.
+  pub fn grade(&self) -> Result<Grade> {
+    Ok(self.rank.parse()?)
+  }
+
+  /// Returns the accuracy, grade and mode-specific judgement names of this score. The API
+  /// doesn't say which mode a score was set in, so it has to be the mode that was asked for.
+  pub fn statistics(&self, mode: Mode) -> Result<ScoreStatistics> {
+    Ok(ScoreStatistics::new(mode, self.hit_counts()?, self.mods()?))
+  }
+}
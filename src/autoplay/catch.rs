@@ -0,0 +1,55 @@
+use crate::beatmap::Beatmap;
+use crate::data::Mods;
+use crate::math::Point;
+use crate::replay::{Buttons, TimelineFrame};
+use crate::sim::CatchSimulator;
+
+use super::{Autoplay, Recorder};
+
+/// The catcher moves this many osu!pixels per millisecond without dashing.
+const WALK_SPEED: f64 = 0.5;
+
+/// The catcher starts moving to the first object at most this long before it.
+const LEAD_IN: f64 = 1000.0;
+
+/// Plays osu!catch by moving the catcher to every fruit, droplet and banana as it arrives. The
+/// dash key (M1) is held while moving faster than the catcher can walk.
+///
+/// Objects arriving at the same time as the previous one are assumed to be caught along with it.
+pub(super) fn generate(
+  autoplay: &Autoplay,
+  beatmap: &Beatmap,
+  mods: Mods,
+) -> Vec<TimelineFrame> {
+  let sim = CatchSimulator::new(beatmap, mods);
+  let mut objects = sim.objects().to_vec();
+  objects.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+  let mut recorder = Recorder::default();
+  let mut last: Option<(f64, f64)> = None;
+  for object in objects.iter() {
+    let (from_time, from_x) = match last {
+      Some((time, _)) if object.time.round() <= time.round() => continue,
+      Some(last) => last,
+      None => {
+        let time = (object.time - LEAD_IN).max(0.0);
+        recorder.move_to(time, Point::new(256.0, 0.0));
+        (time, 256.0)
+      }
+    };
+
+    let duration = object.time - from_time;
+    if (object.x - from_x).abs() > WALK_SPEED * duration {
+      recorder.press(Buttons::M1.bits(), from_time, object.time);
+    }
+    autoplay.travel(
+      &mut recorder,
+      (from_time, Point::new(from_x, 0.0)),
+      (object.time, Point::new(object.x, 0.0)),
+    );
+    recorder.move_to(object.time, Point::new(object.x, 0.0));
+    last = Some((object.time, object.x));
+  }
+
+  recorder.frames()
+}
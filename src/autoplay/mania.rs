@@ -0,0 +1,35 @@
+use crate::beatmap::Beatmap;
+use crate::data::Mods;
+use crate::math::Point;
+use crate::replay::{Buttons, TimelineFrame};
+use crate::sim::ManiaSimulator;
+
+use super::{Autoplay, Recorder};
+
+/// Plays osu!mania by pressing every note right on time and releasing hold notes right as they
+/// end. The pressed columns are stored in the x coordinate of the frames, like osu!mania replays
+/// do.
+pub(super) fn generate(
+  autoplay: &Autoplay,
+  beatmap: &Beatmap,
+  mods: Mods,
+) -> Vec<TimelineFrame> {
+  let sim = ManiaSimulator::new(beatmap, mods);
+  let mut recorder = Recorder::default();
+  for note in sim.notes.iter() {
+    let end_time = note
+      .end_time
+      .unwrap_or(note.start_time + autoplay.key_up_delay);
+    recorder.press(1 << note.column, note.start_time, end_time);
+  }
+
+  recorder
+    .frames()
+    .into_iter()
+    .map(|frame| TimelineFrame {
+      pos: Point::new(frame.buttons.bits() as f64, 0.0),
+      buttons: Buttons::empty(),
+      ..frame
+    })
+    .collect()
+}
@@ -0,0 +1,279 @@
+//! Generating replays that play beatmaps perfectly
+//!
+//! [`Autoplay`] works out replay actions that get the best judgement on every hit object of a
+//! map, like the Autoplay mod does. Together with
+//! [`Replay::update_action_data`][crate::replay::Replay::update_action_data], this can be used
+//! to make reference replays.
+//!
+//! Example
+//! -------
+//!
+//! ```no_run
+//! # use libosu::{autoplay::{Autoplay, Easing}, beatmap::Beatmap, data::Mods, replay::Replay};
+//! # #[cfg(feature = "replay-data")]
+//! # fn invisible(beatmap: Beatmap, mut replay: Replay) -> anyhow::Result<()> {
+//! #
+//! let autoplay = Autoplay {
+//!     easing: Easing::InOutSine,
+//!     ..Autoplay::default()
+//! };
+//! let action_data = autoplay.generate(&beatmap, Mods::HardRock);
+//! replay.update_action_data(&action_data)?;
+//!
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The objects are laid out the same way the simulators in [`sim`][crate::sim] do it, so the
+//! same limitations apply: maps converted from osu!standard to other modes aren't supported.
+
+mod catch;
+mod mania;
+mod osu;
+mod taiko;
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::beatmap::Beatmap;
+use crate::data::{Mode, Mods};
+use crate::math::Point;
+use crate::replay::{Buttons, ReplayActionData, Timeline, TimelineFrame};
+
+/// Settings for generating replays that play a beatmap perfectly.
+///
+/// Cursor movement and spinning are sampled at `frame_interval`, and additional frames are
+/// added whenever a key is pressed or released.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Autoplay {
+  /// How the cursor (or the catcher in osu!catch) moves from one object to the next
+  pub easing: Easing,
+
+  /// How fast spinners are spun, in rotations per minute. Spinning faster than 477 RPM doesn't
+  /// count for anything.
+  pub spinner_rpm: f64,
+
+  /// The time between frames while the cursor is moving, in milliseconds
+  pub frame_interval: f64,
+
+  /// How long keys are held when hitting objects that don't need to be held, in milliseconds.
+  /// Keys are always released before they're needed again.
+  pub key_up_delay: f64,
+}
+
+impl Default for Autoplay {
+  fn default() -> Self {
+    Autoplay {
+      easing: Easing::OutQuad,
+      spinner_rpm: 477.0,
+      frame_interval: 1000.0 / 60.0,
+      key_up_delay: 50.0,
+    }
+  }
+}
+
+impl Autoplay {
+  /// Generates replay actions that play the given beatmap with the given mods perfectly, in the
+  /// game mode of the beatmap.
+  pub fn generate(&self, beatmap: &Beatmap, mods: Mods) -> ReplayActionData {
+    let frames = match beatmap.mode {
+      Mode::Osu => osu::generate(self, beatmap, mods),
+      Mode::Taiko => taiko::generate(self, beatmap, mods),
+      Mode::Catch => catch::generate(self, beatmap, mods),
+      Mode::Mania => mania::generate(self, beatmap, mods),
+    };
+    Timeline::from_frames(frames).to_action_data()
+  }
+
+  /// Moves the cursor from one position to another using the easing of these settings, adding
+  /// frames strictly between the two times.
+  fn travel(
+    &self,
+    recorder: &mut Recorder,
+    (from_time, from): (f64, Point<f64>),
+    (to_time, to): (f64, Point<f64>),
+  ) {
+    let interval = self.frame_interval.max(1.0);
+    let mut time = from_time + interval;
+    while time < to_time {
+      let progress = (time - from_time) / (to_time - from_time);
+      let pos = from + (to - from) * self.easing.apply(progress);
+      recorder.move_to(time, pos);
+      time += interval;
+    }
+  }
+
+  /// The whole milliseconds at which frames are added while following something from `start` to
+  /// `end`, including both ends.
+  fn sample_times(&self, start: f64, end: f64) -> Vec<f64> {
+    let interval = self.frame_interval.max(1.0);
+    let mut times = vec![start.round()];
+    let mut time = start + interval;
+    while time < end {
+      times.push(time.round());
+      time += interval;
+    }
+    if end > start {
+      times.push(end.ceil());
+    }
+    times
+  }
+}
+
+/// How something moves from one point to another over time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Easing {
+  /// Constant speed
+  Linear,
+
+  /// Starts slow and speeds up
+  InQuad,
+
+  /// Starts fast and slows down
+  OutQuad,
+
+  /// Speeds up during the first half and slows down during the second
+  InOutQuad,
+
+  /// Like `InQuad`, but more pronounced
+  InCubic,
+
+  /// Like `OutQuad`, but more pronounced
+  OutCubic,
+
+  /// Like `InOutQuad`, but more pronounced
+  InOutCubic,
+
+  /// Like `InOutQuad`, but following a sine wave
+  InOutSine,
+}
+
+impl Easing {
+  /// Maps the progress of a movement (from 0 to 1) to how far along the distance it is (also from
+  /// 0 to 1).
+  pub fn apply(self, progress: f64) -> f64 {
+    let t = progress.clamp(0.0, 1.0);
+    match self {
+      Easing::Linear => t,
+      Easing::InQuad => t * t,
+      Easing::OutQuad => t * (2.0 - t),
+      Easing::InOutQuad => {
+        if t < 0.5 {
+          2.0 * t * t
+        } else {
+          1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+        }
+      }
+      Easing::InCubic => t * t * t,
+      Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+      Easing::InOutCubic => {
+        if t < 0.5 {
+          4.0 * t * t * t
+        } else {
+          1.0 - 4.0 * (1.0 - t).powi(3)
+        }
+      }
+      Easing::InOutSine => (1.0 - (PI * t).cos()) / 2.0,
+    }
+  }
+}
+
+/// A key (or set of keys that are always pressed together) being held down.
+#[derive(Clone, Debug)]
+struct Press {
+  keys: u32,
+  start: i32,
+  end: i32,
+}
+
+/// Collects cursor positions and key presses, and turns them into frames once everything has
+/// been added.
+#[derive(Clone, Debug, Default)]
+struct Recorder {
+  cursor: Vec<TimelineFrame>,
+  presses: Vec<Press>,
+}
+
+impl Recorder {
+  /// Places the cursor at the given position at the given time.
+  fn move_to(&mut self, time: f64, pos: Point<f64>) {
+    self.cursor.push(TimelineFrame {
+      time: time.round() as i32,
+      pos,
+      buttons: Buttons::empty(),
+    });
+  }
+
+  /// Holds the given keys from `start` until `end`, or until right before the same keys are
+  /// pressed again, whichever comes first.
+  fn press(&mut self, keys: u32, start: f64, end: f64) {
+    let start = start.round() as i32;
+    self.presses.push(Press {
+      keys,
+      start,
+      end: (end.round() as i32).max(start + 1),
+    });
+  }
+
+  /// Turns everything that was recorded into frames. The cursor position is interpolated for
+  /// frames that were only added for key presses, and is at the origin if the cursor was never
+  /// placed. The pressed keys are stored in the buttons of the frames.
+  fn frames(mut self) -> Vec<TimelineFrame> {
+    // keys have to be released for at least a millisecond to be pressed again
+    self.presses.sort_by_key(|press| press.start);
+    let mut next_start = HashMap::new();
+    for press in self.presses.iter_mut().rev() {
+      if let Some(next) = next_start.insert(press.keys, press.start) {
+        if next - 1 > press.start {
+          press.end = press.end.min(next - 1);
+        }
+      }
+    }
+
+    let mut events = Vec::with_capacity(self.presses.len() * 2);
+    for press in self.presses.iter() {
+      events.push((press.start, press.keys, 1));
+      events.push((press.end, press.keys, -1));
+    }
+    events.sort_by_key(|(time, _, _)| *time);
+
+    let mut times = self
+      .cursor
+      .iter()
+      .map(|frame| frame.time)
+      .chain(events.iter().map(|(time, _, _)| *time))
+      .collect::<Vec<_>>();
+    times.sort_unstable();
+    times.dedup();
+
+    let cursor = Timeline::from_frames(self.cursor);
+    let mut held = HashMap::<u32, i32>::new();
+    let mut next_event = 0;
+    times
+      .into_iter()
+      .map(|time| {
+        while let Some((event_time, keys, change)) = events.get(next_event) {
+          if *event_time > time {
+            break;
+          }
+          *held.entry(*keys).or_default() += change;
+          next_event += 1;
+        }
+
+        let buttons = held
+          .iter()
+          .filter(|(_, count)| **count > 0)
+          .fold(0, |buttons, (keys, _)| buttons | keys);
+        TimelineFrame {
+          time,
+          pos: cursor
+            .position_at(time as f64)
+            .unwrap_or_else(|| Point::new(0.0, 0.0)),
+          buttons: Buttons::from_bits_retain(buttons),
+        }
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,80 @@
+use std::f64::consts::TAU;
+
+use crate::beatmap::Beatmap;
+use crate::data::Mods;
+use crate::math::Point;
+use crate::replay::{Buttons, TimelineFrame};
+use crate::sim::osu::{ObjectKind, SPINNER_CENTER};
+use crate::sim::OsuSimulator;
+
+use super::{Autoplay, Recorder};
+
+/// The distance from the center of the playfield at which spinners are spun.
+const SPINNER_RADIUS: f64 = 50.0;
+
+/// The cursor starts moving to the first object at most this long before it.
+const LEAD_IN: f64 = 1000.0;
+
+/// Plays osu!standard by hitting every circle and slider head right on time, following sliders
+/// along their path and spinning spinners around the center of the playfield. Each object is hit
+/// with the other key than the previous one.
+pub(super) fn generate(
+  autoplay: &Autoplay,
+  beatmap: &Beatmap,
+  mods: Mods,
+) -> Vec<TimelineFrame> {
+  let sim = OsuSimulator::new(beatmap, mods);
+  let mut recorder = Recorder::default();
+  let keys = [Buttons::K1 | Buttons::M1, Buttons::K2 | Buttons::M2];
+  let spin_speed = autoplay.spinner_rpm / 60_000.0 * TAU;
+
+  let mut last: Option<(f64, Point<f64>)> = None;
+  for (index, object) in sim.objects.iter().enumerate() {
+    let start_time = object.start_time;
+    let keys = keys[index % 2].bits();
+
+    let (end_time, end_pos) = match &object.kind {
+      ObjectKind::Circle => {
+        recorder.press(keys, start_time, start_time + autoplay.key_up_delay);
+        (start_time, object.pos)
+      }
+      ObjectKind::Slider(slider) => {
+        let end_time = slider.timing.end_time();
+        for time in autoplay.sample_times(start_time, end_time) {
+          recorder.move_to(time, slider.ball_position(time.min(end_time)));
+        }
+        recorder.press(keys, start_time, end_time + autoplay.key_up_delay);
+        (end_time.ceil(), slider.ball_position(end_time))
+      }
+      ObjectKind::Spinner { end_time, .. } => {
+        let spinner_position = |time: f64| {
+          let angle = (time - start_time) * spin_speed - TAU / 4.0;
+          SPINNER_CENTER + Point::new(angle.cos(), angle.sin()) * SPINNER_RADIUS
+        };
+        for time in autoplay.sample_times(start_time, *end_time) {
+          recorder.move_to(time, spinner_position(time));
+        }
+        recorder.press(keys, start_time, end_time + autoplay.key_up_delay);
+        (end_time.ceil(), spinner_position(end_time.ceil()))
+      }
+    };
+
+    let start_pos = match &object.kind {
+      ObjectKind::Spinner { .. } => {
+        SPINNER_CENTER + Point::new(0.0, -SPINNER_RADIUS)
+      }
+      _ => object.pos,
+    };
+    let from = last.unwrap_or_else(|| {
+      let time = (start_time - LEAD_IN).max(0.0);
+      recorder.move_to(time, SPINNER_CENTER);
+      (time, SPINNER_CENTER)
+    });
+    autoplay.travel(&mut recorder, from, (start_time, start_pos));
+    recorder.move_to(start_time, start_pos);
+
+    last = Some((end_time, end_pos));
+  }
+
+  recorder.frames()
+}
@@ -0,0 +1,96 @@
+use crate::beatmap::Beatmap;
+use crate::data::Mods;
+use crate::replay::{Buttons, TimelineFrame};
+use crate::sim::taiko::ObjectKind;
+use crate::sim::TaikoSimulator;
+
+use super::{Autoplay, Recorder};
+
+/// Alternates between the two keys of each colour.
+#[derive(Default)]
+struct Hands {
+  don: bool,
+  kat: bool,
+}
+
+impl Hands {
+  fn next(&mut self, kat: bool) -> Buttons {
+    let (hand, keys) = if kat {
+      (&mut self.kat, [Buttons::K2, Buttons::M2])
+    } else {
+      (&mut self.don, [Buttons::K1, Buttons::M1])
+    };
+    let key = keys[*hand as usize];
+    *hand = !*hand;
+    key
+  }
+}
+
+/// Plays osu!taiko by hitting every note right on time, alternating between the two keys of its
+/// colour. Big notes are hit with both keys, every drumroll tick is hit, and swells are hit
+/// alternating between don and kat, spread out evenly over their duration.
+///
+/// Drumroll ticks and swell hits that are close enough to the next note to count as a miss on it
+/// are skipped.
+pub(super) fn generate(
+  autoplay: &Autoplay,
+  beatmap: &Beatmap,
+  mods: Mods,
+) -> Vec<TimelineFrame> {
+  let sim = TaikoSimulator::new(beatmap, mods);
+  let mut recorder = Recorder::default();
+  let mut hands = Hands::default();
+  let key_up = autoplay.key_up_delay;
+
+  // whether a key press at the given time would be judged on the note after the given object
+  let near_next_note = |index: usize, time: f64| {
+    sim.objects[index + 1..]
+      .iter()
+      .find(|object| matches!(object.kind, ObjectKind::Note { .. }))
+      .is_some_and(|note| {
+        sim.windows.result_for(time - note.start_time).is_some()
+      })
+  };
+
+  for (index, object) in sim.objects.iter().enumerate() {
+    let start_time = object.start_time;
+    match &object.kind {
+      ObjectKind::Note { kat, big } => {
+        let keys = if *big {
+          if *kat {
+            Buttons::K2 | Buttons::M2
+          } else {
+            Buttons::K1 | Buttons::M1
+          }
+        } else {
+          hands.next(*kat)
+        };
+        for key in keys.iter() {
+          recorder.press(key.bits(), start_time, start_time + key_up);
+        }
+      }
+      ObjectKind::DrumRoll { ticks, .. } => {
+        for tick in ticks.iter() {
+          if !near_next_note(index, *tick) {
+            recorder.press(hands.next(false).bits(), *tick, tick + key_up);
+          }
+        }
+      }
+      ObjectKind::Swell {
+        end_time,
+        required_hits,
+      } => {
+        let spacing = (end_time - start_time) / (*required_hits + 1) as f64;
+        for hit in 1..=*required_hits {
+          let time = start_time + spacing * hit as f64;
+          if !near_next_note(index, time) {
+            let key = hands.next(hit % 2 == 0);
+            recorder.press(key.bits(), time, time + key_up);
+          }
+        }
+      }
+    }
+  }
+
+  recorder.frames()
+}
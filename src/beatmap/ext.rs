@@ -1,5 +1,7 @@
 use crate::beatmap::Beatmap;
-use crate::hitobject::{HitObject, HitObjectKind, SpinnerInfo};
+use crate::hitobject::{HitObject, HitObjectKind, HoldNoteInfo, SpinnerInfo};
+use crate::math::Point;
+use crate::spline::Spline;
 use crate::timing::{
   InheritedTimingInfo, Millis, TimingPoint, TimingPointKind,
   UninheritedTimingInfo,
@@ -18,8 +20,10 @@ impl Beatmap {
     let mut sv = 0.0;
     for (i, (obj, tp)) in self.double_iter().enumerate() {
       let sl = match &obj.kind {
-        // trivial case of circle or spinner
-        HitObjectKind::Circle | HitObjectKind::Spinner(_) => {
+        // trivial case of circle, spinner or hold note
+        HitObjectKind::Circle
+        | HitObjectKind::Spinner(_)
+        | HitObjectKind::HoldNote(_) => {
           res += 1;
           continue;
         }
@@ -60,7 +64,8 @@ impl Beatmap {
         let duration = self.get_slider_duration(ho)?;
         Some(ho.start_time.as_seconds() + duration)
       }
-      HitObjectKind::Spinner(SpinnerInfo { end_time }) => {
+      HitObjectKind::Spinner(SpinnerInfo { end_time })
+      | HitObjectKind::HoldNote(HoldNoteInfo { end_time }) => {
         Some(end_time.as_seconds())
       }
     }
@@ -86,6 +91,198 @@ impl Beatmap {
     Some(duration)
   }
 
+  /// Computes the timing information of a slider, such as how fast it moves and where its ticks
+  /// are.
+  ///
+  /// Returns `None` if the hit object isn't a slider, or if there is no timing point to derive
+  /// the beat length from.
+  pub fn slider_timing(&self, ho: &HitObject) -> Option<SliderTiming> {
+    let info = match &ho.kind {
+      HitObjectKind::Slider(info) => info,
+      _ => return None,
+    };
+
+    let beat_length = 60_000.0 / self.get_bpm_at_time(ho.start_time)?;
+    let slider_velocity = self.get_slider_velocity_at_time(ho.start_time);
+    let scoring_distance = 100.0 * self.difficulty.slider_multiplier;
+    let velocity = scoring_distance * slider_velocity / beat_length;
+
+    // maps older than v8 don't scale the tick distance along with the slider velocity
+    let tick_distance = if self.version < 8 {
+      scoring_distance / self.difficulty.slider_tick_rate
+    } else {
+      scoring_distance * slider_velocity / self.difficulty.slider_tick_rate
+    };
+
+    Some(SliderTiming {
+      start_time: ho.start_time.0 as f64,
+      velocity,
+      span_count: info.num_repeats.max(1),
+      pixel_length: info.pixel_length,
+      tick_distance,
+    })
+  }
+
+  /// Computes the stack height of every hit object, in the same order as `hit_objects`.
+  ///
+  /// Objects that appear at nearly the same place within a short amount of time are drawn
+  /// slightly offset from each other so they don't completely cover each other. This uses the
+  /// same algorithms that the game uses, which differ between beatmaps older than version 6 and
+  /// the ones after them.
+  pub fn stack_heights(&self) -> Vec<i32> {
+    const STACK_DISTANCE: f64 = 3.0;
+
+    let objects = &self.hit_objects;
+    let mut heights = vec![0; objects.len()];
+    let threshold =
+      self.difficulty.approach_preempt().0 as f64 * self.stack_leniency;
+
+    let positions = objects
+      .iter()
+      .map(|ho| ho.pos.to_float::<f64>().expect("f64 converts to float"))
+      .collect::<Vec<_>>();
+    let end_positions = objects
+      .iter()
+      .zip(positions.iter())
+      .map(|(ho, pos)| match ho.kind {
+        HitObjectKind::Slider(_) => ho.end_pos(),
+        _ => *pos,
+      })
+      .collect::<Vec<_>>();
+    let start_times = objects
+      .iter()
+      .map(|ho| ho.start_time.0 as f64)
+      .collect::<Vec<_>>();
+    let end_times = objects
+      .iter()
+      .zip(start_times.iter())
+      .map(|(ho, start)| {
+        self
+          .get_hitobject_end_time(ho)
+          .map(|end| end * 1000.0)
+          .unwrap_or(*start)
+      })
+      .collect::<Vec<_>>();
+
+    if self.version < 6 {
+      // older maps stack circles against the end of the slider path, even when the slider ends
+      // back at its head
+      let path_ends = objects
+        .iter()
+        .zip(positions.iter())
+        .map(|(ho, pos)| match &ho.kind {
+          HitObjectKind::Slider(info) => {
+            let mut control_points = vec![ho.pos];
+            control_points.extend(&info.control_points);
+            Spline::from_control(
+              info.kind,
+              &control_points,
+              Some(info.pixel_length),
+            )
+            .end_point()
+          }
+          _ => *pos,
+        })
+        .collect::<Vec<_>>();
+
+      for i in 0..objects.len() {
+        if heights[i] != 0 && !objects[i].kind.is_slider() {
+          continue;
+        }
+
+        let mut end_time = end_times[i];
+        let mut slider_stack = 0;
+        for j in i + 1..objects.len() {
+          if start_times[j] - threshold > end_time {
+            break;
+          }
+
+          if positions[j].distance(positions[i]) < STACK_DISTANCE {
+            heights[i] += 1;
+            end_time = end_times[j];
+          } else if positions[j].distance(path_ends[i]) < STACK_DISTANCE {
+            // circles near the end of a slider are pushed the other way
+            slider_stack += 1;
+            heights[j] -= slider_stack;
+            end_time = end_times[j];
+          }
+        }
+      }
+      return heights;
+    }
+
+    for i in (1..objects.len()).rev() {
+      if heights[i] != 0 || objects[i].kind.is_spinner() {
+        continue;
+      }
+
+      let mut current = i;
+      let mut n = i;
+      if objects[i].kind.is_circle() {
+        while n > 0 {
+          n -= 1;
+          if objects[n].kind.is_spinner() {
+            continue;
+          }
+          if start_times[current] - end_times[n] > threshold {
+            break;
+          }
+
+          // a slider ending close to this circle pushes everything after it the other way
+          if objects[n].kind.is_slider()
+            && end_positions[n].distance(positions[current]) < STACK_DISTANCE
+          {
+            let offset = heights[current] - heights[n] + 1;
+            for j in n + 1..=i {
+              if end_positions[n].distance(positions[j]) < STACK_DISTANCE {
+                heights[j] -= offset;
+              }
+            }
+            break;
+          }
+
+          if positions[n].distance(positions[current]) < STACK_DISTANCE {
+            heights[n] = heights[current] + 1;
+            current = n;
+          }
+        }
+      } else if objects[i].kind.is_slider() {
+        while n > 0 {
+          n -= 1;
+          if objects[n].kind.is_spinner() {
+            continue;
+          }
+          if start_times[current] - start_times[n] > threshold {
+            break;
+          }
+
+          if end_positions[n].distance(positions[current]) < STACK_DISTANCE {
+            heights[n] = heights[current] + 1;
+            current = n;
+          }
+        }
+      }
+    }
+
+    heights
+  }
+
+  /// Computes the position of every hit object after stacking has been applied, in the same order
+  /// as `hit_objects`. See [`Beatmap::stack_heights`] for more info.
+  pub fn stacked_positions(&self) -> Vec<Point<f64>> {
+    let stack_offset = self.difficulty.circle_size_osupx() as f64 / 10.0;
+    self
+      .stack_heights()
+      .into_iter()
+      .zip(self.hit_objects.iter())
+      .map(|(height, ho)| {
+        let offset = height as f64 * stack_offset;
+        ho.pos.to_float::<f64>().expect("f64 converts to float")
+          - Point::new(offset, offset)
+      })
+      .collect()
+  }
+
   /// Returns the slider velocity at the given time
   pub fn get_slider_velocity_at_time(&self, time: Millis) -> f64 {
     // TODO: replace this with binary search
@@ -187,3 +384,135 @@ impl<'a> Iterator for DoubleIter<'a> {
     Some((ho, tp))
   }
 }
+
+/// Timing information about a single slider. See [`Beatmap::slider_timing`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SliderTiming {
+  /// The time (in milliseconds) at which the slider starts.
+  pub start_time: f64,
+
+  /// How fast the slider ball moves, in osu!pixels per millisecond.
+  pub velocity: f64,
+
+  /// The number of times the slider ball travels along the slider body.
+  pub span_count: u32,
+
+  /// The length of the slider body in osu!pixels.
+  pub pixel_length: f64,
+
+  /// The distance between two slider ticks in osu!pixels.
+  pub tick_distance: f64,
+}
+
+/// The kind of a [`SliderEvent`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliderEventKind {
+  /// A slider tick somewhere along the slider body
+  Tick,
+
+  /// The slider ball reached an end of the body and turns around
+  Repeat,
+
+  /// The end of the slider
+  Tail,
+}
+
+/// A point in time during a slider where the player's tracking is checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SliderEvent {
+  /// What kind of event this is.
+  pub kind: SliderEventKind,
+
+  /// The time (in milliseconds) at which this event happens.
+  pub time: f64,
+
+  /// The index of the span this event belongs to.
+  pub span: u32,
+
+  /// How far along the slider body this event is located, from 0 (head) to 1 (end of the body).
+  pub progress: f64,
+}
+
+impl SliderTiming {
+  /// How long (in milliseconds) it takes the slider ball to travel along the body once.
+  pub fn span_duration(&self) -> f64 {
+    self.pixel_length / self.velocity
+  }
+
+  /// The total duration (in milliseconds) of the slider, including repeats.
+  pub fn duration(&self) -> f64 {
+    self.span_duration() * self.span_count as f64
+  }
+
+  /// The time (in milliseconds) at which the slider ends.
+  pub fn end_time(&self) -> f64 {
+    self.start_time + self.duration()
+  }
+
+  /// Returns how far along the slider body the slider ball is at the given time, from 0 (head) to
+  /// 1 (end of the body).
+  pub fn progress_at(&self, time: f64) -> f64 {
+    let spans = ((time - self.start_time) / self.span_duration())
+      .clamp(0.0, self.span_count as f64);
+    let span = (spans.floor() as u32).min(self.span_count - 1);
+    let progress = spans - span as f64;
+    if span % 2 == 1 {
+      1.0 - progress
+    } else {
+      progress
+    }
+  }
+
+  /// Generates the ticks, repeats and tail of this slider, in chronological order.
+  pub fn events(&self) -> Vec<SliderEvent> {
+    let mut events = Vec::new();
+    let span_duration = self.span_duration();
+    let tick_distance = self.tick_distance.min(self.pixel_length);
+
+    // ticks that would be too close to the end of a span are skipped
+    let min_distance_from_end = self.velocity * 10.0;
+
+    for span in 0..self.span_count {
+      let span_start = self.start_time + span as f64 * span_duration;
+      let reversed = span % 2 == 1;
+
+      let mut ticks = Vec::new();
+      if tick_distance > 0.0 {
+        let mut distance = tick_distance;
+        while distance < self.pixel_length - min_distance_from_end {
+          let progress = distance / self.pixel_length;
+          let time_progress = if reversed { 1.0 - progress } else { progress };
+          ticks.push(SliderEvent {
+            kind: SliderEventKind::Tick,
+            time: span_start + time_progress * span_duration,
+            span,
+            progress,
+          });
+          distance += tick_distance;
+        }
+      }
+      if reversed {
+        ticks.reverse();
+      }
+      events.extend(ticks);
+
+      if span < self.span_count - 1 {
+        events.push(SliderEvent {
+          kind: SliderEventKind::Repeat,
+          time: span_start + span_duration,
+          span,
+          progress: if reversed { 0.0 } else { 1.0 },
+        });
+      }
+    }
+
+    events.push(SliderEvent {
+      kind: SliderEventKind::Tail,
+      time: self.end_time(),
+      span: self.span_count - 1,
+      progress: (self.span_count % 2) as f64,
+    });
+
+    events
+  }
+}
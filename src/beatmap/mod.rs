@@ -6,13 +6,13 @@ mod format;
 pub mod pp_calc;
 
 use crate::color::Color;
-use crate::data::{GridSize, Mode};
+use crate::data::{GridSize, Mode, Mods};
 use crate::events::Event;
 use crate::hitobject::{HitObject, HitObjectKind};
 use crate::hitsounds::SampleSet;
 use crate::timing::{Millis, TimingPoint};
 
-pub use self::ext::DoubleIter;
+pub use self::ext::{DoubleIter, SliderEvent, SliderEventKind, SliderTiming};
 pub use self::format::*;
 
 /// Difficulty settings defined by the map.
@@ -45,6 +45,28 @@ pub struct Difficulty {
 }
 
 impl Difficulty {
+  /// Returns a copy of these settings with the effects of Hard Rock or Easy applied.
+  ///
+  /// Hard Rock multiplies the circle size by 1.3 and every other setting by 1.4, and Easy halves
+  /// all of them. Rate changing mods are not applied, since they don't change any values measured
+  /// in map time.
+  pub fn apply_mods(&self, mods: Mods) -> Difficulty {
+    let mut difficulty = self.clone();
+    if mods.contains(Mods::HardRock) {
+      difficulty.circle_size = (difficulty.circle_size * 1.3).min(10.0);
+      difficulty.approach_rate = (difficulty.approach_rate * 1.4).min(10.0);
+      difficulty.overall_difficulty =
+        (difficulty.overall_difficulty * 1.4).min(10.0);
+      difficulty.hp_drain_rate = (difficulty.hp_drain_rate * 1.4).min(10.0);
+    } else if mods.contains(Mods::Easy) {
+      difficulty.circle_size *= 0.5;
+      difficulty.approach_rate *= 0.5;
+      difficulty.overall_difficulty *= 0.5;
+      difficulty.hp_drain_rate *= 0.5;
+    }
+    difficulty
+  }
+
   /// Calculates the size of a circle in OsuPixels, which is how big the circle appears on a
   /// 640x480 screen.
   ///
@@ -96,6 +118,21 @@ impl Difficulty {
   }
 }
 
+/// Maps a difficulty setting onto a range of values, the way the game does for most values
+/// derived from the difficulty settings.
+///
+/// A setting of 0 maps to `min`, 5 maps to `mid` and 10 maps to `max`, interpolating linearly in
+/// between.
+pub fn difficulty_range(value: f64, min: f64, mid: f64, max: f64) -> f64 {
+  if value > 5.0 {
+    mid + (max - mid) * (value - 5.0) / 5.0
+  } else if value < 5.0 {
+    mid - (mid - min) * (5.0 - value) / 5.0
+  } else {
+    mid
+  }
+}
+
 /// Represents a single beatmap.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
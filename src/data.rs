@@ -44,7 +44,7 @@ bitflags! {
     /// Mod listing with their respective bitwise representation.
     ///
     /// This list is ripped directly from the [osu! wiki](https://github.com/ppy/osu-api/wiki).
-    #[derive(Default, Clone, Debug, PartialEq, Eq)]
+    #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
     #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct Mods: u32 {
         /// No selected mods
@@ -163,6 +163,24 @@ bitflags! {
 }
 
 impl Mods {
+    /// Returns the factor by which these mods change the playback rate of the map.
+    ///
+    /// ```
+    /// # use libosu::prelude::Mods;
+    /// assert_eq!(Mods::None.speed_multiplier(), 1.0);
+    /// assert_eq!((Mods::DoubleTime | Mods::Nightcore).speed_multiplier(), 1.5);
+    /// assert_eq!(Mods::HalfTime.speed_multiplier(), 0.75);
+    /// ```
+    pub fn speed_multiplier(&self) -> f64 {
+        if self.intersects(Mods::DoubleTime | Mods::Nightcore) {
+            1.5
+        } else if self.contains(Mods::HalfTime) {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
     /// Attempts to parse mods from a string, delimiter is what goes between mods
     ///
     /// ```
@@ -278,14 +296,17 @@ pub enum RankedStatus {
 }
 
 /// Rank grades
+///
+/// The values are the ones osu!stable uses, where the silver grades come first.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum Grade {
-    SS,
-    SH,
-    SSH,
-    S,
+    SS = 2,
+    SH = 1,
+    /// Silver SS, given with Hidden or Flashlight
+    SSH = 0,
+    S = 3,
     A,
     B,
     C,
@@ -293,3 +314,29 @@ pub enum Grade {
     F,
     None,
 }
+
+/// The number of each kind of judgement in a score.
+///
+/// The fields mirror the ones stored in replays and score listings, so their meaning depends on
+/// the game mode of the score in the same way.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HitCounts {
+    /// The number of 300s
+    pub count_300: u16,
+
+    /// The number of 100s, or 150s in taiko
+    pub count_100: u16,
+
+    /// The number of 50s, or small fruit in Catch the Beat
+    pub count_50: u16,
+
+    /// The number of gekis in standard, or max 300s in Mania
+    pub count_geki: u16,
+
+    /// The number of katus in standard, or 200s in Mania
+    pub count_katu: u16,
+
+    /// The number of misses
+    pub count_miss: u16,
+}
@@ -56,21 +56,27 @@ pub trait ReadBytesOsu: Read {
   /// The first byte indicates whether a string is there (0x0B) or not (0x00). Then the length is
   /// encoded as a ULEB128 number, and then finally the string itself is encoded as UTF-8
   fn read_uleb128_string(&mut self) -> Result<String> {
+    Ok(self.read_uleb128_optional_string()?.unwrap_or_default())
+  }
+
+  /// Read a string from the reader like [`read_uleb128_string`][Self::read_uleb128_string], but
+  /// return `None` if the string isn't there, so that it can be told apart from an empty string
+  fn read_uleb128_optional_string(&mut self) -> Result<Option<String>> {
     match self.read_u8()? {
       // string isn't there
-      0x0 => Ok(String::new()),
+      0x0 => Ok(None),
 
       // read string normally
       0xb => {
         let len = self.read_uleb128()?;
         if len == 0 {
-          return Ok(String::new());
+          return Ok(Some(String::new()));
         }
 
         let mut buf = vec![0; len as usize];
         self.read_exact(&mut buf)?;
         let string = String::from_utf8(buf)?;
-        Ok(string)
+        Ok(Some(string))
       }
 
       // error
@@ -85,7 +91,7 @@ impl<R: Read + ?Sized> ReadBytesOsu for R {}
 pub trait WriteBytesOsu: Write {
   /// Writes a ULEB128 value into the writer
   fn write_uleb128(&mut self, mut n: u128) -> Result<()> {
-    while n > 0 {
+    loop {
       let mut byte = (n & 0x7fu128) as u8;
       n >>= 7;
       if n > 0 {
@@ -93,8 +99,10 @@ pub trait WriteBytesOsu: Write {
         byte |= 1 << 7;
       }
       self.write_u8(byte)?;
+      if n == 0 {
+        return Ok(());
+      }
     }
-    Ok(())
   }
 
   /// Writes a string value into the writer
@@ -110,6 +118,23 @@ pub trait WriteBytesOsu: Write {
     self.write_all(string.as_bytes())?;
     Ok(())
   }
+
+  /// Writes a string value into the writer, or marks it as not there if it's `None`. Unlike
+  /// [`write_uleb128_string`][Self::write_uleb128_string], an empty string is written as there
+  fn write_uleb128_optional_string(
+    &mut self,
+    string: Option<&str>,
+  ) -> Result<()> {
+    match string {
+      Some(string) => {
+        self.write_u8(0xb)?;
+        self.write_uleb128(string.len() as u128)?;
+        self.write_all(string.as_bytes())?;
+      }
+      None => self.write_u8(0x0)?,
+    }
+    Ok(())
+  }
 }
 
 impl<W: Write> WriteBytesOsu for W {}
@@ -133,6 +158,10 @@ mod tests {
     let mut curs = Cursor::new(&mut buf);
     curs.write_uleb128(624485)?;
     assert_eq!(buf, [0xe5, 0x8e, 0x26]);
+
+    let mut zero = Vec::new();
+    zero.write_uleb128(0)?;
+    assert_eq!(zero, [0x00]);
     Ok(())
   }
 
@@ -154,6 +183,23 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn test_optional_uleb128_string() -> Result<()> {
+    for (data, string) in [
+      (&b"\x00"[..], None),
+      (&b"\x0b\x00"[..], Some("")),
+      (&b"\x0b\x02hi"[..], Some("hi")),
+    ] {
+      let mut reader = Cursor::new(data);
+      assert_eq!(reader.read_uleb128_optional_string()?.as_deref(), string);
+
+      let mut buf = Vec::new();
+      buf.write_uleb128_optional_string(string)?;
+      assert_eq!(buf, data);
+    }
+    Ok(())
+  }
+
   #[test]
   fn test_write_uleb128_string() -> Result<()> {
     let mut buf = Vec::new();
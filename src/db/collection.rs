@@ -0,0 +1,188 @@
+use std::io::{self, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::{write_present_string, Db, DbBeatmap, DbResult, ReadBytesOsu};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Collection of beatmaps in collection.db
+pub struct Collection {
+  /// Name of the collection.
+  pub name: String,
+
+  /// MD5 hashes of the beatmaps in the collection.
+  pub beatmap_hashes: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// collection.db object
+pub struct CollectionDb {
+  /// Version of the game that wrote the file (e.g. 20150203)
+  pub version: u32,
+
+  /// The collections, in the order the game shows them
+  pub collections: Vec<Collection>,
+}
+
+impl Collection {
+  /// Looks up the beatmaps of this collection in an osu!.db. Every hash gives the beatmap it
+  /// belongs to, or `None` if the beatmap isn't in the osu!.db (for example if it was deleted).
+  pub fn resolve<'a>(&self, db: &'a Db) -> Vec<Option<&'a DbBeatmap>> {
+    let beatmaps = db.beatmaps_by_hash();
+    self
+      .beatmap_hashes
+      .iter()
+      .map(|hash| beatmaps.get(hash.as_str()).copied())
+      .collect()
+  }
+
+  /// The hashes of this collection that don't belong to any beatmap in an osu!.db.
+  pub fn missing_hashes<'a>(&'a self, db: &Db) -> Vec<&'a str> {
+    let beatmaps = db.beatmaps_by_hash();
+    self
+      .beatmap_hashes
+      .iter()
+      .map(String::as_str)
+      .filter(|hash| !beatmaps.contains_key(hash))
+      .collect()
+  }
+}
+
+impl CollectionDb {
+  /// Parse the collection.db data from a reader.
+  pub fn parse(mut reader: impl io::BufRead) -> DbResult<CollectionDb> {
+    let version = reader.read_u32::<LittleEndian>()?;
+    let collection_count = reader.read_u32::<LittleEndian>()?;
+    let collections = (0..collection_count)
+      .map(|_| {
+        let name = reader.read_uleb128_string()?;
+        let beatmap_count = reader.read_u32::<LittleEndian>()?;
+        let beatmap_hashes = (0..beatmap_count)
+          .map(|_| reader.read_uleb128_string())
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(Collection {
+          name,
+          beatmap_hashes,
+        })
+      })
+      .collect::<DbResult<Vec<_>>>()?;
+
+    Ok(CollectionDb {
+      version,
+      collections,
+    })
+  }
+
+  /// Writes the collection.db data to a writer.
+  pub fn write<W: Write>(&self, mut w: W) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(self.version)?;
+    w.write_u32::<LittleEndian>(self.collections.len() as u32)?;
+    for collection in &self.collections {
+      write_present_string(&mut w, &collection.name)?;
+      w.write_u32::<LittleEndian>(collection.beatmap_hashes.len() as u32)?;
+      for hash in &collection.beatmap_hashes {
+        write_present_string(&mut w, hash)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Finds the collection with the given name.
+  pub fn get(&self, name: &str) -> Option<&Collection> {
+    self
+      .collections
+      .iter()
+      .find(|collection| collection.name == name)
+  }
+
+  /// Looks up the beatmaps of every collection in an osu!.db, like [`Collection::resolve`].
+  pub fn resolve<'a>(
+    &'a self,
+    db: &'a Db,
+  ) -> Vec<(&'a Collection, Vec<Option<&'a DbBeatmap>>)> {
+    let beatmaps = db.beatmaps_by_hash();
+    self
+      .collections
+      .iter()
+      .map(|collection| {
+        let resolved = collection
+          .beatmap_hashes
+          .iter()
+          .map(|hash| beatmaps.get(hash.as_str()).copied())
+          .collect();
+        (collection, resolved)
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::{BufReader, Cursor};
+
+  use super::{Collection, CollectionDb};
+  use crate::db::Db;
+
+  fn collection_db() -> CollectionDb {
+    CollectionDb {
+      version: 20201210,
+      collections: vec![
+        Collection {
+          name: "taiko".to_owned(),
+          beatmap_hashes: vec![
+            "7956380054f6a8023fa7614e18ffe1b6".to_owned(),
+            "00000000000000000000000000000000".to_owned(),
+          ],
+        },
+        Collection {
+          name: "empty".to_owned(),
+          beatmap_hashes: vec![],
+        },
+      ],
+    }
+  }
+
+  #[test]
+  fn test_collectiondb_roundtrip() {
+    let collections = collection_db();
+    let mut written = Vec::new();
+    collections.write(&mut written).unwrap();
+
+    let mut expected = Vec::new();
+    expected.extend(20201210u32.to_le_bytes());
+    expected.extend(2u32.to_le_bytes());
+    expected.extend(b"\x0b\x05taiko");
+    expected.extend(2u32.to_le_bytes());
+    expected.extend(b"\x0b\x207956380054f6a8023fa7614e18ffe1b6");
+    expected.extend(b"\x0b\x2000000000000000000000000000000000");
+    expected.extend(b"\x0b\x05empty");
+    expected.extend(0u32.to_le_bytes());
+    assert_eq!(written, expected);
+
+    let parsed = CollectionDb::parse(Cursor::new(&written)).unwrap();
+    assert_eq!(parsed, collections);
+  }
+
+  #[test]
+  fn test_collectiondb_resolve() {
+    let osr = File::open("tests/files/osu.db").unwrap();
+    let db = Db::parse(BufReader::new(osr)).unwrap();
+    let collections = collection_db();
+
+    let taiko = collections.get("taiko").unwrap();
+    let resolved = taiko.resolve(&db);
+    assert_eq!(resolved.len(), 2);
+    assert_eq!(resolved[0].unwrap().song_title, "TRICK or TREAT");
+    assert!(resolved[1].is_none());
+    assert_eq!(
+      taiko.missing_hashes(&db),
+      ["00000000000000000000000000000000"]
+    );
+
+    let all = collections.resolve(&db);
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].1, resolved);
+    assert!(all[1].1.is_empty());
+  }
+}
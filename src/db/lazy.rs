@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use super::{
+  read_tag, DbBeatmap, DbError, DbResult, ReadBytesOsu,
+  FIRST_VERSION_WITHOUT_SIZE, FIRST_VERSION_WITH_FLOAT_STAR_RATINGS,
+  FIRST_VERSION_WITH_STAR_RATINGS,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// The part of osu!.db that comes before the beatmaps
+pub struct DbHeader {
+  /// Version of the game that wrote the file (e.g. 20201210)
+  pub version: u32,
+  /// The amount of folders?
+  pub folder_count: u32,
+  /// If the account is unlocked, aka not banned or locked.
+  pub account_unlocked: bool,
+  /// When the account will be unlocked, in windows ticks
+  pub unlocked_date: u64,
+  /// The player's username
+  pub player_name: String,
+  /// Whether the player's username was stored as absent instead of as present but empty
+  pub player_name_absent: bool,
+  /// The amount of beatmaps cached
+  pub beatmap_count: u32,
+}
+
+impl DbHeader {
+  /// Parse the osu!.db header from a reader.
+  pub fn parse(mut reader: impl io::BufRead) -> DbResult<DbHeader> {
+    let version = reader.read_u32::<LittleEndian>()?;
+    let folder_count = reader.read_u32::<LittleEndian>()?;
+    let account_unlocked = reader.read_u8()? > 0;
+    let unlocked_date = reader.read_u64::<LittleEndian>()?;
+    let player_name = reader.read_uleb128_optional_string()?;
+    Ok(DbHeader {
+      version,
+      folder_count,
+      account_unlocked,
+      unlocked_date,
+      player_name_absent: player_name.is_none(),
+      player_name: player_name.unwrap_or_default(),
+      beatmap_count: reader.read_u32::<LittleEndian>()?,
+    })
+  }
+}
+
+/// Reads the beatmaps of an osu!.db one at a time, so that the whole database never has to be in
+/// memory.
+///
+/// ```no_run
+/// # use std::{fs::File, io::BufReader};
+/// # use libosu::db::DbReader;
+/// # fn invisible() -> anyhow::Result<()> {
+/// let file = BufReader::new(File::open("osu!.db")?);
+/// for beatmap in DbReader::new(file)? {
+///     let beatmap = beatmap?;
+///     println!("{} - {}", beatmap.artist_name, beatmap.song_title);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DbReader<R> {
+  reader: R,
+  header: DbHeader,
+  remaining: u32,
+}
+
+impl<R: BufRead> DbReader<R> {
+  /// Reads the header of the osu!.db, leaving the reader at the first beatmap.
+  pub fn new(mut reader: R) -> DbResult<DbReader<R>> {
+    let header = DbHeader::parse(&mut reader)?;
+    Ok(DbReader {
+      reader,
+      remaining: header.beatmap_count,
+      header,
+    })
+  }
+
+  /// The header of the osu!.db.
+  pub fn header(&self) -> &DbHeader {
+    &self.header
+  }
+
+  /// Reads the permissions at the end of the osu!.db, skipping over the beatmaps that haven't
+  /// been read yet. They're stored as 32 bits, but every permission fits in the lowest 8.
+  pub fn permissions(mut self) -> DbResult<u8> {
+    while self.remaining > 0 {
+      self.remaining -= 1;
+      skip_beatmap(&mut self.reader, self.header.version)?;
+    }
+    let permissions = self.reader.read_u32::<LittleEndian>()?;
+    u8::try_from(permissions)
+      .map_err(|_| DbError::InvalidPermissions(permissions))
+  }
+}
+
+impl<R: BufRead> Iterator for DbReader<R> {
+  type Item = DbResult<DbBeatmap>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    self.remaining -= 1;
+
+    let beatmap = DbBeatmap::parse(&mut self.reader, self.header.version);
+    if beatmap.is_err() {
+      // the position of the next beatmap isn't known anymore
+      self.remaining = 0;
+    }
+    Some(beatmap)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (0, Some(self.remaining as usize))
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Where a beatmap is in an osu!.db
+pub struct DbIndexEntry {
+  /// Offset of the beatmap entry from the start of the file.
+  pub offset: u64,
+
+  /// MD5 hash of the beatmap.
+  pub hash: String,
+}
+
+/// The positions and hashes of all beatmaps in an osu!.db, which lets single beatmaps be read
+/// without parsing the rest of the database.
+///
+/// Building the index only allocates the hashes, and skips over everything else.
+///
+/// ```no_run
+/// # use std::{fs::File, io::BufReader};
+/// # use libosu::db::DbIndex;
+/// # fn invisible() -> anyhow::Result<()> {
+/// let mut file = BufReader::new(File::open("osu!.db")?);
+/// let index = DbIndex::build(&mut file)?;
+/// if let Some(beatmap) = index.beatmap_by_hash(&mut file, "7956380054f6a8023fa7614e18ffe1b6")? {
+///     println!("{}", beatmap.folder_name);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DbIndex {
+  /// The header of the osu!.db.
+  pub header: DbHeader,
+
+  /// The beatmaps, in the order they're stored in.
+  pub entries: Vec<DbIndexEntry>,
+
+  /// The permissions of the user
+  pub permissions: u8,
+
+  by_hash: HashMap<String, usize>,
+}
+
+impl DbIndex {
+  /// Indexes an osu!.db. The reader has to be at the start of the file, since the offsets are
+  /// counted from there.
+  pub fn build(reader: impl BufRead) -> DbResult<DbIndex> {
+    let mut reader = CountingReader {
+      inner: reader,
+      position: 0,
+    };
+    let header = DbHeader::parse(&mut reader)?;
+    let entries = (0..header.beatmap_count)
+      .map(|_| {
+        let offset = reader.position;
+        let hash = skip_beatmap(&mut reader, header.version)?;
+        Ok(DbIndexEntry { offset, hash })
+      })
+      .collect::<DbResult<Vec<_>>>()?;
+    let permissions = reader.read_u32::<LittleEndian>()? as u8;
+
+    let mut by_hash = HashMap::with_capacity(entries.len());
+    for (position, entry) in entries.iter().enumerate() {
+      by_hash.entry(entry.hash.clone()).or_insert(position);
+    }
+    Ok(DbIndex {
+      header,
+      entries,
+      permissions,
+      by_hash,
+    })
+  }
+
+  /// The number of beatmaps in the index.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Whether there are no beatmaps in the index.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The position of the beatmap with the given MD5 hash in the index. If the hash is there more
+  /// than once, this is the first one.
+  pub fn position(&self, hash: &str) -> Option<usize> {
+    self.by_hash.get(hash).copied()
+  }
+
+  /// Reads the beatmap at the given position in the index from the osu!.db it was built from.
+  /// Returns `None` if the position is past the last beatmap.
+  pub fn beatmap<R: BufRead + Seek>(
+    &self,
+    mut reader: R,
+    position: usize,
+  ) -> DbResult<Option<DbBeatmap>> {
+    let entry = match self.entries.get(position) {
+      Some(entry) => entry,
+      None => return Ok(None),
+    };
+    reader.seek(SeekFrom::Start(entry.offset))?;
+    Ok(Some(DbBeatmap::parse(reader, self.header.version)?))
+  }
+
+  /// Reads the beatmap with the given MD5 hash from the osu!.db the index was built from.
+  pub fn beatmap_by_hash<R: BufRead + Seek>(
+    &self,
+    reader: R,
+    hash: &str,
+  ) -> DbResult<Option<DbBeatmap>> {
+    match self.position(hash) {
+      Some(position) => self.beatmap(reader, position),
+      None => Ok(None),
+    }
+  }
+}
+
+/// Passes reads through while counting how many bytes were read.
+struct CountingReader<R> {
+  inner: R,
+  position: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.position += read as u64;
+    Ok(read)
+  }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+  fn fill_buf(&mut self) -> io::Result<&[u8]> {
+    self.inner.fill_buf()
+  }
+
+  fn consume(&mut self, amt: usize) {
+    self.inner.consume(amt);
+    self.position += amt as u64;
+  }
+}
+
+/// Skips bytes without copying them anywhere.
+fn skip(mut reader: impl BufRead, mut count: u64) -> DbResult<()> {
+  while count > 0 {
+    let available = reader.fill_buf()?.len() as u64;
+    if available == 0 {
+      return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let consumed = available.min(count);
+    reader.consume(consumed as usize);
+    count -= consumed;
+  }
+  Ok(())
+}
+
+/// Skips a string without reading it into memory.
+fn skip_string(mut reader: impl BufRead) -> DbResult<()> {
+  match reader.read_u8()? {
+    0x0 => Ok(()),
+    0xb => {
+      let length = reader.read_uleb128()?;
+      skip(reader, length as u64)
+    }
+    v => Err(super::binary::Error::InvalidStringStatusChar(v).into()),
+  }
+}
+
+/// Skips over a beatmap entry, laid out like [`DbBeatmap::parse`] reads it, and returns its hash.
+fn skip_beatmap(mut reader: impl BufRead, version: u32) -> DbResult<String> {
+  if version < FIRST_VERSION_WITHOUT_SIZE {
+    skip(&mut reader, 4)?;
+  }
+  // artist, title, creator, difficulty and audio file names
+  for _ in 0..7 {
+    skip_string(&mut reader)?;
+  }
+  let hash = reader.read_uleb128_string()?;
+  skip_string(&mut reader)?;
+
+  // ranked status, object counts and modification date
+  skip(&mut reader, 1 + 3 * 2 + 8)?;
+  let difficulty_size = if version < FIRST_VERSION_WITH_STAR_RATINGS {
+    1
+  } else {
+    4
+  };
+  // approach rate, circle size, HP drain, overall difficulty and slider velocity
+  skip(&mut reader, 4 * difficulty_size + 8)?;
+
+  if version >= FIRST_VERSION_WITH_STAR_RATINGS {
+    for _ in 0..4 {
+      let count = reader.read_u32::<LittleEndian>()?;
+      for _ in 0..count {
+        read_tag(&mut reader, super::INT_TAG)?;
+        skip(&mut reader, 4)?;
+        if version < FIRST_VERSION_WITH_FLOAT_STAR_RATINGS {
+          read_tag(&mut reader, super::DOUBLE_TAG)?;
+          skip(&mut reader, 8)?;
+        } else {
+          read_tag(&mut reader, super::FLOAT_TAG)?;
+          skip(&mut reader, 4)?;
+        }
+      }
+    }
+  }
+
+  // drain, total and preview times
+  skip(&mut reader, 3 * 4)?;
+  let timing_point_count = reader.read_u32::<LittleEndian>()?;
+  skip(&mut reader, timing_point_count as u64 * (8 + 8 + 1))?;
+
+  // ids, grades, local offset, stack leniency and mode
+  skip(&mut reader, 3 * 4 + 4 + 2 + 4 + 1)?;
+  skip_string(&mut reader)?;
+  skip_string(&mut reader)?;
+  skip(&mut reader, 2)?;
+  skip_string(&mut reader)?;
+  // unplayed, last played and osz2
+  skip(&mut reader, 1 + 8 + 1)?;
+  skip_string(&mut reader)?;
+  // last checked and the overrides
+  skip(&mut reader, 8 + 5)?;
+  if version < FIRST_VERSION_WITH_STAR_RATINGS {
+    skip(&mut reader, 2)?;
+  }
+  // last modification time and mania scroll speed
+  skip(&mut reader, 4 + 1)?;
+
+  Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::{BufReader, Cursor};
+
+  use super::{DbIndex, DbReader};
+  use crate::db::{Db, FIRST_VERSION_WITH_FLOAT_STAR_RATINGS};
+
+  #[test]
+  fn test_osudb_reader() {
+    let osr = File::open("tests/files/osu.db").unwrap();
+    let db = Db::parse(BufReader::new(osr)).unwrap();
+
+    let osr = File::open("tests/files/osu.db").unwrap();
+    let mut reader = DbReader::new(BufReader::new(osr)).unwrap();
+    assert_eq!(reader.header().beatmap_count, 245);
+    let first = reader.next().unwrap().unwrap();
+    assert_eq!(&first, &db.beatmaps[0]);
+    assert_eq!(reader.permissions().unwrap(), db.permissions);
+  }
+
+  #[test]
+  fn test_osudb_index() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+    for version in [20201210, FIRST_VERSION_WITH_FLOAT_STAR_RATINGS, 20140101] {
+      let mut db = Db::parse(Cursor::new(&data)).unwrap();
+      db.version = version;
+      let mut written = Vec::new();
+      db.write(&mut written).unwrap();
+      let db = Db::parse(Cursor::new(&written)).unwrap();
+
+      let mut reader = Cursor::new(&written);
+      let index = DbIndex::build(&mut reader).unwrap();
+      assert_eq!(index.len(), db.beatmaps.len());
+      assert_eq!(index.permissions, db.permissions);
+      for (position, beatmap) in db.beatmaps.iter().enumerate().rev() {
+        assert_eq!(index.entries[position].hash, beatmap.hash);
+        let read = index.beatmap(&mut reader, position).unwrap();
+        assert_eq!(read.as_ref(), Some(beatmap));
+      }
+      assert!(index
+        .beatmap(&mut reader, db.beatmaps.len())
+        .unwrap()
+        .is_none());
+
+      let hash = &db.beatmaps[100].hash;
+      let read = index.beatmap_by_hash(&mut reader, hash).unwrap().unwrap();
+      assert_eq!(&read.hash, hash);
+      assert!(index
+        .beatmap_by_hash(&mut reader, "none")
+        .unwrap()
+        .is_none());
+    }
+  }
+}
@@ -1,8 +1,13 @@
 pub mod binary;
+mod collection;
+mod lazy;
+mod presence;
+mod scores;
 
-use std::io;
+use std::collections::HashMap;
+use std::io::{self, Write};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use crate::{
   data::{Grade, Mode, Mods, RankedStatus},
@@ -10,6 +15,29 @@ use crate::{
 };
 
 pub use self::binary::{Error, ReadBytesOsu, WriteBytesOsu};
+pub use self::collection::{Collection, CollectionDb};
+pub use self::lazy::{DbHeader, DbIndex, DbIndexEntry, DbReader};
+pub use self::presence::{PresenceDb, PresenceDbPlayer};
+pub use self::scores::{ScoresDb, ScoresDbBeatmap};
+
+/// The first osu!.db version whose beatmaps don't start with their size in bytes.
+pub const FIRST_VERSION_WITHOUT_SIZE: u32 = 20191106;
+
+/// The first osu!.db version that stores star ratings, and stores the approach rate, circle size,
+/// HP drain and overall difficulty as floats instead of bytes.
+pub const FIRST_VERSION_WITH_STAR_RATINGS: u32 = 20140609;
+
+/// The first osu!.db version that stores star ratings as floats instead of doubles.
+pub const FIRST_VERSION_WITH_FLOAT_STAR_RATINGS: u32 = 20250107;
+
+/// Type tag of an Int in an osu!.db Int-Double or Int-Float pair.
+const INT_TAG: u8 = 0x08;
+
+/// Type tag of a Float in an osu!.db Int-Float pair.
+const FLOAT_TAG: u8 = 0x0C;
+
+/// Type tag of a Double in an osu!.db Int-Double pair.
+const DOUBLE_TAG: u8 = 0x0D;
 
 /// Result type for .db file processing
 pub type DbResult<T, E = DbError> = std::result::Result<T, E>;
@@ -26,9 +54,25 @@ pub enum DbError {
   #[error("io error: {0}")]
   Io(#[from] std::io::Error),
 
+  /// Error reading a score, which is laid out like a replay
+  #[error("error reading score: {0}")]
+  Replay(#[from] crate::replay::ReplayError),
+
   /// Invalid mod combination
   #[error("unexpected mods: {0}")]
   InvalidMods(u32),
+
+  /// Invalid game mode
+  #[error("invalid mode: {0}")]
+  InvalidMode(u8),
+
+  /// Permissions with bits that don't belong to any permission
+  #[error("invalid permissions: {0:#x}")]
+  InvalidPermissions(u32),
+
+  /// A value had a different type tag than the format version calls for
+  #[error("unexpected type tag: expected {expected:#04x}, found {found:#04x}")]
+  UnexpectedTag { expected: u8, found: u8 },
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -44,6 +88,42 @@ pub struct DbBeatmapTimingPoint {
   pub is_uninherited: bool,
 }
 
+bitflags! {
+    /// The string fields of a [`DbBeatmap`].
+    ///
+    /// osu!.db can store an empty string either as absent or as present with a length of zero,
+    /// and these are used to remember which one it was.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct DbBeatmapStrings: u16 {
+        /// [`DbBeatmap::artist_name`]
+        const ARTIST_NAME = 1 << 0;
+        /// [`DbBeatmap::artist_name_unicode`]
+        const ARTIST_NAME_UNICODE = 1 << 1;
+        /// [`DbBeatmap::song_title`]
+        const SONG_TITLE = 1 << 2;
+        /// [`DbBeatmap::song_title_unicode`]
+        const SONG_TITLE_UNICODE = 1 << 3;
+        /// [`DbBeatmap::creator_name`]
+        const CREATOR_NAME = 1 << 4;
+        /// [`DbBeatmap::difficulty`]
+        const DIFFICULTY = 1 << 5;
+        /// [`DbBeatmap::audio_file_name`]
+        const AUDIO_FILE_NAME = 1 << 6;
+        /// [`DbBeatmap::hash`]
+        const HASH = 1 << 7;
+        /// [`DbBeatmap::beatmap_file_name`]
+        const BEATMAP_FILE_NAME = 1 << 8;
+        /// [`DbBeatmap::source`]
+        const SOURCE = 1 << 9;
+        /// [`DbBeatmap::tags`]
+        const TAGS = 1 << 10;
+        /// [`DbBeatmap::title_font`]
+        const TITLE_FONT = 1 << 11;
+        /// [`DbBeatmap::folder_name`]
+        const FOLDER_NAME = 1 << 12;
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 /// Beatmap located in osu!.db, they are different from normal beatmaps
 pub struct DbBeatmap {
@@ -199,6 +279,10 @@ pub struct DbBeatmap {
 
   /// Scroll speed for mania.
   pub mania_scrollspeed: u8,
+
+  /// The strings that were stored as absent instead of as present but empty. Strings that aren't
+  /// empty are always written as present.
+  pub absent_strings: DbBeatmapStrings,
 }
 
 #[derive(Debug, Clone)]
@@ -220,6 +304,8 @@ pub struct Db {
   pub unlocked_date: u64,
   /// The player's username
   pub player_name: String,
+  /// Whether the player's username was stored as absent instead of as present but empty
+  pub player_name_absent: bool,
   /// The amount of beatmaps cached
   pub beatmap_count: u32,
   /// The cached beatmaps     
@@ -228,22 +314,58 @@ pub struct Db {
   pub permissions: u8,
 }
 
+/// Writes a string as present, even when it's empty.
+fn write_present_string<W: Write>(mut w: W, string: &str) -> DbResult<()> {
+  w.write_u8(0xb)?;
+  w.write_uleb128(string.len() as u128)?;
+  w.write_all(string.as_bytes())?;
+  Ok(())
+}
+
+/// Reads a string, remembering in `absent` whether it was stored as absent.
+fn read_string(
+  mut reader: impl io::BufRead,
+  absent: &mut DbBeatmapStrings,
+  field: DbBeatmapStrings,
+) -> DbResult<String> {
+  let string = reader.read_uleb128_optional_string()?;
+  absent.set(field, string.is_none());
+  Ok(string.unwrap_or_default())
+}
+
+/// Reads a type tag and checks that it's the expected one.
+fn read_tag(mut reader: impl io::BufRead, expected: u8) -> DbResult<()> {
+  let found = reader.read_u8()?;
+  if found != expected {
+    return Err(DbError::UnexpectedTag { expected, found });
+  }
+  Ok(())
+}
+
 impl DbBeatmap {
   fn read_star_rating(
     mut reader: impl io::BufRead,
+    version: u32,
   ) -> DbResult<Vec<(Mods, f64)>> {
+    if version < FIRST_VERSION_WITH_STAR_RATINGS {
+      return Ok(Vec::new());
+    }
+
     let count = reader.read_u32::<LittleEndian>()?;
     let ratings = (0..count)
       .map(|_| -> DbResult<(Mods, f64)> {
         Ok((
           {
-            assert_eq!(reader.read_u8()?, 0x08);
+            read_tag(&mut reader, INT_TAG)?;
             let value = reader.read_u32::<LittleEndian>()?;
             Mods::from_bits(value).ok_or(DbError::InvalidMods(value))?
           },
-          {
-            assert_eq!(reader.read_u8()?, 0x0D);
+          if version < FIRST_VERSION_WITH_FLOAT_STAR_RATINGS {
+            read_tag(&mut reader, DOUBLE_TAG)?;
             reader.read_f64::<LittleEndian>()?
+          } else {
+            read_tag(&mut reader, FLOAT_TAG)?;
+            reader.read_f32::<LittleEndian>()? as f64
           },
         ))
       })
@@ -251,6 +373,19 @@ impl DbBeatmap {
     Ok(ratings)
   }
 
+  /// Reads the approach rate, circle size, HP drain or overall difficulty, which are bytes in
+  /// versions without star ratings.
+  fn read_difficulty(
+    mut reader: impl io::BufRead,
+    version: u32,
+  ) -> DbResult<f32> {
+    if version < FIRST_VERSION_WITH_STAR_RATINGS {
+      Ok(reader.read_u8()? as f32)
+    } else {
+      Ok(reader.read_f32::<LittleEndian>()?)
+    }
+  }
+
   fn read_timing_points(
     mut reader: impl io::BufRead,
   ) -> DbResult<Vec<DbBeatmapTimingPoint>> {
@@ -268,35 +403,72 @@ impl DbBeatmap {
   }
 
   fn parse(mut reader: impl io::BufRead, version: u32) -> DbResult<DbBeatmap> {
+    let mut absent_strings = DbBeatmapStrings::empty();
     Ok(DbBeatmap {
-      size: if version < 20191106 {
+      size: if version < FIRST_VERSION_WITHOUT_SIZE {
         Some(reader.read_u32::<LittleEndian>()?)
       } else {
         None
       },
-      artist_name: reader.read_uleb128_string()?,
-      artist_name_unicode: reader.read_uleb128_string()?,
-      song_title: reader.read_uleb128_string()?,
-      song_title_unicode: reader.read_uleb128_string()?,
-      creator_name: reader.read_uleb128_string()?,
-      difficulty: reader.read_uleb128_string()?,
-      audio_file_name: reader.read_uleb128_string()?,
-      hash: reader.read_uleb128_string()?,
-      beatmap_file_name: reader.read_uleb128_string()?,
+      artist_name: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::ARTIST_NAME,
+      )?,
+      artist_name_unicode: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::ARTIST_NAME_UNICODE,
+      )?,
+      song_title: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::SONG_TITLE,
+      )?,
+      song_title_unicode: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::SONG_TITLE_UNICODE,
+      )?,
+      creator_name: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::CREATOR_NAME,
+      )?,
+      difficulty: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::DIFFICULTY,
+      )?,
+      audio_file_name: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::AUDIO_FILE_NAME,
+      )?,
+      hash: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::HASH,
+      )?,
+      beatmap_file_name: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::BEATMAP_FILE_NAME,
+      )?,
       ranked_status: num::FromPrimitive::from_u8(reader.read_u8()?).unwrap(),
       hitcircle_count: reader.read_u16::<LittleEndian>()?,
       slider_count: reader.read_u16::<LittleEndian>()?,
       spinner_count: reader.read_u16::<LittleEndian>()?,
       modification_date: reader.read_u64::<LittleEndian>()?,
-      approach_rate: reader.read_f32::<LittleEndian>()?,
-      circle_size: reader.read_f32::<LittleEndian>()?,
-      hp_drain: reader.read_f32::<LittleEndian>()?,
-      overall_difficulty: reader.read_f32::<LittleEndian>()?,
+      approach_rate: Self::read_difficulty(&mut reader, version)?,
+      circle_size: Self::read_difficulty(&mut reader, version)?,
+      hp_drain: Self::read_difficulty(&mut reader, version)?,
+      overall_difficulty: Self::read_difficulty(&mut reader, version)?,
       slider_velocity: reader.read_f64::<LittleEndian>()?,
-      std_star_rating: Self::read_star_rating(&mut reader)?,
-      std_taiko_rating: Self::read_star_rating(&mut reader)?,
-      std_ctb_rating: Self::read_star_rating(&mut reader)?,
-      std_mania_rating: Self::read_star_rating(&mut reader)?,
+      std_star_rating: Self::read_star_rating(&mut reader, version)?,
+      std_taiko_rating: Self::read_star_rating(&mut reader, version)?,
+      std_ctb_rating: Self::read_star_rating(&mut reader, version)?,
+      std_mania_rating: Self::read_star_rating(&mut reader, version)?,
       drain_time: Millis(reader.read_i32::<LittleEndian>()? * 1000), // the file contains seconds, not milliseconds
       total_time: Millis(reader.read_i32::<LittleEndian>()?),
       preview_time: Millis(reader.read_i32::<LittleEndian>()?),
@@ -311,68 +483,299 @@ impl DbBeatmap {
       beatmap_offset: reader.read_u16::<LittleEndian>()?,
       stack_leniency: reader.read_f32::<LittleEndian>()?,
       mode: num::FromPrimitive::from_u8(reader.read_u8()?).unwrap(),
-      source: reader.read_uleb128_string()?,
-      tags: reader.read_uleb128_string()?,
+      source: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::SOURCE,
+      )?,
+      tags: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::TAGS,
+      )?,
       online_offset: reader.read_u16::<LittleEndian>()?,
-      title_font: reader.read_uleb128_string()?,
+      title_font: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::TITLE_FONT,
+      )?,
       is_unplayed: reader.read_u8()? > 0,
       last_played: reader.read_u64::<LittleEndian>()?,
       is_osz2: reader.read_u8()? > 0,
-      folder_name: reader.read_uleb128_string()?,
+      folder_name: read_string(
+        &mut reader,
+        &mut absent_strings,
+        DbBeatmapStrings::FOLDER_NAME,
+      )?,
       last_checked: reader.read_u64::<LittleEndian>()?,
       ignore_beatmap_sounds: reader.read_u8()? > 0,
       ignore_beatmap_skin: reader.read_u8()? > 0,
       disable_storyboard: reader.read_u8()? > 0,
       disable_video: reader.read_u8()? > 0,
       visual_override: reader.read_u8()? > 0,
-      unknown: if version < 20140609 {
+      unknown: if version < FIRST_VERSION_WITH_STAR_RATINGS {
         Some(reader.read_u16::<LittleEndian>()?)
       } else {
         None
       },
       unknown_modification_date: reader.read_u32::<LittleEndian>()?,
       mania_scrollspeed: reader.read_u8()?,
+      absent_strings,
     })
   }
+
+  fn write_star_rating<W: Write>(
+    mut w: W,
+    ratings: &[(Mods, f64)],
+    version: u32,
+  ) -> DbResult<()> {
+    if version < FIRST_VERSION_WITH_STAR_RATINGS {
+      return Ok(());
+    }
+
+    w.write_u32::<LittleEndian>(ratings.len() as u32)?;
+    for (mods, rating) in ratings {
+      w.write_u8(INT_TAG)?;
+      w.write_u32::<LittleEndian>(mods.bits())?;
+      if version < FIRST_VERSION_WITH_FLOAT_STAR_RATINGS {
+        w.write_u8(DOUBLE_TAG)?;
+        w.write_f64::<LittleEndian>(*rating)?;
+      } else {
+        w.write_u8(FLOAT_TAG)?;
+        w.write_f32::<LittleEndian>(*rating as f32)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn write_difficulty<W: Write>(
+    mut w: W,
+    value: f32,
+    version: u32,
+  ) -> DbResult<()> {
+    if version < FIRST_VERSION_WITH_STAR_RATINGS {
+      w.write_u8(value.round() as u8)?;
+    } else {
+      w.write_f32::<LittleEndian>(value)?;
+    }
+    Ok(())
+  }
+
+  fn write_timing_points<W: Write>(
+    mut w: W,
+    points: &[DbBeatmapTimingPoint],
+  ) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(points.len() as u32)?;
+    for point in points {
+      w.write_f64::<LittleEndian>(point.bpm)?;
+      w.write_f64::<LittleEndian>(point.offset)?;
+      w.write_u8(point.is_uninherited as u8)?;
+    }
+    Ok(())
+  }
+
+  /// Writes the beatmap entry the way the given osu!.db version lays it out. Versions without
+  /// star ratings leave them out and round the difficulty settings to whole numbers.
+  ///
+  /// Before 20191106 the entry starts with its size, which is worked out from the written data
+  /// rather than taken from `size`, so that it stays correct after the entry was edited.
+  fn write<W: Write>(&self, mut w: W, version: u32) -> DbResult<()> {
+    if version < FIRST_VERSION_WITHOUT_SIZE {
+      let mut entry = Vec::new();
+      self.write_entry(&mut entry, version)?;
+      w.write_u32::<LittleEndian>(entry.len() as u32)?;
+      w.write_all(&entry)?;
+    } else {
+      self.write_entry(w, version)?;
+    }
+    Ok(())
+  }
+
+  /// Writes a string, as absent if it's empty and was read as absent.
+  fn write_string<W: Write>(
+    &self,
+    mut w: W,
+    string: &str,
+    field: DbBeatmapStrings,
+  ) -> DbResult<()> {
+    let absent = string.is_empty() && self.absent_strings.contains(field);
+    w.write_uleb128_optional_string((!absent).then_some(string))?;
+    Ok(())
+  }
+
+  fn write_entry<W: Write>(&self, mut w: W, version: u32) -> DbResult<()> {
+    self.write_string(
+      &mut w,
+      &self.artist_name,
+      DbBeatmapStrings::ARTIST_NAME,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.artist_name_unicode,
+      DbBeatmapStrings::ARTIST_NAME_UNICODE,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.song_title,
+      DbBeatmapStrings::SONG_TITLE,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.song_title_unicode,
+      DbBeatmapStrings::SONG_TITLE_UNICODE,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.creator_name,
+      DbBeatmapStrings::CREATOR_NAME,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.difficulty,
+      DbBeatmapStrings::DIFFICULTY,
+    )?;
+    self.write_string(
+      &mut w,
+      &self.audio_file_name,
+      DbBeatmapStrings::AUDIO_FILE_NAME,
+    )?;
+    self.write_string(&mut w, &self.hash, DbBeatmapStrings::HASH)?;
+    self.write_string(
+      &mut w,
+      &self.beatmap_file_name,
+      DbBeatmapStrings::BEATMAP_FILE_NAME,
+    )?;
+    w.write_u8(self.ranked_status as u8)?;
+    w.write_u16::<LittleEndian>(self.hitcircle_count)?;
+    w.write_u16::<LittleEndian>(self.slider_count)?;
+    w.write_u16::<LittleEndian>(self.spinner_count)?;
+    w.write_u64::<LittleEndian>(self.modification_date)?;
+    Self::write_difficulty(&mut w, self.approach_rate, version)?;
+    Self::write_difficulty(&mut w, self.circle_size, version)?;
+    Self::write_difficulty(&mut w, self.hp_drain, version)?;
+    Self::write_difficulty(&mut w, self.overall_difficulty, version)?;
+    w.write_f64::<LittleEndian>(self.slider_velocity)?;
+    Self::write_star_rating(&mut w, &self.std_star_rating, version)?;
+    Self::write_star_rating(&mut w, &self.std_taiko_rating, version)?;
+    Self::write_star_rating(&mut w, &self.std_ctb_rating, version)?;
+    Self::write_star_rating(&mut w, &self.std_mania_rating, version)?;
+    w.write_i32::<LittleEndian>(self.drain_time.0 / 1000)?; // the file contains seconds
+    w.write_i32::<LittleEndian>(self.total_time.0)?;
+    w.write_i32::<LittleEndian>(self.preview_time.0)?;
+    Self::write_timing_points(&mut w, &self.timing_points)?;
+    w.write_u32::<LittleEndian>(self.beatmap_id)?;
+    w.write_u32::<LittleEndian>(self.beatmap_set_id)?;
+    w.write_u32::<LittleEndian>(self.thread_id)?;
+    w.write_u8(self.std_grade as u8)?;
+    w.write_u8(self.taiko_grade as u8)?;
+    w.write_u8(self.ctb_grade as u8)?;
+    w.write_u8(self.mania_grade as u8)?;
+    w.write_u16::<LittleEndian>(self.beatmap_offset)?;
+    w.write_f32::<LittleEndian>(self.stack_leniency)?;
+    w.write_u8(self.mode as u8)?;
+    self.write_string(&mut w, &self.source, DbBeatmapStrings::SOURCE)?;
+    self.write_string(&mut w, &self.tags, DbBeatmapStrings::TAGS)?;
+    w.write_u16::<LittleEndian>(self.online_offset)?;
+    self.write_string(
+      &mut w,
+      &self.title_font,
+      DbBeatmapStrings::TITLE_FONT,
+    )?;
+    w.write_u8(self.is_unplayed as u8)?;
+    w.write_u64::<LittleEndian>(self.last_played)?;
+    w.write_u8(self.is_osz2 as u8)?;
+    self.write_string(
+      &mut w,
+      &self.folder_name,
+      DbBeatmapStrings::FOLDER_NAME,
+    )?;
+    w.write_u64::<LittleEndian>(self.last_checked)?;
+    w.write_u8(self.ignore_beatmap_sounds as u8)?;
+    w.write_u8(self.ignore_beatmap_skin as u8)?;
+    w.write_u8(self.disable_storyboard as u8)?;
+    w.write_u8(self.disable_video as u8)?;
+    w.write_u8(self.visual_override as u8)?;
+    if version < FIRST_VERSION_WITH_STAR_RATINGS {
+      w.write_u16::<LittleEndian>(self.unknown.unwrap_or(0))?;
+    }
+    w.write_u32::<LittleEndian>(self.unknown_modification_date)?;
+    w.write_u8(self.mania_scrollspeed)?;
+    Ok(())
+  }
 }
 
 impl Db {
   /// Parse the osu!.db data from a reader.
-  pub fn parse(mut reader: impl io::BufRead) -> DbResult<Db> {
-    let version = reader.read_u32::<LittleEndian>()?;
-    let folder_count = reader.read_u32::<LittleEndian>()?;
-    let account_unlocked = reader.read_u8()? > 0;
-    let unlocked_date = reader.read_u64::<LittleEndian>()?;
-    let player_name = reader.read_uleb128_string()?;
-    let beatmap_count = reader.read_u32::<LittleEndian>()?;
-    let beatmaps = (0..beatmap_count)
-      .map(|_| DbBeatmap::parse(&mut reader, version))
-      .collect::<DbResult<Vec<_>>>()?;
-    let permissions = reader.read_u8()?;
+  pub fn parse(reader: impl io::BufRead) -> DbResult<Db> {
+    let mut reader = DbReader::new(reader)?;
+    let beatmaps = (&mut reader).collect::<DbResult<Vec<_>>>()?;
+    let header = reader.header().clone();
+    let permissions = reader.permissions()?;
 
     Ok(Db {
-      version,
-      folder_count,
-      account_unlocked,
-      unlocked_date,
-      player_name,
-      beatmap_count,
+      version: header.version,
+      folder_count: header.folder_count,
+      account_unlocked: header.account_unlocked,
+      unlocked_date: header.unlocked_date,
+      player_name: header.player_name,
+      player_name_absent: header.player_name_absent,
+      beatmap_count: header.beatmap_count,
       beatmaps,
       permissions,
     })
   }
+
+  /// Finds the beatmap with the given MD5 hash.
+  pub fn beatmap_by_hash(&self, hash: &str) -> Option<&DbBeatmap> {
+    self.beatmaps.iter().find(|beatmap| beatmap.hash == hash)
+  }
+
+  /// Every beatmap, by its MD5 hash.
+  pub fn beatmaps_by_hash(&self) -> HashMap<&str, &DbBeatmap> {
+    self
+      .beatmaps
+      .iter()
+      .map(|beatmap| (beatmap.hash.as_str(), beatmap))
+      .collect()
+  }
+
+  /// Writes the osu!.db data to a writer, laid out for the database's `version`.
+  ///
+  /// The beatmap count is taken from `beatmaps`, so beatmaps can be added or removed without
+  /// updating `beatmap_count`. Empty strings are written as absent or present the way they were
+  /// read, so an unchanged database comes out byte-for-byte the same as it was read.
+  pub fn write<W: Write>(&self, mut w: W) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(self.version)?;
+    w.write_u32::<LittleEndian>(self.folder_count)?;
+    w.write_u8(self.account_unlocked as u8)?;
+    w.write_u64::<LittleEndian>(self.unlocked_date)?;
+    let player_name_absent =
+      self.player_name.is_empty() && self.player_name_absent;
+    w.write_uleb128_optional_string(
+      (!player_name_absent).then_some(self.player_name.as_str()),
+    )?;
+    w.write_u32::<LittleEndian>(self.beatmaps.len() as u32)?;
+    for beatmap in &self.beatmaps {
+      beatmap.write(&mut w, self.version)?;
+    }
+    w.write_u32::<LittleEndian>(self.permissions as u32)?;
+    Ok(())
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use std::io::BufReader;
+  use std::io::{BufReader, Cursor};
 
   use crate::{
     data::{Grade, Mode, Mods, RankedStatus, UserPermission},
     timing::Millis,
   };
 
-  use super::{Db, DbBeatmap, DbBeatmapTimingPoint};
+  use super::{
+    Db, DbBeatmap, DbBeatmapStrings, DbBeatmapTimingPoint, DbError,
+    FIRST_VERSION_WITH_FLOAT_STAR_RATINGS,
+  };
 
   // Thanks vernonlim for the osu.db file
   #[test]
@@ -608,10 +1011,155 @@ mod tests {
         unknown: None,
         unknown_modification_date: 0,
         mania_scrollspeed: 0,
+        absent_strings: DbBeatmapStrings::empty(),
     }));
     assert_eq!(
       db.permissions,
       UserPermission::Normal | UserPermission::Supporter
     );
   }
+
+  #[test]
+  fn test_osudb_write() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+    let db = Db::parse(Cursor::new(&data)).unwrap();
+
+    let mut written = Vec::new();
+    db.write(&mut written).unwrap();
+    assert_eq!(written, data);
+  }
+
+  #[test]
+  fn test_osudb_write_absent_strings() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+    let mut db = Db::parse(Cursor::new(&data)).unwrap();
+    db.player_name = String::new();
+    db.player_name_absent = true;
+    let beatmap = &mut db.beatmaps[0];
+    beatmap.source = String::new();
+    beatmap.tags = String::new();
+    beatmap.artist_name_unicode = String::new();
+    beatmap.absent_strings =
+      DbBeatmapStrings::SOURCE | DbBeatmapStrings::FOLDER_NAME;
+
+    let mut written = Vec::new();
+    db.write(&mut written).unwrap();
+    let reparsed = Db::parse(Cursor::new(&written)).unwrap();
+    assert!(reparsed.player_name_absent);
+    // the folder name isn't empty, so it was written as present
+    assert_eq!(
+      reparsed.beatmaps[0],
+      DbBeatmap {
+        absent_strings: DbBeatmapStrings::SOURCE,
+        ..db.beatmaps[0].clone()
+      }
+    );
+    assert_eq!(
+      reparsed.beatmaps[0].folder_name,
+      "952626 Drop - TRICK or TREAT"
+    );
+
+    let mut rewritten = Vec::new();
+    reparsed.write(&mut rewritten).unwrap();
+    assert_eq!(rewritten, written);
+
+    // absent strings take one byte, present but empty ones take two
+    let mut present = reparsed.clone();
+    present.player_name_absent = false;
+    present.beatmaps[0].absent_strings = DbBeatmapStrings::empty();
+    let mut present_written = Vec::new();
+    present.write(&mut present_written).unwrap();
+    assert_eq!(present_written.len(), written.len() + 2);
+  }
+
+  #[test]
+  fn test_osudb_write_old_version() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+    let mut db = Db::parse(Cursor::new(&data)).unwrap();
+    db.version = 20140101;
+    db.beatmaps.truncate(2);
+    db.beatmaps[0].unknown = Some(7);
+
+    let mut written = Vec::new();
+    db.write(&mut written).unwrap();
+    let reparsed = Db::parse(Cursor::new(&written)).unwrap();
+    assert_eq!(reparsed.version, 20140101);
+    assert_eq!(reparsed.beatmap_count, 2);
+    assert_eq!(reparsed.beatmaps[0].unknown, Some(7));
+    assert_eq!(reparsed.beatmaps[1].unknown, Some(0));
+
+    // the size covers the rest of the entry, up to the next one
+    let first_size = reparsed.beatmaps[0].size.unwrap();
+    let mut first = Vec::new();
+    reparsed.beatmaps[0]
+      .write(&mut first, reparsed.version)
+      .unwrap();
+    assert_eq!(first.len(), 4 + first_size as usize);
+    assert_eq!(
+      reparsed.beatmaps[0],
+      DbBeatmap {
+        size: Some(first_size),
+        // this version has no star ratings and whole number difficulty settings
+        hp_drain: 9.0,
+        std_taiko_rating: vec![],
+        ..db.beatmaps[0].clone()
+      }
+    );
+
+    let mut rewritten = Vec::new();
+    reparsed.write(&mut rewritten).unwrap();
+    assert_eq!(rewritten, written);
+  }
+
+  #[test]
+  fn test_osudb_float_star_ratings() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+    let mut db = Db::parse(Cursor::new(&data)).unwrap();
+    db.version = FIRST_VERSION_WITH_FLOAT_STAR_RATINGS;
+
+    let mut written = Vec::new();
+    db.write(&mut written).unwrap();
+    let reparsed = Db::parse(Cursor::new(&written)).unwrap();
+    assert_eq!(reparsed.beatmaps.len(), db.beatmaps.len());
+    for (beatmap, original) in reparsed.beatmaps.iter().zip(&db.beatmaps) {
+      let ratings = beatmap.std_taiko_rating.iter();
+      for (rating, original) in ratings.zip(&original.std_taiko_rating) {
+        assert_eq!(rating.0, original.0);
+        assert_eq!(rating.1, original.1 as f32 as f64);
+      }
+      assert_eq!(beatmap.folder_name, original.folder_name);
+    }
+
+    let mut rewritten = Vec::new();
+    reparsed.write(&mut rewritten).unwrap();
+    assert_eq!(rewritten, written);
+  }
+
+  #[test]
+  fn test_osudb_invalid_permissions() {
+    let mut data = std::fs::read("tests/files/osu.db").unwrap();
+    let permissions = data.len() - 4;
+    data[permissions..].copy_from_slice(&0x105u32.to_le_bytes());
+    assert!(matches!(
+      Db::parse(Cursor::new(&data)),
+      Err(DbError::InvalidPermissions(0x105))
+    ));
+  }
+
+  #[test]
+  fn test_osudb_unexpected_tag() {
+    let data = std::fs::read("tests/files/osu.db").unwrap();
+
+    // read doubles as if they were floats
+    let mut float_version = data.clone();
+    float_version[..4]
+      .copy_from_slice(&FIRST_VERSION_WITH_FLOAT_STAR_RATINGS.to_le_bytes());
+    assert!(matches!(
+      Db::parse(Cursor::new(&float_version)),
+      Err(DbError::UnexpectedTag {
+        expected: 0x0C,
+        found: 0x0D
+      })
+    ));
+  }
 }
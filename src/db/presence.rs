@@ -0,0 +1,212 @@
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::data::{Mode, UserPermission};
+use crate::replay::ticks_to_system_time;
+
+use super::{write_present_string, DbError, DbResult, ReadBytesOsu};
+
+/// The bits of the permissions byte that hold the permissions, the rest hold the mode.
+const PERMISSIONS_MASK: u8 = 0b0001_1111;
+
+/// How far the mode is shifted into the permissions byte.
+const MODE_SHIFT: u8 = 5;
+
+/// presence.db stores UTC offsets shifted by this much, so that they're never negative.
+const UTC_OFFSET_BIAS: i16 = 24;
+
+#[derive(Debug, PartialEq, Clone)]
+/// Player cached in presence.db
+pub struct PresenceDbPlayer {
+  /// Id of the player.
+  pub user_id: u32,
+
+  /// Name of the player.
+  pub username: String,
+
+  /// Offset of the player's timezone from UTC in hours.
+  pub utc_offset: i8,
+
+  /// Index of the player's country in the game's country list.
+  pub country: u8,
+
+  /// The player's permissions as bits of [`UserPermission`].
+  ///
+  /// presence.db keeps the permissions in the lowest 5 bits of a byte whose
+  /// upper bits hold the mode, so [`UserPermission::WorldCupStaff`] can't be
+  /// stored. It's never read, and it's left out when writing.
+  pub permissions: u8,
+
+  /// The mode the player was last seen playing.
+  pub mode: Mode,
+
+  /// Longitude of the player's location.
+  pub longitude: f32,
+
+  /// Latitude of the player's location.
+  pub latitude: f32,
+
+  /// Global rank of the player in `mode`.
+  pub global_rank: u32,
+
+  /// When the player's information was last updated.
+  ///
+  /// This is value is measured in [windows ticks][1].  It counts the number of ticks from
+  /// 12:00:00 midnight, January 1, 0001 to the time this replay was created
+  ///
+  /// [1]: https://docs.microsoft.com/en-us/dotnet/api/system.datetime.ticks?redirectedfrom=MSDN&view=net-5.0#System_DateTime_Ticks
+  pub last_update: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// presence.db object
+pub struct PresenceDb {
+  /// Version of the game that wrote the file (e.g. 20150204)
+  pub version: u32,
+
+  /// The cached players
+  pub players: Vec<PresenceDbPlayer>,
+}
+
+impl PresenceDbPlayer {
+  /// Whether the player has the given permission. This is always `false` for
+  /// [`UserPermission::WorldCupStaff`], which presence.db can't store.
+  pub fn has_permission(&self, permission: UserPermission) -> bool {
+    match permission {
+      UserPermission::None => self.permissions == 0,
+      permission => self.permissions & permission as u8 != 0,
+    }
+  }
+
+  /// Returns when the player's information was last updated, converted from
+  /// [`last_update`][Self::last_update].
+  pub fn last_update_time(&self) -> SystemTime {
+    ticks_to_system_time(self.last_update)
+  }
+
+  fn parse(mut reader: impl io::BufRead) -> DbResult<PresenceDbPlayer> {
+    let user_id = reader.read_u32::<LittleEndian>()?;
+    let username = reader.read_uleb128_string()?;
+    let utc_offset = (reader.read_u8()? as i16 - UTC_OFFSET_BIAS) as i8;
+    let country = reader.read_u8()?;
+    let permissions_and_mode = reader.read_u8()?;
+    let mode_value = permissions_and_mode >> MODE_SHIFT;
+    let mode = num::FromPrimitive::from_u8(mode_value)
+      .ok_or(DbError::InvalidMode(mode_value))?;
+
+    Ok(PresenceDbPlayer {
+      user_id,
+      username,
+      utc_offset,
+      country,
+      permissions: permissions_and_mode & PERMISSIONS_MASK,
+      mode,
+      longitude: reader.read_f32::<LittleEndian>()?,
+      latitude: reader.read_f32::<LittleEndian>()?,
+      global_rank: reader.read_u32::<LittleEndian>()?,
+      last_update: reader.read_u64::<LittleEndian>()?,
+    })
+  }
+
+  fn write<W: Write>(&self, mut w: W) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(self.user_id)?;
+    write_present_string(&mut w, &self.username)?;
+    w.write_u8((self.utc_offset as i16 + UTC_OFFSET_BIAS) as u8)?;
+    w.write_u8(self.country)?;
+    w.write_u8(
+      self.permissions & PERMISSIONS_MASK | (self.mode as u8) << MODE_SHIFT,
+    )?;
+    w.write_f32::<LittleEndian>(self.longitude)?;
+    w.write_f32::<LittleEndian>(self.latitude)?;
+    w.write_u32::<LittleEndian>(self.global_rank)?;
+    w.write_u64::<LittleEndian>(self.last_update)?;
+    Ok(())
+  }
+}
+
+impl PresenceDb {
+  /// Parse the presence.db data from a reader.
+  pub fn parse(mut reader: impl io::BufRead) -> DbResult<PresenceDb> {
+    let version = reader.read_u32::<LittleEndian>()?;
+    let player_count = reader.read_u32::<LittleEndian>()?;
+    let players = (0..player_count)
+      .map(|_| PresenceDbPlayer::parse(&mut reader))
+      .collect::<DbResult<Vec<_>>>()?;
+
+    Ok(PresenceDb { version, players })
+  }
+
+  /// Writes the presence.db data to a writer.
+  pub fn write<W: Write>(&self, mut w: W) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(self.version)?;
+    w.write_u32::<LittleEndian>(self.players.len() as u32)?;
+    for player in &self.players {
+      player.write(&mut w)?;
+    }
+    Ok(())
+  }
+
+  /// Finds the cached player with the given name, ignoring case like osu! does.
+  pub fn player_by_name(&self, username: &str) -> Option<&PresenceDbPlayer> {
+    self
+      .players
+      .iter()
+      .find(|player| player.username.eq_ignore_ascii_case(username))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use crate::data::{Mode, UserPermission};
+
+  use super::{PresenceDb, PresenceDbPlayer};
+
+  #[test]
+  fn test_presencedb_roundtrip() {
+    let mut data = Vec::new();
+    data.extend(20201210u32.to_le_bytes());
+    data.extend(1u32.to_le_bytes());
+    data.extend(2u32.to_le_bytes());
+    data.extend(b"\x0b\x05peppy");
+    data.push(24 + 9);
+    data.push(111);
+    data.push((2 << 5) | 0b10101);
+    data.extend(139.69f32.to_le_bytes());
+    data.extend(35.69f32.to_le_bytes());
+    data.extend(12345u32.to_le_bytes());
+    data.extend(637441576105762815u64.to_le_bytes());
+
+    let presence = PresenceDb::parse(Cursor::new(&data)).unwrap();
+    assert_eq!(presence.version, 20201210);
+    assert_eq!(
+      presence.players,
+      [PresenceDbPlayer {
+        user_id: 2,
+        username: "peppy".to_owned(),
+        utc_offset: 9,
+        country: 111,
+        permissions: UserPermission::Normal
+          | UserPermission::Supporter
+          | UserPermission::Peppy as u8,
+        mode: Mode::Catch,
+        longitude: 139.69,
+        latitude: 35.69,
+        global_rank: 12345,
+        last_update: 637441576105762815,
+      }]
+    );
+
+    let player = presence.player_by_name("PEPPY").unwrap();
+    assert!(player.has_permission(UserPermission::Peppy));
+    assert!(!player.has_permission(UserPermission::Moderator));
+    assert!(!player.has_permission(UserPermission::None));
+
+    let mut written = Vec::new();
+    presence.write(&mut written).unwrap();
+    assert_eq!(written, data);
+  }
+}
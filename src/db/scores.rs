@@ -0,0 +1,216 @@
+use std::io::{self, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::replay::Replay;
+
+use super::{write_present_string, DbResult, ReadBytesOsu};
+
+/// Length that scores.db stores in place of the replay data length, since it has no action data.
+const NO_ACTION_DATA: i32 = -1;
+
+#[derive(Debug, Clone)]
+/// Scores of one beatmap in scores.db
+pub struct ScoresDbBeatmap {
+  /// MD5 hash of the beatmap.
+  pub hash: String,
+
+  /// The local scores set on the beatmap.
+  ///
+  /// These are stored like replays, except that they have no action data, which osu! keeps in
+  /// separate files in its `Data/r` folder instead.
+  pub scores: Vec<Replay>,
+}
+
+#[derive(Debug, Clone)]
+/// scores.db object
+pub struct ScoresDb {
+  /// Version of the game that wrote the file (e.g. 20150204)
+  pub version: u32,
+
+  /// The beatmaps that have local scores
+  pub beatmaps: Vec<ScoresDbBeatmap>,
+}
+
+impl ScoresDb {
+  /// Parse the scores.db data from a reader.
+  pub fn parse(mut reader: impl io::BufRead) -> DbResult<ScoresDb> {
+    let version = reader.read_u32::<LittleEndian>()?;
+    let beatmap_count = reader.read_u32::<LittleEndian>()?;
+    let beatmaps = (0..beatmap_count)
+      .map(|_| {
+        let hash = reader.read_uleb128_string()?;
+        let score_count = reader.read_u32::<LittleEndian>()?;
+        let scores = (0..score_count)
+          .map(|_| {
+            Replay::parse_with(&mut reader, |reader, length| {
+              if length as i32 == NO_ACTION_DATA {
+                return Ok(Vec::new());
+              }
+              let mut action_data = vec![0; length as usize];
+              reader.read_exact(&mut action_data)?;
+              Ok(action_data)
+            })
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(ScoresDbBeatmap { hash, scores })
+      })
+      .collect::<DbResult<Vec<_>>>()?;
+
+    Ok(ScoresDb { version, beatmaps })
+  }
+
+  /// Writes the scores.db data to a writer.
+  pub fn write<W: Write>(&self, mut w: W) -> DbResult<()> {
+    w.write_u32::<LittleEndian>(self.version)?;
+    w.write_u32::<LittleEndian>(self.beatmaps.len() as u32)?;
+    for beatmap in &self.beatmaps {
+      write_present_string(&mut w, &beatmap.hash)?;
+      w.write_u32::<LittleEndian>(beatmap.scores.len() as u32)?;
+      for score in &beatmap.scores {
+        score.write_with(&mut w, |w, action_data| {
+          if action_data.is_empty() {
+            w.write_i32::<LittleEndian>(NO_ACTION_DATA)?;
+          } else {
+            w.write_u32::<LittleEndian>(action_data.len() as u32)?;
+            w.write_all(action_data)?;
+          }
+          Ok(())
+        })?;
+      }
+    }
+    Ok(())
+  }
+
+  /// The local scores of the beatmap with the given MD5 hash.
+  pub fn scores(&self, hash: &str) -> &[Replay] {
+    self
+      .beatmaps
+      .iter()
+      .find(|beatmap| beatmap.hash == hash)
+      .map(|beatmap| beatmap.scores.as_slice())
+      .unwrap_or_default()
+  }
+
+  /// Adds a score to the beatmap it was set on, which is added if it has no scores yet. Returns
+  /// `false` without adding anything if a score with the same replay hash is already there.
+  ///
+  /// The action data of the score is kept, but osu! doesn't expect any in scores.db, so it
+  /// should usually be cleared first.
+  pub fn add_score(&mut self, score: Replay) -> bool {
+    let index = match self
+      .beatmaps
+      .iter()
+      .position(|beatmap| beatmap.hash == score.beatmap_hash)
+    {
+      Some(index) => index,
+      None => {
+        self.beatmaps.push(ScoresDbBeatmap {
+          hash: score.beatmap_hash.clone(),
+          scores: Vec::new(),
+        });
+        self.beatmaps.len() - 1
+      }
+    };
+
+    let scores = &mut self.beatmaps[index].scores;
+    if scores
+      .iter()
+      .any(|existing| existing.replay_hash == score.replay_hash)
+    {
+      return false;
+    }
+    scores.push(score);
+    true
+  }
+
+  /// Adds every score of another scores.db, leaving out the ones that are already here. Returns
+  /// how many scores were added.
+  pub fn merge(&mut self, other: ScoresDb) -> usize {
+    other
+      .beatmaps
+      .into_iter()
+      .flat_map(|beatmap| beatmap.scores)
+      .map(|score| self.add_score(score))
+      .filter(|added| *added)
+      .count()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::Cursor;
+
+  use crate::data::Mode;
+  use crate::replay::Replay;
+
+  use super::ScoresDb;
+
+  fn score(path: &str) -> Replay {
+    let mut file = File::open(path).unwrap();
+    let mut replay = Replay::parse_skip_actions(&mut file).unwrap();
+    replay.life_graph.clear();
+    replay
+  }
+
+  #[test]
+  fn test_scoresdb_roundtrip() {
+    let mut scores = ScoresDb {
+      version: 20201210,
+      beatmaps: Vec::new(),
+    };
+    assert!(
+      scores.add_score(score("tests/files/replay-osu_1816113_2892542031.osr"))
+    );
+    assert!(
+      scores.add_score(score("tests/files/replay-osu_2058788_3017707256.osr"))
+    );
+
+    let mut written = Vec::new();
+    scores.write(&mut written).unwrap();
+    let parsed = ScoresDb::parse(Cursor::new(&written)).unwrap();
+    assert_eq!(parsed.version, 20201210);
+    assert_eq!(parsed.beatmaps.len(), 2);
+    for (beatmap, original) in parsed.beatmaps.iter().zip(&scores.beatmaps) {
+      assert_eq!(beatmap.hash, original.hash);
+      assert_eq!(beatmap.scores.len(), 1);
+      let (score, original) = (&beatmap.scores[0], &original.scores[0]);
+      assert_eq!(score.mode, Mode::Osu);
+      assert_eq!(score.replay_hash, original.replay_hash);
+      assert_eq!(score.player_username, original.player_username);
+      assert_eq!(score.score, original.score);
+      assert_eq!(score.statistics(), original.statistics());
+      assert_eq!(score.score_id, original.score_id);
+      assert!(score.action_data.is_empty());
+    }
+
+    let mut rewritten = Vec::new();
+    parsed.write(&mut rewritten).unwrap();
+    assert_eq!(rewritten, written);
+  }
+
+  #[test]
+  fn test_scoresdb_merge() {
+    let first = score("tests/files/replay-osu_1816113_2892542031.osr");
+    let second = score("tests/files/replay-osu_2058788_3017707256.osr");
+
+    let mut scores = ScoresDb {
+      version: 20201210,
+      beatmaps: Vec::new(),
+    };
+    assert!(scores.add_score(first.clone()));
+    assert!(!scores.add_score(first.clone()));
+
+    let mut other = scores.clone();
+    other.add_score(second.clone());
+    assert_eq!(scores.merge(other), 1);
+    assert_eq!(scores.beatmaps.len(), 2);
+    assert_eq!(scores.scores(&first.beatmap_hash).len(), 1);
+    assert_eq!(
+      scores.scores(&second.beatmap_hash)[0].replay_hash,
+      second.replay_hash
+    );
+    assert!(scores.scores("missing").is_empty());
+  }
+}
@@ -74,6 +74,14 @@ pub struct SpinnerInfo {
   pub end_time: Millis,
 }
 
+/// Extra information provided by an osu!mania hold note.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HoldNoteInfo {
+  /// The time at which the hold note ends.
+  pub end_time: Millis,
+}
+
 /// Distinguishes between different types of hit objects.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -86,6 +94,9 @@ pub enum HitObjectKind {
 
   /// Spinner.
   Spinner(SpinnerInfo),
+
+  /// Hold note (osu!mania only).
+  HoldNote(HoldNoteInfo),
 }
 
 impl HitObjectKind {
@@ -103,6 +114,11 @@ impl HitObjectKind {
   pub fn is_spinner(&self) -> bool {
     matches!(self, HitObjectKind::Spinner(_))
   }
+
+  /// Is the given HitObject an osu!mania hold note?
+  pub fn is_hold_note(&self) -> bool {
+    matches!(self, HitObjectKind::HoldNote(_))
+  }
 }
 
 /// Represents a single hit object.
@@ -293,6 +309,26 @@ impl FromStr for HitObject {
           end_time: Millis(end_time),
         })
       }
+
+      // mania hold note, the end time is the first field of the hitsample
+      o if (o & 128) == 128 => {
+        let (end_time, sample) = match parts.get(5) {
+          Some(s) => match s.split_once(':') {
+            Some((end_time, sample)) => (end_time, Some(sample)),
+            None => (*s, None),
+          },
+          None => return Err(ParseError::InvalidObjectType(o)),
+        };
+        let end_time = end_time.parse::<i32>()?;
+        sample_info = if let Some(s) = sample.filter(|s| !s.is_empty()) {
+          SampleInfo::from_str(s)?
+        } else {
+          SampleInfo::default()
+        };
+        HitObjectKind::HoldNote(HoldNoteInfo {
+          end_time: Millis(end_time),
+        })
+      }
       o => {
         return Err(ParseError::InvalidObjectType(o));
       }
@@ -321,6 +357,7 @@ impl fmt::Display for HitObject {
       HitObjectKind::Circle => 1,
       HitObjectKind::Slider { .. } => 2,
       HitObjectKind::Spinner { .. } => 8,
+      HitObjectKind::HoldNote { .. } => 128,
     } | if self.new_combo { 4 } else { 0 }
       | self.skip_color;
     write!(f, ",{}", obj_type)?;
@@ -364,6 +401,12 @@ impl fmt::Display for HitObject {
       HitObjectKind::Spinner(info) => {
         write!(f, ",{}", info.end_time.0)?;
       }
+
+      HitObjectKind::HoldNote(info) => {
+        // hold notes separate the end time from the hitsample with a colon
+        write!(f, ",{}:{}", info.end_time.0, self.sample_info)?;
+        return Ok(());
+      }
     }
 
     // hitsample
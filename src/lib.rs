@@ -32,6 +32,7 @@ pub extern crate ordered_float;
 pub(crate) mod float;
 pub(crate) mod utils;
 
+pub mod analysis;
 /// client for the osu api
 #[cfg(feature = "apiv1")]
 #[cfg_attr(docsrs, doc(cfg(feature = "apiv1")))]
@@ -39,6 +40,7 @@ pub mod apiv1;
 #[cfg(feature = "apiv2")]
 #[cfg_attr(docsrs, doc(cfg(feature = "apiv2")))]
 pub mod apiv2;
+pub mod autoplay;
 /// beatmaps
 pub mod beatmap;
 /// defines the color struct
@@ -59,6 +61,8 @@ pub mod hitsounds;
 /// math
 pub mod math;
 pub mod replay;
+pub mod score;
+pub mod sim;
 /// calculating slider body shapes.
 pub mod spline;
 /// timing and timing points.
@@ -72,6 +76,8 @@ pub mod prelude {
   #[cfg(feature = "apiv2")]
   #[cfg_attr(docsrs, doc(cfg(feature = "apiv2")))]
   pub use crate::apiv2::*;
+  pub use crate::analysis::*;
+  pub use crate::autoplay::*;
   pub use crate::beatmap::*;
   #[cfg(feature = "experimental-diff-calc")]
   #[cfg_attr(docsrs, doc(cfg(feature = "experimental-diff-calc")))]
@@ -84,6 +90,8 @@ pub mod prelude {
   pub use crate::hitsounds::*;
   pub use crate::math::*;
   pub use crate::replay::*;
+  pub use crate::score::*;
+  pub use crate::sim::*;
   pub use crate::spline::*;
   pub use crate::timing::*;
   pub use ordered_float::*;
@@ -1,12 +1,14 @@
-#[cfg(feature = "replay-data")]
-#[cfg_attr(docsrs, doc(cfg(feature = "replay-data")))]
-use std::io::BufRead;
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use std::io::{BufRead, BufReader};
 
 use crate::timing::Millis;
 
-#[cfg(feature = "replay-data")]
-#[cfg_attr(docsrs, doc(cfg(feature = "replay-data")))]
-use super::ReplayResult;
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use super::{lzma::Decoder, ReplayError, ReplayResult};
+
+/// The time of the frame that holds the RNG seed instead of any input.
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+const SEED_FRAME_TIME: i32 = -12345;
 
 /// An action by the player while playing the map
 #[derive(Debug, Clone)]
@@ -29,7 +31,8 @@ pub struct ReplayAction {
 
 bitflags! {
     /// The buttons being pressed during a frame of a replay
-    #[derive(Clone, Debug)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
     pub struct Buttons: u32 {
         /// First mouse button
         const M1 = 1;
@@ -60,53 +63,183 @@ pub struct ReplayActionData {
 }
 
 impl ReplayActionData {
-  #[cfg(feature = "replay-data")]
-  #[cfg_attr(docsrs, doc(cfg(feature = "replay-data")))]
-  /// create a new ReplayActionParser from a BufRead
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+  )]
+  /// Decompresses and parses all of the frames of compressed action data.
+  ///
+  /// To go through the frames without keeping all of them in memory, use [`ReplayFrames`]
+  /// instead.
   pub fn parse(data: impl BufRead) -> ReplayResult<Self> {
-    use super::ReplayError;
-
-    let data = super::lzma::decode(data)?;
-    let string = String::from_utf8(data)?;
-    let mut frames = string
-      .split(',')
-      .filter(|action_str| !action_str.trim().is_empty())
-      .map(|action_str| {
-        let mut parts = action_str.split('|');
-        let time = Millis(parts.next().unwrap().parse::<i32>()?);
-        let x = parts.next().unwrap().parse::<f32>()?;
-        let y = parts.next().unwrap().parse::<f32>()?;
-        let bits = parts.next().unwrap().parse::<u32>()?;
-
-        let buttons = if time.0 == -12345 {
-          // allow this
-          unsafe { Buttons::from_bits_unchecked(bits) }
-        } else {
-          Buttons::from_bits(bits).ok_or(ReplayError::InvalidButtons(bits))?
-        };
-        Ok(ReplayAction {
-          time,
-          x,
-          y,
-          buttons,
-        })
-      })
-      .collect::<ReplayResult<Vec<_>>>()?;
-
-    let has_seed = matches!(
-      frames.last(),
-      Some(ReplayAction {
-        time: Millis(-12345),
-        ..
-      })
-    );
-    let rng_seed = if has_seed {
-      let last_element = frames.pop().expect("has_seed checked");
-      Some(last_element.buttons.bits())
-    } else {
-      None
+    let mut parser = ReplayFrames::new(data)?;
+    let frames = parser.by_ref().collect::<ReplayResult<Vec<_>>>()?;
+    Ok(ReplayActionData {
+      frames,
+      rng_seed: parser.rng_seed(),
+    })
+  }
+}
+
+/// An iterator that decompresses and parses the frames of compressed action data as they're
+/// needed, so that the whole replay never has to be in memory at once.
+///
+/// With the `replay-data` feature, decompressed data only becomes available a dictionary at a
+/// time (2MB for replays written by osu!), which is more than most replays hold. Memory use stays
+/// bounded by the dictionary size however long the replay is.
+///
+/// If the last frame of the replay holds the RNG seed, it isn't returned by the iterator, and
+/// [`rng_seed`][Self::rng_seed] can be used to get the seed once all of the frames have been read.
+///
+/// Malformed frames are reported as [`ReplayError::InvalidFrame`] along with their index, and the
+/// iterator carries on with the next frame. Errors during decompression end the iteration.
+///
+/// ```no_run
+/// # use libosu::replay::Replay;
+/// # fn invisible(replay: Replay) -> anyhow::Result<()> {
+/// #
+/// let mut frames = replay.action_frames()?;
+/// let mut time = 0;
+/// for frame in frames.by_ref() {
+///     time += frame?.time.0;
+/// }
+/// println!("replay ends at {}ms, seed {:?}", time, frames.rng_seed());
+///
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[cfg_attr(
+  docsrs,
+  doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+)]
+pub struct ReplayFrames<R> {
+  reader: BufReader<Decoder<R>>,
+  buf: Vec<u8>,
+  index: usize,
+  pending: Option<ReplayResult<ReplayAction>>,
+  rng_seed: Option<u32>,
+  done: bool,
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+impl<R: BufRead> ReplayFrames<R> {
+  /// Starts reading frames from the given compressed action data.
+  pub fn new(data: R) -> ReplayResult<Self> {
+    Ok(ReplayFrames {
+      reader: BufReader::new(Decoder::new(data)?),
+      buf: Vec::new(),
+      index: 0,
+      pending: None,
+      rng_seed: None,
+      done: false,
+    })
+  }
+
+  /// The RNG seed stored in the replay, once all of the frames have been read.
+  ///
+  /// Only replays from version 20130319 or later have a seed.
+  pub fn rng_seed(&self) -> Option<u32> {
+    self.rng_seed
+  }
+
+  /// Reads and parses the next frame, skipping over empty ones.
+  fn read_frame(&mut self) -> Option<ReplayResult<ReplayAction>> {
+    loop {
+      if self.done {
+        return None;
+      }
+
+      self.buf.clear();
+      let index = self.index;
+      match self.reader.read_until(b',', &mut self.buf) {
+        Ok(0) => {
+          self.done = true;
+          return None;
+        }
+        Ok(_) => {}
+        Err(err) => {
+          self.done = true;
+          return Some(Err(ReplayError::InvalidFrame {
+            index,
+            source: Box::new(err.into()),
+          }));
+        }
+      }
+
+      if self.buf.last() == Some(&b',') {
+        self.buf.pop();
+      }
+      if self.buf.iter().all(u8::is_ascii_whitespace) {
+        continue;
+      }
+
+      self.index += 1;
+      return Some(self.parse_frame().map_err(|err| {
+        ReplayError::InvalidFrame {
+          index,
+          source: Box::new(err),
+        }
+      }));
+    }
+  }
+
+  fn parse_frame(&mut self) -> ReplayResult<ReplayAction> {
+    // the buffer is taken out to avoid copying it into a String
+    let frame = String::from_utf8(std::mem::take(&mut self.buf))?;
+    let result = parse_frame(&frame);
+    self.buf = frame.into_bytes();
+    result
+  }
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+impl<R: BufRead> Iterator for ReplayFrames<R> {
+  type Item = ReplayResult<ReplayAction>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let frame = match self.pending.take() {
+      Some(frame) => frame,
+      None => self.read_frame()?,
     };
 
-    Ok(ReplayActionData { frames, rng_seed })
+    if let Ok(action) = &frame {
+      if action.time.0 == SEED_FRAME_TIME {
+        // the seed is only stored in the last frame
+        match self.read_frame() {
+          None => {
+            self.rng_seed = Some(action.buttons.bits());
+            return None;
+          }
+          next => self.pending = next,
+        }
+      }
+    }
+    Some(frame)
   }
 }
+
+/// Parses a single `time|x|y|buttons` frame.
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+fn parse_frame(frame: &str) -> ReplayResult<ReplayAction> {
+  let mut parts = frame.trim().split('|');
+  let mut next = || parts.next().ok_or(ReplayError::FrameMissing);
+  let time = Millis(next()?.parse::<i32>()?);
+  let x = next()?.parse::<f32>()?;
+  let y = next()?.parse::<f32>()?;
+  let bits = next()?.parse::<u32>()?;
+
+  let buttons = if time.0 == SEED_FRAME_TIME {
+    // the seed is stored in place of the buttons
+    Buttons::from_bits_retain(bits)
+  } else {
+    Buttons::from_bits(bits).ok_or(ReplayError::InvalidButtons(bits))?
+  };
+  Ok(ReplayAction {
+    time,
+    x,
+    y,
+    buttons,
+  })
+}
@@ -0,0 +1,256 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::data::{Grade, HitCounts, Mode, Mods};
+use crate::score::ScoreStatistics;
+use crate::sim::SimulationResult;
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use super::ReplayActionData;
+use super::{Replay, ReplayResult};
+
+/// The game version that [`ReplayBuilder`] writes into replays unless told otherwise.
+pub const DEFAULT_REPLAY_VERSION: u32 = 20240123;
+
+/// The number of .NET ticks (100 nanoseconds) between 0001-01-01 and the Unix epoch.
+const UNIX_EPOCH_TICKS: u64 = 621_355_968_000_000_000;
+
+/// Converts a point in time to the number of .NET ticks since 0001-01-01, which is the format
+/// of [`Replay::timestamp`]. Times before 0001-01-01 become 0.
+pub fn system_time_to_ticks(time: SystemTime) -> u64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(since) => UNIX_EPOCH_TICKS + (since.as_nanos() / 100) as u64,
+    Err(err) => {
+      let before = (err.duration().as_nanos() / 100) as u64;
+      UNIX_EPOCH_TICKS.saturating_sub(before)
+    }
+  }
+}
+
+/// Converts a number of .NET ticks since 0001-01-01, such as [`Replay::timestamp`], to a point
+/// in time.
+pub fn ticks_to_system_time(ticks: u64) -> SystemTime {
+  let nanos = |ticks: u64| Duration::from_nanos(ticks * 100);
+  if ticks >= UNIX_EPOCH_TICKS {
+    UNIX_EPOCH + nanos(ticks - UNIX_EPOCH_TICKS)
+  } else {
+    UNIX_EPOCH - nanos(UNIX_EPOCH_TICKS - ticks)
+  }
+}
+
+/// Builds a [`Replay`] from the score it should contain, filling in the fields that the game
+/// expects to be consistent with each other.
+///
+/// Unless set otherwise, the replay gets [`DEFAULT_REPLAY_VERSION`] as its version and the
+/// current time as its timestamp, and the grade used for the replay hash is worked out from the
+/// judgement counts.
+///
+/// ```no_run
+/// # use libosu::{beatmap::Beatmap, data::{Mode, Mods}, replay::{Replay, ReplayActionData}};
+/// # use libosu::sim::OsuSimulator;
+/// # #[cfg(feature = "replay-data")]
+/// # fn invisible(beatmap: Beatmap, actions: ReplayActionData) -> anyhow::Result<()> {
+/// #
+/// let result = OsuSimulator::new(&beatmap, Mods::Hidden).simulate(&actions);
+/// let replay = Replay::builder(Mode::Osu, "06b536749d5a59536983854be90504ee")
+///     .player_username("peppy")
+///     .mods(Mods::Hidden)
+///     .simulation(&result)
+///     .score(1_000_000)
+///     .action_data(actions)
+///     .build()?;
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplayBuilder {
+  mode: Mode,
+  version: u32,
+  beatmap_hash: String,
+  player_username: String,
+  counts: HitCounts,
+  score: u32,
+  max_combo: u16,
+  perfect: Option<bool>,
+  mods: Mods,
+  grade: Option<Grade>,
+  life_graph: Vec<(i32, f64)>,
+  timestamp: u64,
+  score_id: Option<u64>,
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  action_data: Option<ReplayActionData>,
+}
+
+impl ReplayBuilder {
+  /// Starts building a replay of the given mode for the beatmap with the given MD5 hash.
+  pub fn new(mode: Mode, beatmap_hash: impl Into<String>) -> Self {
+    ReplayBuilder {
+      mode,
+      version: DEFAULT_REPLAY_VERSION,
+      beatmap_hash: beatmap_hash.into(),
+      player_username: String::new(),
+      counts: HitCounts::default(),
+      score: 0,
+      max_combo: 0,
+      perfect: None,
+      mods: Mods::None,
+      grade: None,
+      life_graph: Vec::new(),
+      timestamp: system_time_to_ticks(SystemTime::now()),
+      score_id: None,
+      #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+      action_data: None,
+    }
+  }
+
+  /// Sets the game version the replay claims to be recorded on.
+  pub fn version(mut self, version: u32) -> Self {
+    self.version = version;
+    self
+  }
+
+  /// Sets the name of the player.
+  pub fn player_username(mut self, player_username: impl Into<String>) -> Self {
+    self.player_username = player_username.into();
+    self
+  }
+
+  /// Sets the judgement counts. Unless [`perfect`][Self::perfect] is set, the replay counts as
+  /// perfect if there are no misses.
+  pub fn hit_counts(mut self, counts: HitCounts) -> Self {
+    self.counts = counts;
+    self
+  }
+
+  /// Takes the judgement counts and max combo from a simulated play. The replay counts as
+  /// perfect if no judgement that affects the combo was missed, including slider ends.
+  pub fn simulation(mut self, result: &SimulationResult) -> Self {
+    self.counts = result.counts;
+    self.max_combo = result.max_combo.min(u16::MAX as u32) as u16;
+    self.perfect = Some(!result.judgements.iter().any(|judgement| {
+      judgement.kind.affects_combo() && judgement.result.is_miss()
+    }));
+    self
+  }
+
+  /// Sets the total score.
+  pub fn score(mut self, score: u32) -> Self {
+    self.score = score;
+    self
+  }
+
+  /// Sets the highest combo reached.
+  pub fn max_combo(mut self, max_combo: u16) -> Self {
+    self.max_combo = max_combo;
+    self
+  }
+
+  /// Sets whether the play was a full combo, instead of deriving it from the judgements.
+  pub fn perfect(mut self, perfect: bool) -> Self {
+    self.perfect = Some(perfect);
+    self
+  }
+
+  /// Sets the mods that were used.
+  pub fn mods(mut self, mods: Mods) -> Self {
+    self.mods = mods;
+    self
+  }
+
+  /// Sets the grade that goes into the replay hash, instead of working it out from the
+  /// judgement counts.
+  pub fn grade(mut self, grade: Grade) -> Self {
+    self.grade = Some(grade);
+    self
+  }
+
+  /// Sets the life graph, as pairs of times and life between 0 and 1.
+  pub fn life_graph(mut self, life_graph: Vec<(i32, f64)>) -> Self {
+    self.life_graph = life_graph;
+    self
+  }
+
+  /// Sets when the replay was recorded.
+  pub fn timestamp(mut self, time: SystemTime) -> Self {
+    self.timestamp = system_time_to_ticks(time);
+    self
+  }
+
+  /// Sets the online ID of the score.
+  pub fn score_id(mut self, score_id: u64) -> Self {
+    self.score_id = Some(score_id);
+    self
+  }
+
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+  )]
+  /// Sets the actions of the replay, which are compressed when the replay is built.
+  pub fn action_data(mut self, action_data: ReplayActionData) -> Self {
+    self.action_data = Some(action_data);
+    self
+  }
+
+  /// Builds the replay, computing its hash the same way osu!stable does.
+  pub fn build(self) -> ReplayResult<Replay> {
+    let grade = self.grade.unwrap_or_else(|| {
+      ScoreStatistics::new(self.mode, self.counts, self.mods).grade()
+    });
+    let replay_hash = stable_replay_hash(
+      self.max_combo,
+      &self.player_username,
+      &self.beatmap_hash,
+      self.score,
+      grade,
+    );
+
+    #[allow(unused_mut)]
+    let mut replay = Replay {
+      mode: self.mode,
+      version: self.version,
+      beatmap_hash: self.beatmap_hash,
+      player_username: self.player_username,
+      replay_hash,
+      count_300: self.counts.count_300,
+      count_100: self.counts.count_100,
+      count_50: self.counts.count_50,
+      count_geki: self.counts.count_geki,
+      count_katu: self.counts.count_katu,
+      count_miss: self.counts.count_miss,
+      score: self.score,
+      max_combo: self.max_combo,
+      perfect: self.perfect.unwrap_or(self.counts.count_miss == 0),
+      mods: self.mods,
+      life_graph: self.life_graph,
+      timestamp: self.timestamp,
+      action_data: Vec::new(),
+      score_id: self.score_id,
+      target_practice_total_accuracy: None,
+      score_info: None,
+    };
+
+    #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+    if let Some(action_data) = &self.action_data {
+      replay.update_action_data(action_data)?;
+    }
+
+    Ok(replay)
+  }
+}
+
+/// The replay hash that osu!stable writes into the replays it saves.
+pub(crate) fn stable_replay_hash(
+  max_combo: u16,
+  player_username: &str,
+  beatmap_hash: &str,
+  score: u32,
+  grade: Grade,
+) -> String {
+  let input = format!(
+    "{}osu{}{}{}{}",
+    max_combo, player_username, beatmap_hash, score, grade
+  );
+  format!("{:x}", md5::compute(input))
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+/// The extra information about a score that osu!lazer stores at the end of exported replays.
+///
+/// Fields that aren't known to libosu are kept in `extra`, so that they're written back when the
+/// replay is saved.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LazerScoreInfo {
+  /// Online ID of this score, or -1 if it wasn't submitted
+  pub online_id: i64,
+
+  /// The mods that were used, including their settings
+  pub mods: Vec<LazerMod>,
+
+  /// The number of each hit result that was scored, keyed by the name of the result in lazer
+  /// (`great`, `ok`, `meh`, `miss`, `large_tick_hit`, `slider_tail_hit`, ...)
+  pub statistics: BTreeMap<String, u32>,
+
+  /// The highest number of each hit result that could have been scored on the map
+  pub maximum_statistics: BTreeMap<String, u32>,
+
+  /// The version of the client that set the score, such as `2024.906.2`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub client_version: Option<String>,
+
+  /// The rank achieved, such as `XH` or `A`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rank: Option<String>,
+
+  /// The ID of the player, or -1 if unknown
+  pub user_id: i64,
+
+  /// The total score before mod multipliers were applied
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub total_score_without_mods: Option<i64>,
+
+  /// Any other fields
+  #[serde(flatten)]
+  pub extra: Map<String, Value>,
+}
+
+impl Default for LazerScoreInfo {
+  fn default() -> Self {
+    LazerScoreInfo {
+      online_id: -1,
+      mods: Vec::new(),
+      statistics: BTreeMap::new(),
+      maximum_statistics: BTreeMap::new(),
+      client_version: None,
+      rank: None,
+      user_id: -1,
+      total_score_without_mods: None,
+      extra: Map::new(),
+    }
+  }
+}
+
+/// A mod in osu!lazer, along with its settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LazerMod {
+  /// The acronym of the mod, such as `DT`
+  pub acronym: String,
+
+  /// The settings of the mod that differ from the defaults, such as `speed_change` for DT
+  #[serde(default, skip_serializing_if = "Map::is_empty")]
+  pub settings: Map<String, Value>,
+}
+
+impl LazerMod {
+  /// Creates a mod without any settings.
+  pub fn new(acronym: impl Into<String>) -> Self {
+    LazerMod {
+      acronym: acronym.into(),
+      settings: Map::new(),
+    }
+  }
+}
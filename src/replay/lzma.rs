@@ -1,4 +1,4 @@
-pub use lzma_impl::{decode, encode};
+pub use lzma_impl::{encode, Decoder};
 
 static_assertions::assert_cfg!(
     not(all(feature = "replay-data", feature = "replay-data-xz2")),
@@ -7,16 +7,71 @@ static_assertions::assert_cfg!(
 
 #[cfg(all(feature = "replay-data", not(feature = "replay-data-xz2")))]
 mod lzma_impl {
-  use std::io::BufRead;
+  use std::io::{self, BufRead, Read, Write};
 
-  use lzma_rs::{lzma_compress, lzma_decompress};
+  use lzma_rs::decompress::Stream;
+  use lzma_rs::lzma_compress;
 
   use super::super::ReplayResult;
 
-  pub fn decode(mut input: impl BufRead) -> ReplayResult<Vec<u8>> {
-    let mut data = Vec::new();
-    lzma_decompress(&mut input, &mut data)?;
-    Ok(data)
+  /// Decompresses an LZMA stream as it's being read, so only the dictionary and the output of the
+  /// last chunk of input are kept in memory.
+  pub struct Decoder<R> {
+    input: R,
+    stream: Option<Stream<Vec<u8>>>,
+    output: Vec<u8>,
+    position: usize,
+  }
+
+  impl<R: BufRead> Decoder<R> {
+    pub fn new(input: R) -> ReplayResult<Self> {
+      Ok(Decoder {
+        input,
+        stream: Some(Stream::new(Vec::new())),
+        output: Vec::new(),
+        position: 0,
+      })
+    }
+
+    /// Feeds the next chunk of input to the decompressor, returning false once it's finished.
+    fn fill(&mut self) -> io::Result<bool> {
+      let stream = match self.stream.as_mut() {
+        Some(stream) => stream,
+        None => return Ok(false),
+      };
+
+      let chunk = self.input.fill_buf()?;
+      if chunk.is_empty() {
+        let stream = self.stream.take().expect("checked above");
+        self.output = stream.finish().map_err(io::Error::from)?;
+      } else {
+        let length = chunk.len();
+        stream.write_all(chunk)?;
+        self.input.consume(length);
+        let output = stream.get_output_mut().expect("stream hasn't failed");
+        std::mem::swap(&mut self.output, output);
+      }
+      self.position = 0;
+      Ok(true)
+    }
+  }
+
+  impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      while self.position == self.output.len() {
+        self.output.clear();
+        self.position = 0;
+        if !self.fill()? {
+          return Ok(0);
+        }
+      }
+
+      let available = &self.output[self.position..];
+      let length = available.len().min(buf.len());
+      buf[..length].copy_from_slice(&available[..length]);
+      self.position += length;
+      Ok(length)
+    }
   }
 
   pub fn encode(mut input: impl BufRead) -> ReplayResult<Vec<u8>> {
@@ -34,14 +89,20 @@ mod lzma_impl {
 
   use super::super::ReplayResult;
 
-  pub fn decode(input: impl BufRead) -> ReplayResult<Vec<u8>> {
-    let lzma_decoder = Stream::new_lzma_decoder(std::u32::MAX as u64)?;
-    let mut xz_decoder = XzDecoder::new_stream(input, lzma_decoder);
+  /// Decompresses an LZMA stream as it's being read.
+  pub struct Decoder<R>(XzDecoder<R>);
 
-    let mut data = Vec::new();
-    xz_decoder.read_to_end(&mut data)?;
+  impl<R: BufRead> Decoder<R> {
+    pub fn new(input: R) -> ReplayResult<Self> {
+      let lzma_decoder = Stream::new_lzma_decoder(u32::MAX as u64)?;
+      Ok(Decoder(XzDecoder::new_stream(input, lzma_decoder)))
+    }
+  }
 
-    Ok(data)
+  impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      self.0.read(buf)
+    }
   }
 
   pub fn encode(input: impl BufRead) -> ReplayResult<Vec<u8>> {
@@ -60,18 +60,41 @@
 //! [1]: https://osu.ppy.sh/wiki/en/osu%21_File_Formats/Osr_%28file_format%29
 
 mod actions;
+mod builder;
+#[cfg(feature = "serde")]
+mod lazer;
+mod timeline;
+mod transform;
 
 #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
 mod lzma;
 
-use std::io::{Read, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::time::SystemTime;
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::data::{Mode, Mods};
+use crate::data::{HitCounts, Mode, Mods};
 use crate::db::{ReadBytesOsu, WriteBytesOsu};
+use crate::score::{ScoreStatistics, StandardisedScoreEstimate};
 
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+pub use self::actions::ReplayFrames;
 pub use self::actions::{Buttons, ReplayAction, ReplayActionData};
+pub use self::builder::{
+  system_time_to_ticks, ticks_to_system_time, ReplayBuilder,
+  DEFAULT_REPLAY_VERSION,
+};
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub use self::lazer::{LazerMod, LazerScoreInfo};
+pub use self::timeline::{
+  Key, KeyEvent, KeyEventKind, KeyPressCounts, Timeline, TimelineFrame,
+};
+
+/// The first replay version written by osu!lazer. Replays from this version on end with
+/// [`Replay::score_info`].
+pub const FIRST_LAZER_VERSION: u32 = 30000001;
 
 /// Result type for Replay processing
 pub type ReplayResult<T, E = ReplayError> = std::result::Result<T, E>;
@@ -104,6 +127,11 @@ pub enum ReplayError {
   #[error("error parsing float: {0}")]
   ParseFloat(#[from] std::num::ParseFloatError),
 
+  #[cfg(feature = "serde")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+  #[error("error parsing score info: {0}")]
+  Json(#[from] serde_json::Error),
+
   #[error("binary data error: {0}")]
   Binary(#[from] crate::db::binary::Error),
 
@@ -118,6 +146,15 @@ pub enum ReplayError {
 
   #[error("invalid buttons: {0}")]
   InvalidButtons(u32),
+
+  #[error("missing field in replay frame")]
+  FrameMissing,
+
+  #[error("error in replay frame {index}: {source}")]
+  InvalidFrame {
+    index: usize,
+    source: Box<ReplayError>,
+  },
 }
 
 /// A replay object.
@@ -200,14 +237,81 @@ pub struct Replay {
   /// Total accuracy of all hits.
   /// Divide this value by the number of targets to find the actual accuracy
   pub target_practice_total_accuracy: Option<f64>,
+
+  /// The compressed score information that osu!lazer adds to the end of replays with a version of
+  /// at least [`FIRST_LAZER_VERSION`].
+  ///
+  /// If the features `serde` and `replay-data` are enabled, the function `parse_score_info` can be
+  /// used to decompress and parse it.
+  pub score_info: Option<Vec<u8>>,
 }
 
 impl Replay {
-  /// Parse the replay header from a replay file.
+  /// Starts building a replay from scratch. See [`ReplayBuilder`].
+  pub fn builder(mode: Mode, beatmap_hash: impl Into<String>) -> ReplayBuilder {
+    ReplayBuilder::new(mode, beatmap_hash)
+  }
+
+  /// Parse a replay file.
   ///
-  /// The returned struct will be missing the `action`, `score_id`, and `target_practice_total_accuracy` fields.
-  /// If you need `score_id` or `target_practice_total_accuracy`, but don't want to parse actions use the `parse_skip_actions` function instead.
+  /// The compressed action data is read into memory as it is, and can be decompressed later with
+  /// `parse_action_data`. If you only need the rest of the replay, use
+  /// [`parse_skip_actions`][Self::parse_skip_actions] instead.
   pub fn parse<R: Read>(reader: &mut R) -> ReplayResult<Replay> {
+    Replay::parse_with(reader, |reader, length| {
+      let mut action_data = vec![0; length as usize];
+      reader.read_exact(&mut action_data)?;
+      Ok(action_data)
+    })
+  }
+
+  /// Parse a replay file without reading its action data, by seeking past it.
+  ///
+  /// Everything else, including the `score_id` and `target_practice_total_accuracy` that come after
+  /// the action data, is parsed as usual, and the `action_data` of the returned replay is empty.
+  /// This is a lot faster than [`parse`][Self::parse] when indexing a large number of replays.
+  ///
+  /// ```no_run
+  /// # use std::{fs::File, io::BufReader};
+  /// # use libosu::replay::Replay;
+  /// # fn invisible() -> anyhow::Result<()> {
+  /// #
+  /// let mut reader = BufReader::new(File::open("replay.osr")?);
+  /// let replay = Replay::parse_skip_actions(&mut reader)?;
+  /// println!("{} on {}: {:?}", replay.player_username, replay.beatmap_hash, replay.score_id);
+  ///
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn parse_skip_actions<R: Read + Seek>(
+    reader: &mut R,
+  ) -> ReplayResult<Replay> {
+    Replay::parse_with(reader, |reader, length| {
+      reader.seek(SeekFrom::Current(length as i64))?;
+      Ok(Vec::new())
+    })
+  }
+
+  /// Like [`parse_skip_actions`][Self::parse_skip_actions], but for readers that can't seek. The
+  /// action data is read and thrown away as it comes in, instead of being kept in memory.
+  pub fn parse_skip_actions_unseekable<R: Read>(
+    reader: &mut R,
+  ) -> ReplayResult<Replay> {
+    Replay::parse_with(reader, |reader, length| {
+      let skipped = io::copy(&mut reader.take(length as u64), &mut io::sink())?;
+      if skipped != length as u64 {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+      }
+      Ok(Vec::new())
+    })
+  }
+
+  /// Parses a replay, leaving the action data to `action_data`, which is given its length. This
+  /// is shared with scores.db, whose scores are laid out like replays without action data.
+  pub(crate) fn parse_with<R: Read>(
+    reader: &mut R,
+    action_data: impl FnOnce(&mut R, u32) -> ReplayResult<Vec<u8>>,
+  ) -> ReplayResult<Replay> {
     let mode = match reader.read_u8()? {
       0 => Mode::Osu,
       1 => Mode::Taiko,
@@ -257,8 +361,7 @@ impl Replay {
     let timestamp = reader.read_u64::<LittleEndian>()?;
     let replay_data_length = reader.read_u32::<LittleEndian>()?;
 
-    let mut action_data = vec![0; replay_data_length as usize];
-    reader.read_exact(&mut action_data)?;
+    let action_data = action_data(reader, replay_data_length)?;
 
     let score_id = match reader.read_u64::<LittleEndian>()? {
       0 => None,
@@ -270,6 +373,18 @@ impl Replay {
     } else {
       None
     };
+    let score_info = if version >= FIRST_LAZER_VERSION {
+      match reader.read_i32::<LittleEndian>()? {
+        length if length < 0 => None,
+        length => {
+          let mut score_info = vec![0; length as usize];
+          reader.read_exact(&mut score_info)?;
+          Some(score_info)
+        }
+      }
+    } else {
+      None
+    };
 
     Ok(Replay {
       mode,
@@ -294,11 +409,59 @@ impl Replay {
       action_data,
       score_id,
       target_practice_total_accuracy,
+      score_info,
     })
   }
 
+  /// Returns when this replay was recorded, converted from [`timestamp`][Self::timestamp].
+  pub fn time(&self) -> SystemTime {
+    ticks_to_system_time(self.timestamp)
+  }
+
+  /// Returns the judgement counts stored in this replay.
+  pub fn hit_counts(&self) -> HitCounts {
+    HitCounts {
+      count_300: self.count_300,
+      count_100: self.count_100,
+      count_50: self.count_50,
+      count_geki: self.count_geki,
+      count_katu: self.count_katu,
+      count_miss: self.count_miss,
+    }
+  }
+
+  /// Returns the accuracy, grade and mode-specific judgement names of the score in this replay.
+  pub fn statistics(&self) -> ScoreStatistics {
+    ScoreStatistics::new(self.mode, self.hit_counts(), self.mods)
+  }
+
+  /// Estimates the standardised score osu!lazer would give the score in this replay, on a map
+  /// whose highest possible combo is `beatmap_max_combo`. See
+  /// [`ScoreStatistics::estimate_standardised_score`].
+  pub fn estimate_standardised_score(
+    &self,
+    beatmap_max_combo: u32,
+  ) -> StandardisedScoreEstimate {
+    self
+      .statistics()
+      .estimate_standardised_score(self.max_combo as u32, beatmap_max_combo)
+  }
+
   /// Writes this replay to the given writer
-  pub fn write<W: Write>(&self, mut w: W) -> ReplayResult<()> {
+  pub fn write<W: Write>(&self, w: W) -> ReplayResult<()> {
+    self.write_with(w, |w, action_data| {
+      w.write_u32::<LittleEndian>(action_data.len() as u32)?;
+      w.write_all(action_data)?;
+      Ok(())
+    })
+  }
+
+  /// Writes this replay, leaving the length and contents of the action data to `action_data`.
+  pub(crate) fn write_with<W: Write>(
+    &self,
+    mut w: W,
+    action_data: impl FnOnce(&mut W, &[u8]) -> ReplayResult<()>,
+  ) -> ReplayResult<()> {
     w.write_u8(self.mode as u8)?;
     w.write_u32::<LittleEndian>(self.version)?;
     w.write_uleb128_string(&self.beatmap_hash)?;
@@ -312,7 +475,7 @@ impl Replay {
     w.write_u16::<LittleEndian>(self.count_miss)?;
     w.write_u32::<LittleEndian>(self.score)?;
     w.write_u16::<LittleEndian>(self.max_combo)?;
-    w.write_u8(if self.perfect { 0 } else { 1 })?;
+    w.write_u8(self.perfect as u8)?;
     w.write_u32::<LittleEndian>(self.mods.bits())?;
     w.write_uleb128_string(
       &self
@@ -323,17 +486,28 @@ impl Replay {
         .join(","),
     )?;
     w.write_u64::<LittleEndian>(self.timestamp)?;
-    w.write_u32::<LittleEndian>(self.action_data.len() as u32)?;
-    w.write_all(&self.action_data)?;
+    action_data(&mut w, &self.action_data)?;
     w.write_u64::<LittleEndian>(self.score_id.unwrap_or(0))?;
     if let Some(acc) = self.target_practice_total_accuracy {
       w.write_f64::<LittleEndian>(acc)?;
     }
+    if self.version >= FIRST_LAZER_VERSION {
+      match &self.score_info {
+        Some(score_info) => {
+          w.write_i32::<LittleEndian>(score_info.len() as i32)?;
+          w.write_all(score_info)?;
+        }
+        None => w.write_i32::<LittleEndian>(-1)?,
+      }
+    }
     Ok(())
   }
 
-  #[cfg(feature = "replay-data")]
-  #[cfg_attr(docsrs, doc(cfg(feature = "replay-data")))]
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+  )]
   /// Updates the Replay object with action data
   pub fn update_action_data(
     &mut self,
@@ -343,7 +517,7 @@ impl Replay {
       let mut writer = Vec::new();
       for (i, frame) in action_data.frames.iter().enumerate() {
         if i > 0 {
-          writer.write_all(&[b','])?;
+          writer.write_all(b",")?;
         }
 
         let this_frame = format!(
@@ -367,12 +541,69 @@ impl Replay {
     Ok(())
   }
 
-  #[cfg(feature = "replay-data")]
-  #[cfg_attr(docsrs, doc(cfg(feature = "replay-data")))]
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+  )]
   /// Parse and retrieve the actions in the replay
   pub fn parse_action_data(&self) -> ReplayResult<ReplayActionData> {
-    use std::io::Cursor;
-    let cursor = Cursor::new(&self.action_data);
-    ReplayActionData::parse(cursor)
+    ReplayActionData::parse(self.action_data.as_slice())
+  }
+
+  #[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+  )]
+  /// Returns an iterator that parses the actions in the replay one at a time. See
+  /// [`ReplayFrames`].
+  pub fn action_frames(&self) -> ReplayResult<ReplayFrames<&[u8]>> {
+    ReplayFrames::new(self.action_data.as_slice())
+  }
+
+  #[cfg(all(
+    feature = "serde",
+    any(feature = "replay-data", feature = "replay-data-xz2")
+  ))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(all(
+      feature = "serde",
+      any(feature = "replay-data", feature = "replay-data-xz2")
+    )))
+  )]
+  /// Parse the score information written by osu!lazer, if there is any
+  pub fn parse_score_info(&self) -> ReplayResult<Option<LazerScoreInfo>> {
+    let score_info = match &self.score_info {
+      Some(score_info) => score_info,
+      None => return Ok(None),
+    };
+    let decoder = lzma::Decoder::new(score_info.as_slice())?;
+    Ok(Some(serde_json::from_reader(decoder)?))
+  }
+
+  #[cfg(all(
+    feature = "serde",
+    any(feature = "replay-data", feature = "replay-data-xz2")
+  ))]
+  #[cfg_attr(
+    docsrs,
+    doc(cfg(all(
+      feature = "serde",
+      any(feature = "replay-data", feature = "replay-data-xz2")
+    )))
+  )]
+  /// Updates the Replay object with score information
+  ///
+  /// The score information is only written for replays with a version of at least
+  /// [`FIRST_LAZER_VERSION`].
+  pub fn update_score_info(
+    &mut self,
+    score_info: &LazerScoreInfo,
+  ) -> ReplayResult<()> {
+    let json = serde_json::to_vec(score_info)?;
+    self.score_info = Some(lzma::encode(json.as_slice())?);
+    Ok(())
   }
 }
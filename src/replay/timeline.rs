@@ -0,0 +1,334 @@
+use std::sync::OnceLock;
+
+use crate::math::Point;
+use crate::timing::Millis;
+
+use super::{Buttons, ReplayAction, ReplayActionData};
+
+/// A replay frame with an absolute timestamp. See [`Timeline`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimelineFrame {
+  /// The time of this frame, in milliseconds since the start of the map.
+  pub time: i32,
+
+  /// The position of the cursor. In osu!mania replays, the x coordinate holds the pressed
+  /// columns instead, and in osu!catch replays it's the position of the catcher.
+  pub pos: Point<f64>,
+
+  /// The buttons being pressed.
+  pub buttons: Buttons,
+}
+
+/// One of the four keys that can be recorded in a replay.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Key {
+  /// First keyboard key
+  K1,
+
+  /// Second keyboard key
+  K2,
+
+  /// First mouse button
+  M1,
+
+  /// Second mouse button
+  M2,
+}
+
+/// Whether a [`KeyEvent`] is a press or a release.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeyEventKind {
+  /// The key was pressed
+  Press,
+
+  /// The key was released
+  Release,
+}
+
+/// A key being pressed or released.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyEvent {
+  /// The time of the event, in milliseconds since the start of the map.
+  pub time: i32,
+
+  /// The key that was pressed or released.
+  pub key: Key,
+
+  /// Whether the key was pressed or released.
+  pub kind: KeyEventKind,
+}
+
+/// The number of times each key was pressed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyPressCounts {
+  /// Presses of the first keyboard key
+  pub k1: u32,
+
+  /// Presses of the second keyboard key
+  pub k2: u32,
+
+  /// Presses of the first mouse button
+  pub m1: u32,
+
+  /// Presses of the second mouse button
+  pub m2: u32,
+}
+
+impl KeyPressCounts {
+  /// The total number of key presses.
+  pub fn total(&self) -> u32 {
+    self.k1 + self.k2 + self.m1 + self.m2
+  }
+}
+
+/// The frames of a replay with absolute timestamps, for looking up what happened at any time.
+///
+/// The frames in [`ReplayActionData`] are stored relative to each other, and contain a few that
+/// don't represent any input. When building the timeline, these are dropped the same way the game
+/// does during playback:
+///
+/// - stable places two frames at (256, -500) at the start of every replay, the second one right
+///   before the point the map can be skipped to
+/// - frames going back in time are ignored, though their time still counts towards the frames
+///   after them, and so are the frames that this puts before the last frame that was kept
+///
+/// Frames with the same time as the previous one are kept, and the last one of them wins when
+/// looking up the state at that time.
+///
+/// ```
+/// # use libosu::replay::{Buttons, ReplayAction, ReplayActionData, Timeline};
+/// # use libosu::timing::Millis;
+/// let frame = |time, x, buttons| ReplayAction { time: Millis(time), x, y: 0.0, buttons };
+/// let actions = ReplayActionData {
+///     frames: vec![frame(10, 0.0, Buttons::empty()), frame(20, 100.0, Buttons::K1 | Buttons::M1)],
+///     rng_seed: None,
+/// };
+/// let timeline = Timeline::new(&actions);
+/// assert_eq!(timeline.position_at(20.0).unwrap().x, 50.0);
+/// assert_eq!(timeline.press_counts().k1, 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Timeline {
+  frames: Vec<TimelineFrame>,
+
+  /// The times of all key presses, worked out the first time they're needed.
+  #[cfg_attr(feature = "serde", serde(skip))]
+  press_times: OnceLock<Vec<i32>>,
+}
+
+impl PartialEq for Timeline {
+  fn eq(&self, other: &Self) -> bool {
+    self.frames == other.frames
+  }
+}
+
+impl Timeline {
+  /// Builds the timeline of the given replay actions.
+  pub fn new(actions: &ReplayActionData) -> Timeline {
+    let mut frames = Vec::with_capacity(actions.frames.len());
+    let mut time = 0;
+    for (i, action) in actions.frames.iter().enumerate() {
+      time += action.time.0;
+
+      if i < 2 && action.x == 256.0 && action.y == -500.0 {
+        continue;
+      }
+      if action.time.0 < 0 {
+        continue;
+      }
+      if frames
+        .last()
+        .is_some_and(|last: &TimelineFrame| time < last.time)
+      {
+        continue;
+      }
+
+      frames.push(TimelineFrame {
+        time,
+        pos: Point::new(action.x as f64, action.y as f64),
+        buttons: action.buttons,
+      });
+    }
+    Timeline {
+      frames,
+      press_times: OnceLock::new(),
+    }
+  }
+
+  /// Builds a timeline out of frames with absolute timestamps. The frames are sorted by time,
+  /// keeping the order of frames with the same time.
+  pub fn from_frames(mut frames: Vec<TimelineFrame>) -> Timeline {
+    frames.sort_by_key(|frame| frame.time);
+    Timeline {
+      frames,
+      press_times: OnceLock::new(),
+    }
+  }
+
+  /// Converts the timeline back into replay actions, with the two frames that stable puts at the
+  /// start of every replay added back in. The returned actions don't have an RNG seed.
+  pub fn to_action_data(&self) -> ReplayActionData {
+    let leading = |time| ReplayAction {
+      time: Millis(time),
+      x: 256.0,
+      y: -500.0,
+      buttons: Buttons::empty(),
+    };
+
+    let mut frames = Vec::with_capacity(self.frames.len() + 2);
+    frames.push(leading(0));
+    frames.push(leading(-1));
+
+    let mut time = -1;
+    for frame in self.frames.iter() {
+      frames.push(ReplayAction {
+        time: Millis(frame.time - time),
+        x: frame.pos.x as f32,
+        y: frame.pos.y as f32,
+        buttons: frame.buttons,
+      });
+      time = frame.time;
+    }
+
+    ReplayActionData {
+      frames,
+      rng_seed: None,
+    }
+  }
+
+  /// The frames of the timeline, in chronological order.
+  pub fn frames(&self) -> &[TimelineFrame] {
+    &self.frames
+  }
+
+  /// The index of the last frame at or before the given time.
+  fn frame_before(&self, time: f64) -> Option<usize> {
+    self
+      .frames
+      .partition_point(|frame| frame.time as f64 <= time)
+      .checked_sub(1)
+  }
+
+  /// The position of the cursor at the given time, interpolated between the surrounding frames.
+  ///
+  /// Before the first frame and after the last one, the position of that frame is used. Returns
+  /// `None` if the timeline is empty.
+  pub fn position_at(&self, time: f64) -> Option<Point<f64>> {
+    let previous = match self.frame_before(time) {
+      Some(index) => index,
+      None => return self.frames.first().map(|frame| frame.pos),
+    };
+
+    let a = &self.frames[previous];
+    match self.frames.get(previous + 1) {
+      Some(b) if a.time as f64 != time => {
+        let progress = (time - a.time as f64) / (b.time - a.time) as f64;
+        Some(a.pos + (b.pos - a.pos) * progress)
+      }
+      _ => Some(a.pos),
+    }
+  }
+
+  /// The buttons that are held at the given time.
+  pub fn buttons_at(&self, time: f64) -> Buttons {
+    self
+      .frame_before(time)
+      .map(|index| self.frames[index].buttons)
+      .unwrap_or_else(Buttons::empty)
+  }
+
+  /// Works out when each key was pressed and released, in chronological order.
+  ///
+  /// Pressing a keyboard key in osu!standard also sets the mouse button on the same side, so a
+  /// mouse button only counts as held if the keyboard key on its side isn't.
+  pub fn key_events(&self) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut held = [false; 4];
+    for frame in self.frames.iter() {
+      for (i, (key, down)) in logical_keys(frame.buttons).iter().enumerate() {
+        if *down != held[i] {
+          events.push(KeyEvent {
+            time: frame.time,
+            key: *key,
+            kind: if *down {
+              KeyEventKind::Press
+            } else {
+              KeyEventKind::Release
+            },
+          });
+          held[i] = *down;
+        }
+      }
+    }
+    events
+  }
+
+  /// Counts how many times each key was pressed, like the key overlay does.
+  pub fn press_counts(&self) -> KeyPressCounts {
+    let mut counts = KeyPressCounts::default();
+    for event in self.presses() {
+      match event.key {
+        Key::K1 => counts.k1 += 1,
+        Key::K2 => counts.k2 += 1,
+        Key::M1 => counts.m1 += 1,
+        Key::M2 => counts.m2 += 1,
+      }
+    }
+    counts
+  }
+
+  /// The number of key presses during the second leading up to (and including) the given time.
+  pub fn kps_at(&self, time: f64) -> u32 {
+    let presses = self.press_times();
+    let end = presses.partition_point(|press| *press as f64 <= time);
+    let start = presses.partition_point(|press| *press as f64 <= time - 1000.0);
+    (end - start) as u32
+  }
+
+  /// The highest number of key presses during any one second of the replay.
+  pub fn max_kps(&self) -> u32 {
+    let presses = self.press_times();
+    let mut start = 0;
+    let mut max = 0;
+    for (end, time) in presses.iter().enumerate() {
+      while presses[start] <= time - 1000 {
+        start += 1;
+      }
+      max = max.max(end - start + 1);
+    }
+    max as u32
+  }
+
+  /// The times of all key presses, in chronological order.
+  fn press_times(&self) -> &[i32] {
+    self
+      .press_times
+      .get_or_init(|| self.presses().map(|event| event.time).collect())
+  }
+
+  fn presses(&self) -> impl Iterator<Item = KeyEvent> {
+    self
+      .key_events()
+      .into_iter()
+      .filter(|event| event.kind == KeyEventKind::Press)
+  }
+}
+
+/// Splits the buttons of a frame into the four keys, with mouse buttons that are only set
+/// because of the keyboard key on the same side removed.
+fn logical_keys(buttons: Buttons) -> [(Key, bool); 4] {
+  let k1 = buttons.contains(Buttons::K1);
+  let k2 = buttons.contains(Buttons::K2);
+  [
+    (Key::K1, k1),
+    (Key::K2, k2),
+    (Key::M1, buttons.contains(Buttons::M1) && !k1),
+    (Key::M2, buttons.contains(Buttons::M2) && !k2),
+  ]
+}
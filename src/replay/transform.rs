@@ -0,0 +1,146 @@
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use crate::data::{Mode, Mods};
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use crate::timing::Millis;
+
+use super::builder::stable_replay_hash;
+use super::Replay;
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use super::{ReplayAction, ReplayActionData, ReplayResult};
+
+/// The height of the playfield, which HardRock flips the cursor across.
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+const PLAYFIELD_HEIGHT: f32 = 384.0;
+
+impl Replay {
+  /// Returns a copy of this replay for the beatmap with the given MD5 hash, such as a different
+  /// version of the same map. The replay hash is recomputed for the new beatmap.
+  pub fn retarget(&self, beatmap_hash: impl Into<String>) -> Replay {
+    let beatmap_hash = beatmap_hash.into();
+    let grade = self.statistics().grade();
+    Replay {
+      replay_hash: stable_replay_hash(
+        self.max_combo,
+        &self.player_username,
+        &beatmap_hash,
+        self.score,
+        grade,
+      ),
+      beatmap_hash,
+      ..self.clone()
+    }
+  }
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[cfg_attr(
+  docsrs,
+  doc(cfg(any(feature = "replay-data", feature = "replay-data-xz2")))
+)]
+impl Replay {
+  /// Returns a copy of this replay with HardRock added or removed. In osu!standard, the cursor is
+  /// flipped vertically so that it still follows the (now flipped) objects. Nothing is changed if
+  /// the replay already has the requested state.
+  ///
+  /// Other mods, such as Easy, are left alone, so adding HardRock to a replay with Easy makes an
+  /// invalid mod combination.
+  pub fn with_hard_rock(&self, hard_rock: bool) -> ReplayResult<Replay> {
+    let mut replay = self.clone();
+    if self.mods.contains(Mods::HardRock) == hard_rock {
+      return Ok(replay);
+    }
+    replay.mods.set(Mods::HardRock, hard_rock);
+
+    if self.mode == Mode::Osu {
+      let mut action_data = self.parse_action_data()?;
+      for (i, action) in action_data.frames.iter_mut().enumerate() {
+        if !is_leading_frame(i, action) {
+          action.y = PLAYFIELD_HEIGHT - action.y;
+        }
+      }
+      replay.update_action_data(&action_data)?;
+    }
+    Ok(replay)
+  }
+
+  /// Returns a copy of this replay with every time multiplied by `factor`, which is how a replay
+  /// is carried over to a copy of the map that was sped up or slowed down.
+  ///
+  /// For example, a play with DoubleTime lines up with a copy of the map that plays 1.5 times as
+  /// fast (and so has all of its times divided by 1.5) after scaling by `1.0 / 1.5` and taking
+  /// DoubleTime off. The mods aren't changed here.
+  pub fn scale_time(&self, factor: f64) -> ReplayResult<Replay> {
+    let scale = |time: i32| (time as f64 * factor).round() as i32;
+    let mut replay = self.map_times(scale, |_| true)?;
+    for (time, _) in replay.life_graph.iter_mut() {
+      *time = scale(*time);
+    }
+    Ok(replay)
+  }
+
+  /// Returns a copy of this replay with every time moved by `offset` milliseconds. Positive
+  /// offsets make everything happen later.
+  pub fn offset(&self, offset: i32) -> ReplayResult<Replay> {
+    let mut replay = self.map_times(|time| time + offset, |_| true)?;
+    for (time, _) in replay.life_graph.iter_mut() {
+      *time += offset;
+    }
+    Ok(replay)
+  }
+
+  /// Returns a copy of this replay with only the frames from `start` up to and including `end`,
+  /// in milliseconds. The life graph is cut down the same way, but the judgement counts, score
+  /// and combo are kept as they are, since they can't be worked out without the beatmap.
+  pub fn trim(&self, start: i32, end: i32) -> ReplayResult<Replay> {
+    let in_range = |time: i32| start <= time && time <= end;
+    let mut replay = self.map_times(|time| time, in_range)?;
+    replay.life_graph.retain(|(time, _)| in_range(*time));
+    Ok(replay)
+  }
+
+  /// Returns a copy of this replay with the absolute time of every frame changed by `map`,
+  /// keeping only the frames for whose original time `keep` returns true. The leading frames
+  /// that stable puts at the start of every replay and the RNG seed are kept as they are.
+  fn map_times(
+    &self,
+    map: impl Fn(i32) -> i32,
+    keep: impl Fn(i32) -> bool,
+  ) -> ReplayResult<Replay> {
+    let action_data = self.parse_action_data()?;
+    let mut frames = Vec::with_capacity(action_data.frames.len());
+    let mut time = 0;
+    let mut new_time = 0;
+    for (i, action) in action_data.frames.into_iter().enumerate() {
+      time += action.time.0;
+      if is_leading_frame(i, &action) {
+        new_time += action.time.0;
+        frames.push(action);
+        continue;
+      }
+      if !keep(time) {
+        continue;
+      }
+
+      let mapped = map(time);
+      frames.push(ReplayAction {
+        time: Millis(mapped - new_time),
+        ..action
+      });
+      new_time = mapped;
+    }
+
+    let mut replay = self.clone();
+    replay.update_action_data(&ReplayActionData {
+      frames,
+      rng_seed: action_data.rng_seed,
+    })?;
+    Ok(replay)
+  }
+}
+
+/// Whether the action is one of the two frames that stable puts at the start of every replay,
+/// which don't hold any input.
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+fn is_leading_frame(index: usize, action: &ReplayAction) -> bool {
+  index < 2 && action.x == 256.0 && action.y == -500.0
+}
@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+
+use crate::beatmap::Beatmap;
+use crate::data::{HitCounts, Mode, Mods};
+use crate::events::Event;
+use crate::hitobject::HitObjectKind;
+use crate::sim::{HitResult, Judgement, JudgementKind, OsuSimulator};
+
+use super::ScoreStatistics;
+
+/// The part of a ScoreV2 score that comes from the combo.
+const SCORE_V2_COMBO_PORTION: f64 = 700_000.0;
+
+/// The part of a ScoreV2 score that comes from the accuracy.
+const SCORE_V2_ACCURACY_PORTION: f64 = 300_000.0;
+
+/// The mod multiplier osu!stable uses for ScoreV1 in the given mode.
+///
+/// ```
+/// # use libosu::{data::{Mode, Mods}, score::score_v1_mod_multiplier};
+/// let multiplier = score_v1_mod_multiplier(Mode::Osu, Mods::Hidden | Mods::HardRock);
+/// assert!((multiplier - 1.1236).abs() < 1e-9);
+/// ```
+pub fn score_v1_mod_multiplier(mode: Mode, mods: Mods) -> f64 {
+  let (hidden, hard_rock, double_time, flashlight) = match mode {
+    Mode::Osu | Mode::Taiko => (1.06, 1.06, 1.12, 1.12),
+    Mode::Catch => (1.06, 1.12, 1.06, 1.12),
+    Mode::Mania => (1.0, 1.0, 1.0, 1.0),
+  };
+  let half_time = if mode == Mode::Mania { 0.5 } else { 0.3 };
+
+  [
+    (Mods::NoFail, 0.5),
+    (Mods::Easy, 0.5),
+    (Mods::HalfTime, half_time),
+    (Mods::Hidden, hidden),
+    (Mods::HardRock, hard_rock),
+    (Mods::DoubleTime | Mods::Nightcore, double_time),
+    (Mods::Flashlight, flashlight),
+    (Mods::SpunOut, 0.9),
+  ]
+  .iter()
+  .filter(|(flags, _)| mods.intersects(*flags))
+  .map(|(_, multiplier)| multiplier)
+  .product()
+}
+
+/// The mod multiplier osu!stable uses for ScoreV2 in osu!standard. Unlike ScoreV1, No Fail
+/// doesn't lower the score.
+pub fn score_v2_mod_multiplier(mods: Mods) -> f64 {
+  [
+    (Mods::Easy, 0.5),
+    (Mods::HalfTime, 0.3),
+    (Mods::Hidden, 1.06),
+    (Mods::HardRock, 1.1),
+    (Mods::DoubleTime | Mods::Nightcore, 1.2),
+    (Mods::Flashlight, 1.12),
+    (Mods::SpunOut, 0.9),
+  ]
+  .iter()
+  .filter(|(flags, _)| mods.intersects(*flags))
+  .map(|(_, multiplier)| multiplier)
+  .product()
+}
+
+/// Works out the total score osu!stable gives for a sequence of judgements, both with the
+/// regular scoring (ScoreV1) and the one used in tournaments (ScoreV2).
+///
+/// The judgements come from one of the [simulators][crate::sim], either of a replay or of a
+/// made-up play. Only the scoring of osu!standard and osu!catch is supported for ScoreV1, and
+/// only osu!standard for ScoreV2; other modes give `None`.
+///
+/// With ScoreV1, every hit object gives its hit value (300, 100 or 50) plus a bonus that grows
+/// with the combo, the [difficulty multiplier][Self::difficulty_multiplier] and the
+/// [mod multiplier][score_v1_mod_multiplier]. Slider heads, repeats and tails give 30, slider
+/// ticks 10, spinner rotations 100 and bonus spins another 1000. In osu!catch droplets give 100,
+/// tiny droplets 10 and bananas 1100.
+///
+/// ```no_run
+/// # use libosu::{beatmap::Beatmap, replay::Replay, score::ScoreCalculator, sim::OsuSimulator};
+/// # #[cfg(feature = "replay-data")]
+/// # fn invisible(beatmap: Beatmap, replay: Replay) -> anyhow::Result<()> {
+/// #
+/// let action_data = replay.parse_action_data()?;
+/// let result = OsuSimulator::new(&beatmap, replay.mods).simulate(&action_data);
+/// let calculator = ScoreCalculator::new(&beatmap, replay.mode, replay.mods);
+/// assert_eq!(calculator.score_v1(&result.judgements), Some(replay.score));
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScoreCalculator {
+  mode: Mode,
+  mods: Mods,
+  difficulty_multiplier: u32,
+  object_count: usize,
+  perfect: Option<Vec<Judgement>>,
+}
+
+impl ScoreCalculator {
+  /// Prepares to score plays of the given beatmap in the given mode with the given mods.
+  pub fn new(beatmap: &Beatmap, mode: Mode, mods: Mods) -> ScoreCalculator {
+    // the highest possible combo score is needed for ScoreV2
+    let perfect = match mode {
+      Mode::Osu => {
+        Some(OsuSimulator::new(beatmap, mods).perfect_result().judgements)
+      }
+      _ => None,
+    };
+
+    ScoreCalculator {
+      mode,
+      mods,
+      difficulty_multiplier: difficulty_multiplier(beatmap),
+      object_count: beatmap.hit_objects.len(),
+      perfect,
+    }
+  }
+
+  /// The difficulty multiplier of the beatmap, between 0 and 6 or so, which osu!stable works out
+  /// from the HP drain rate, circle size, overall difficulty and how many objects there are for
+  /// the length of the map, all without mods.
+  pub fn difficulty_multiplier(&self) -> u32 {
+    self.difficulty_multiplier
+  }
+
+  /// The total ScoreV1 score for the given judgements, in the order they were given.
+  pub fn score_v1(&self, judgements: &[Judgement]) -> Option<u32> {
+    if !matches!(self.mode, Mode::Osu | Mode::Catch) {
+      return None;
+    }
+
+    let multiplier = self.difficulty_multiplier as f64
+      * score_v1_mod_multiplier(self.mode, self.mods);
+    let mut score = 0u64;
+    for_each_combo(judgements, |judgement, combo| {
+      let (value, combo_bonus) = score_v1_value(judgement);
+      score += value as u64;
+      if combo_bonus {
+        let bonus = combo.saturating_sub(1) as f64 * (value / 25) as f64;
+        score += (bonus * multiplier) as u64;
+      }
+    });
+    Some(score.min(u32::MAX as u64) as u32)
+  }
+
+  /// The total ScoreV2 score for the given judgements, in the order they were given.
+  ///
+  /// 700,000 points come from the combo, where every judgement that counts towards the combo
+  /// is worth its hit value times one tenth of the combo, compared to a perfect play. The other
+  /// 300,000 come from the accuracy to the power of 10, scaled by the share of objects that
+  /// were judged so that failed plays don't get all of it. Bonus spins are worth 1000 on top.
+  /// All of this is multiplied by the [mod multiplier][score_v2_mod_multiplier].
+  pub fn score_v2(&self, judgements: &[Judgement]) -> Option<u32> {
+    let perfect = self.perfect.as_ref()?;
+    let combo_score = score_v2_combo_score(judgements);
+    let max_combo_score = score_v2_combo_score(perfect);
+
+    let mut counts = HitCounts::default();
+    let mut bonus = 0.0;
+    for judgement in judgements {
+      match judgement.kind {
+        JudgementKind::Object => match judgement.result {
+          HitResult::Hit300 => counts.count_300 += 1,
+          HitResult::Hit100 => counts.count_100 += 1,
+          HitResult::Hit50 => counts.count_50 += 1,
+          _ => counts.count_miss += 1,
+        },
+        JudgementKind::SpinnerBonus => bonus += 1000.0,
+        _ => {}
+      }
+    }
+    let statistics = ScoreStatistics::new(self.mode, counts, self.mods);
+    let progress = if self.object_count == 0 {
+      1.0
+    } else {
+      statistics.total_judgements() as f64 / self.object_count as f64
+    };
+
+    let combo_portion = if max_combo_score > 0.0 {
+      combo_score / max_combo_score
+    } else {
+      1.0
+    };
+    let score = (SCORE_V2_COMBO_PORTION * combo_portion
+      + SCORE_V2_ACCURACY_PORTION * statistics.accuracy().powi(10) * progress
+      + bonus)
+      * score_v2_mod_multiplier(self.mods);
+    Some(score.round().min(u32::MAX as f64) as u32)
+  }
+}
+
+/// The ScoreV1 value of a judgement, and whether the combo bonus is added to it.
+fn score_v1_value(judgement: &Judgement) -> (u32, bool) {
+  if judgement.result.is_miss() {
+    return (0, false);
+  }
+
+  match judgement.kind {
+    JudgementKind::Object => (hit_value(judgement.result), true),
+    JudgementKind::SliderHead
+    | JudgementKind::SliderRepeat
+    | JudgementKind::SliderTail => (30, false),
+    JudgementKind::SliderTick => (10, false),
+    JudgementKind::SpinnerSpin => (100, false),
+    JudgementKind::SpinnerBonus => (1000, false),
+    JudgementKind::Fruit => (300, true),
+    JudgementKind::Droplet => (100, false),
+    JudgementKind::TinyDroplet => (10, false),
+    JudgementKind::Banana => (1100, false),
+    _ => (0, false),
+  }
+}
+
+/// The number of points a judgement of a whole object is worth before any bonuses.
+fn hit_value(result: HitResult) -> u32 {
+  match result {
+    HitResult::Hit320 | HitResult::Hit300 => 300,
+    HitResult::Hit200 => 200,
+    HitResult::Hit100 => 100,
+    HitResult::Hit50 => 50,
+    HitResult::Miss | HitResult::Hit => 0,
+  }
+}
+
+/// The combo part of a ScoreV2 score, before it's compared to a perfect play.
+fn score_v2_combo_score(judgements: &[Judgement]) -> f64 {
+  let mut score = 0.0;
+  for_each_combo(judgements, |judgement, combo| {
+    if judgement.increases_combo() {
+      let value = match judgement.kind {
+        JudgementKind::Object => hit_value(judgement.result),
+        JudgementKind::SliderTick => 10,
+        _ => 30,
+      };
+      score += value as f64 * (1.0 + (combo + 1) as f64 / 10.0);
+    }
+  });
+  score
+}
+
+/// Calls `f` with every judgement that counts, along with the combo right before it. The combo
+/// is counted the same way as in [`SimulationResult`][crate::sim::SimulationResult], where
+/// objects that have parts affecting the combo (like sliders) only add to the combo through
+/// their parts. The judgements of those objects as a whole are still passed to `f`, but they
+/// don't change the combo.
+fn for_each_combo(
+  judgements: &[Judgement],
+  mut f: impl FnMut(&Judgement, u32),
+) {
+  let objects_with_parts = judgements
+    .iter()
+    .filter(|judgement| {
+      !judgement.kind.is_whole_object() && judgement.kind.affects_combo()
+    })
+    .map(|judgement| judgement.object_index)
+    .collect::<HashSet<_>>();
+
+  let mut combo = 0;
+  for judgement in judgements {
+    let has_parts = judgement.kind == JudgementKind::Object
+      && objects_with_parts.contains(&judgement.object_index);
+    f(judgement, combo);
+    if has_parts {
+      continue;
+    }
+
+    if judgement.breaks_combo() {
+      combo = 0;
+    } else if judgement.increases_combo() {
+      combo += 1;
+    }
+  }
+}
+
+/// The difficulty multiplier osu!stable works out for a beatmap.
+fn difficulty_multiplier(beatmap: &Beatmap) -> u32 {
+  let (first, last) =
+    match (beatmap.hit_objects.first(), beatmap.hit_objects.last()) {
+      (Some(first), Some(last)) => (first, last),
+      _ => return 0,
+    };
+  let end_time = match &last.kind {
+    HitObjectKind::Circle => last.start_time.0,
+    HitObjectKind::Slider(_) => beatmap
+      .slider_timing(last)
+      .map(|timing| timing.end_time().round() as i32)
+      .unwrap_or(last.start_time.0),
+    HitObjectKind::Spinner(info) => info.end_time.0,
+    HitObjectKind::HoldNote(info) => info.end_time.0,
+  };
+  let break_time = beatmap
+    .events
+    .iter()
+    .map(|event| match event {
+      Event::Break(info) => info.end_time.0 - info.start_time.0,
+      _ => 0,
+    })
+    .sum::<i32>();
+  let drain_time = (end_time - first.start_time.0 - break_time) / 1000;
+
+  let difficulty = &beatmap.difficulty;
+  let density = if drain_time > 0 {
+    (beatmap.hit_objects.len() as f32 / drain_time as f32 * 8.0)
+      .clamp(0.0, 16.0)
+  } else {
+    16.0
+  };
+  ((difficulty.hp_drain_rate
+    + difficulty.overall_difficulty
+    + difficulty.circle_size
+    + density)
+    / 38.0
+    * 5.0)
+    .round() as u32
+}
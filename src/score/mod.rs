@@ -0,0 +1,33 @@
+//! Working out the numbers shown for a score
+//!
+//! Scores are stored in several places, such as [replays][crate::replay::Replay] and API
+//! listings, which all keep the same judgement counts. [`ScoreStatistics`] turns those counts into
+//! the accuracy and grade the game shows. [`ScoreCalculator`] works out the total score of the
+//! judgements produced by the [simulators][crate::sim], and scores can be converted to and from
+//! the standardised scoring of osu!lazer.
+//!
+//! Example
+//! -------
+//!
+//! ```no_run
+//! # use libosu::replay::Replay;
+//! # fn invisible(replay: Replay) {
+//! let stats = replay.statistics();
+//! println!("{:.2}% ({})", stats.accuracy() * 100.0, stats.grade());
+//! for (name, count) in stats.named_counts() {
+//!     println!("{}: {}", name, count);
+//! }
+//! # }
+//! ```
+
+mod legacy;
+mod standardised;
+mod statistics;
+
+pub use self::legacy::{
+  score_v1_mod_multiplier, score_v2_mod_multiplier, ScoreCalculator,
+};
+pub use self::standardised::{
+  classic_to_standardised, standardised_to_classic, StandardisedScoreEstimate,
+};
+pub use self::statistics::{ParseGradeError, ScoreStatistics};
@@ -0,0 +1,192 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::data::Mode;
+
+use super::{score_v1_mod_multiplier, ScoreStatistics};
+
+/// The highest standardised score, before mod multipliers.
+const MAX_STANDARDISED_SCORE: f64 = 1_000_000.0;
+
+/// osu!lazer gives every hit a combo score of its value times the combo to this power.
+const COMBO_EXPONENT: f64 = 0.5;
+
+/// Converts a standardised score from osu!lazer to the "classic" score it shows when set to
+/// display scores the way osu!stable does, for a map with the given number of objects.
+///
+/// This follows osu!lazer's conversion: the classic score grows with the square of the object
+/// count in osu!standard and osu!catch and linearly in osu!taiko, while osu!mania already uses
+/// 1,000,000 as its maximum and is left alone.
+///
+/// ```
+/// # use libosu::{data::Mode, score::standardised_to_classic};
+/// assert_eq!(standardised_to_classic(Mode::Osu, 1_000_000, 100), 425_700);
+/// assert_eq!(standardised_to_classic(Mode::Mania, 950_000, 100), 950_000);
+/// ```
+pub fn standardised_to_classic(
+  mode: Mode,
+  standardised: u32,
+  object_count: u32,
+) -> u32 {
+  let standardised = standardised as f64;
+  let object_count = object_count as f64;
+  let classic = match mode {
+    Mode::Osu => {
+      (object_count * object_count * 32.57 + 100_000.0) * standardised
+        / MAX_STANDARDISED_SCORE
+    }
+    Mode::Taiko => {
+      (object_count * 1109.0 + 100_000.0) * standardised
+        / MAX_STANDARDISED_SCORE
+    }
+    Mode::Catch => {
+      (standardised / MAX_STANDARDISED_SCORE * object_count).powi(2) * 21.62
+        + standardised / 10.0
+    }
+    Mode::Mania => standardised,
+  };
+  classic.round().min(u32::MAX as f64) as u32
+}
+
+/// Converts a classic score shown by osu!lazer back to the standardised score it came from,
+/// undoing [`standardised_to_classic`] for a map with the given number of objects.
+pub fn classic_to_standardised(
+  mode: Mode,
+  classic: u32,
+  object_count: u32,
+) -> u32 {
+  let classic = classic as f64;
+  let object_count = object_count as f64;
+  let standardised = match mode {
+    Mode::Osu => {
+      classic * MAX_STANDARDISED_SCORE
+        / (object_count * object_count * 32.57 + 100_000.0)
+    }
+    Mode::Taiko => {
+      classic * MAX_STANDARDISED_SCORE / (object_count * 1109.0 + 100_000.0)
+    }
+    Mode::Catch => {
+      // solves classic = a * standardised² + standardised / 10
+      let a = (object_count / MAX_STANDARDISED_SCORE).powi(2) * 21.62;
+      if a == 0.0 {
+        classic * 10.0
+      } else {
+        (-0.1 + (0.01 + 4.0 * a * classic).sqrt()) / (2.0 * a)
+      }
+    }
+    Mode::Mania => classic,
+  };
+  standardised.round().min(u32::MAX as f64) as u32
+}
+
+/// A guess at the standardised score osu!lazer would give a score from osu!stable, made by
+/// [`ScoreStatistics::estimate_standardised_score`].
+///
+/// osu!lazer's own mod multipliers aren't known here, so the base score is kept apart from the
+/// osu!stable multiplier that [`score`][Self::score] applies to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StandardisedScoreEstimate {
+  /// The estimated score before mod multipliers, out of 1,000,000.
+  pub base: u32,
+
+  /// The osu!stable [mod multiplier][super::score_v1_mod_multiplier] of the score's mods, which
+  /// osu!lazer doesn't always agree with.
+  pub mod_multiplier: f64,
+}
+
+impl StandardisedScoreEstimate {
+  /// The estimated score with the osu!stable mod multiplier applied.
+  pub fn score(&self) -> u32 {
+    (self.base as f64 * self.mod_multiplier)
+      .round()
+      .min(u32::MAX as f64) as u32
+  }
+}
+
+impl ScoreStatistics {
+  /// The number of judgements osu!lazer counts as objects when converting to classic scores,
+  /// which leaves out the droplets of osu!catch.
+  pub fn object_count(&self) -> u32 {
+    match self.mode {
+      Mode::Catch => {
+        self.counts.count_300 as u32 + self.counts.count_miss as u32
+      }
+      _ => self.total_judgements(),
+    }
+  }
+
+  /// The accuracy of this score the way osu!lazer works it out. This is the same as
+  /// [`accuracy`][Self::accuracy], except in osu!mania where MAX judgements are worth a bit more
+  /// than 300s.
+  pub fn lazer_accuracy(&self) -> f64 {
+    if self.mode != Mode::Mania {
+      return self.accuracy();
+    }
+
+    let counts = &self.counts;
+    let total = self.total_judgements();
+    if total == 0 {
+      return 1.0;
+    }
+    (305.0 * counts.count_geki as f64
+      + 300.0 * counts.count_300 as f64
+      + 200.0 * counts.count_katu as f64
+      + 100.0 * counts.count_100 as f64
+      + 50.0 * counts.count_50 as f64)
+      / (305.0 * total as f64)
+  }
+
+  /// Estimates the standardised score osu!lazer gives this score, on a map whose highest
+  /// possible combo is `beatmap_max_combo`.
+  ///
+  /// osu!lazer splits the 1,000,000 points between combo and accuracy differently in every
+  /// mode, and weighs the accuracy part with a power of the accuracy. The combo part is the sum
+  /// of the square roots of the combo at every hit, compared to a perfect play. Since scores
+  /// from osu!stable only store the highest combo, the hits outside of it are assumed to be
+  /// spread out evenly between the misses, which can't account for slider breaks. Bonus score
+  /// from spinners and the like isn't included either.
+  pub fn estimate_standardised_score(
+    &self,
+    max_combo: u32,
+    beatmap_max_combo: u32,
+  ) -> StandardisedScoreEstimate {
+    let combo_progress = if beatmap_max_combo == 0 {
+      1.0
+    } else {
+      let combo_score = |combo: f64| combo.powf(1.0 + COMBO_EXPONENT);
+      let max_combo = max_combo.min(beatmap_max_combo) as f64;
+      let misses = self.counts.count_miss as f64;
+      let remaining = (beatmap_max_combo as f64 - max_combo - misses).max(0.0);
+      let other_combos = if misses > 0.0 {
+        misses * combo_score(remaining / misses)
+      } else {
+        0.0
+      };
+      ((combo_score(max_combo) + other_combos)
+        / combo_score(beatmap_max_combo as f64))
+      .min(1.0)
+    };
+
+    let accuracy = self.lazer_accuracy();
+    let (combo_portion, accuracy_portion) = match self.mode {
+      Mode::Osu => (700_000.0, 300_000.0 * accuracy.powi(10)),
+      Mode::Taiko => (250_000.0, 750_000.0 * accuracy.powf(3.6)),
+      Mode::Catch => (600_000.0, 400_000.0 * accuracy.powi(5)),
+      Mode::Mania => {
+        (150_000.0, 850_000.0 * accuracy.powf(2.0 + 2.0 * accuracy))
+      }
+    };
+    let base = (combo_portion * combo_progress + accuracy_portion).round();
+    StandardisedScoreEstimate {
+      base: base as u32,
+      mod_multiplier: score_v1_mod_multiplier(self.mode, self.mods),
+    }
+  }
+
+  /// Converts a standardised score of this play to the classic score osu!lazer would show for
+  /// it. See [`standardised_to_classic`].
+  pub fn classic_score(&self, standardised: u32) -> u32 {
+    standardised_to_classic(self.mode, standardised, self.object_count())
+  }
+}
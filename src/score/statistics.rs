@@ -0,0 +1,259 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::data::{Grade, HitCounts, Mode, Mods};
+
+/// The names that osu!stable uses for grades, in the order of [`Grade`].
+const RANKINGS: [&str; 10] =
+  ["XH", "SH", "X", "S", "A", "B", "C", "D", "F", "N"];
+
+/// An error returned when parsing a [`Grade`] that isn't one of osu!stable's grade names.
+#[derive(Debug, Error)]
+#[error("unknown grade: {0:?}")]
+pub struct ParseGradeError(pub String);
+
+impl Grade {
+  /// The name osu!stable uses for this grade in score listings and replay hashes, such as `"XH"`
+  /// for the silver SS and `"X"` for the normal one.
+  pub fn as_str(&self) -> &'static str {
+    // the values of the variants follow the numbering of the game, where 0 is the silver SS and 2
+    // the normal one
+    RANKINGS[*self as usize]
+  }
+
+  /// Whether this is one of the silver grades given when playing with Hidden or Flashlight.
+  pub fn is_silver(&self) -> bool {
+    matches!(self, Grade::SSH | Grade::SH)
+  }
+
+  /// Whether this is an SS, silver or not.
+  pub fn is_ss(&self) -> bool {
+    matches!(self, Grade::SS | Grade::SSH)
+  }
+}
+
+impl fmt::Display for Grade {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl FromStr for Grade {
+  type Err = ParseGradeError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    RANKINGS
+      .iter()
+      .position(|ranking| *ranking == s)
+      .and_then(num::FromPrimitive::from_usize)
+      .ok_or_else(|| ParseGradeError(s.to_owned()))
+  }
+}
+
+/// The judgement counts of a score together with the mode and mods they were played with, which
+/// is what's needed to make sense of them.
+///
+/// The `count_*` fields of [`HitCounts`] mean different things in each mode. For example, in
+/// osu!mania `count_geki` holds the MAX judgements and `count_katu` the 200s, while in Catch the
+/// Beat `count_50` and `count_katu` hold the caught and missed droplets. This works out the
+/// accuracy and grade the same way osu!stable does, and names the counts the way the game shows
+/// them.
+///
+/// ```
+/// # use libosu::{data::{Grade, HitCounts, Mode, Mods}, score::ScoreStatistics};
+/// let counts = HitCounts {
+///     count_300: 95,
+///     count_100: 5,
+///     ..HitCounts::default()
+/// };
+/// let stats = ScoreStatistics::new(Mode::Osu, counts, Mods::Hidden);
+/// assert!((stats.accuracy() - 0.9667).abs() < 1e-4);
+/// assert_eq!(stats.grade(), Grade::SH);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScoreStatistics {
+  /// The mode the score was set in.
+  pub mode: Mode,
+
+  /// The judgement counts of the score.
+  pub counts: HitCounts,
+
+  /// The mods the score was set with.
+  pub mods: Mods,
+}
+
+impl ScoreStatistics {
+  /// Creates the statistics for a score with the given mode, judgement counts and mods.
+  pub fn new(mode: Mode, counts: HitCounts, mods: Mods) -> Self {
+    ScoreStatistics { mode, counts, mods }
+  }
+
+  /// The number of judgements that count towards the accuracy. In Catch the Beat this includes
+  /// droplets, and in osu!mania the MAX judgements.
+  pub fn total_judgements(&self) -> u32 {
+    let counts = &self.counts;
+    let [count_300, count_100, count_50, count_geki, count_katu, count_miss] =
+      [
+        counts.count_300,
+        counts.count_100,
+        counts.count_50,
+        counts.count_geki,
+        counts.count_katu,
+        counts.count_miss,
+      ]
+      .map(u32::from);
+    match self.mode {
+      Mode::Osu => count_300 + count_100 + count_50 + count_miss,
+      Mode::Taiko => count_300 + count_100 + count_miss,
+      Mode::Catch => count_300 + count_100 + count_50 + count_katu + count_miss,
+      Mode::Mania => {
+        count_geki + count_300 + count_katu + count_100 + count_50 + count_miss
+      }
+    }
+  }
+
+  /// The accuracy of the score between 0 and 1, as shown by osu!stable. A score without any
+  /// judgements has an accuracy of 1.
+  pub fn accuracy(&self) -> f64 {
+    let total = self.total_judgements();
+    if total == 0 {
+      return 1.0;
+    }
+
+    let counts = &self.counts;
+    let count_300 = counts.count_300 as f64;
+    let count_100 = counts.count_100 as f64;
+    let count_50 = counts.count_50 as f64;
+    let count_geki = counts.count_geki as f64;
+    let count_katu = counts.count_katu as f64;
+    let total = total as f64;
+    match self.mode {
+      Mode::Osu => {
+        (300.0 * count_300 + 100.0 * count_100 + 50.0 * count_50)
+          / (300.0 * total)
+      }
+      Mode::Taiko => (count_300 + 0.5 * count_100) / total,
+      Mode::Catch => (count_300 + count_100 + count_50) / total,
+      Mode::Mania => {
+        (300.0 * (count_geki + count_300)
+          + 200.0 * count_katu
+          + 100.0 * count_100
+          + 50.0 * count_50)
+          / (300.0 * total)
+      }
+    }
+  }
+
+  /// The grade osu!stable gives for this score, assuming it was passed. With Hidden or
+  /// Flashlight (or Fade In in osu!mania), SS and S become their silver variants.
+  pub fn grade(&self) -> Grade {
+    let counts = &self.counts;
+    let silver = match self.mode {
+      Mode::Mania => self
+        .mods
+        .intersects(Mods::Hidden | Mods::Flashlight | Mods::FadeIn),
+      _ => self.mods.intersects(Mods::Hidden | Mods::Flashlight),
+    };
+    let (ss, s) = if silver {
+      (Grade::SSH, Grade::SH)
+    } else {
+      (Grade::SS, Grade::S)
+    };
+
+    match self.mode {
+      Mode::Osu | Mode::Taiko => {
+        let total = (counts.count_300 as u32
+          + counts.count_100 as u32
+          + counts.count_50 as u32
+          + counts.count_miss as u32) as f64;
+        if total == 0.0 {
+          return ss;
+        }
+        let ratio_300 = counts.count_300 as f64 / total;
+        let ratio_50 = counts.count_50 as f64 / total;
+        let no_miss = counts.count_miss == 0;
+        if ratio_300 == 1.0 {
+          ss
+        } else if ratio_300 > 0.9 && ratio_50 <= 0.01 && no_miss {
+          s
+        } else if ratio_300 > 0.9 || (ratio_300 > 0.8 && no_miss) {
+          Grade::A
+        } else if ratio_300 > 0.8 || (ratio_300 > 0.7 && no_miss) {
+          Grade::B
+        } else if ratio_300 > 0.6 {
+          Grade::C
+        } else {
+          Grade::D
+        }
+      }
+      Mode::Catch => {
+        grade_by_accuracy(self.accuracy(), [0.98, 0.94, 0.9, 0.85], ss, s)
+      }
+      Mode::Mania => {
+        grade_by_accuracy(self.accuracy(), [0.95, 0.9, 0.8, 0.7], ss, s)
+      }
+    }
+  }
+
+  /// The judgement counts that matter in this mode, along with the names osu!stable shows for
+  /// them on the results screen, from best to worst.
+  pub fn named_counts(&self) -> Vec<(&'static str, u16)> {
+    let counts = &self.counts;
+    match self.mode {
+      Mode::Osu => vec![
+        ("300", counts.count_300),
+        ("100", counts.count_100),
+        ("50", counts.count_50),
+        ("Miss", counts.count_miss),
+        ("Geki", counts.count_geki),
+        ("Katu", counts.count_katu),
+      ],
+      Mode::Taiko => vec![
+        ("Great", counts.count_300),
+        ("Good", counts.count_100),
+        ("Miss", counts.count_miss),
+        ("Great (big)", counts.count_geki),
+        ("Good (big)", counts.count_katu),
+      ],
+      Mode::Catch => vec![
+        ("Fruits", counts.count_300),
+        ("Drops", counts.count_100),
+        ("Droplets", counts.count_50),
+        ("Miss", counts.count_miss),
+        ("Missed droplets", counts.count_katu),
+      ],
+      Mode::Mania => vec![
+        ("MAX", counts.count_geki),
+        ("300", counts.count_300),
+        ("200", counts.count_katu),
+        ("100", counts.count_100),
+        ("50", counts.count_50),
+        ("Miss", counts.count_miss),
+      ],
+    }
+  }
+}
+
+/// Picks a grade for modes that are graded by accuracy alone, given the accuracy needed to get
+/// more than an S, A, B and C.
+fn grade_by_accuracy(
+  accuracy: f64,
+  [s_limit, a_limit, b_limit, c_limit]: [f64; 4],
+  ss: Grade,
+  s: Grade,
+) -> Grade {
+  if accuracy == 1.0 {
+    ss
+  } else if accuracy > s_limit {
+    s
+  } else if accuracy > a_limit {
+    Grade::A
+  } else if accuracy > b_limit {
+    Grade::B
+  } else if accuracy > c_limit {
+    Grade::C
+  } else {
+    Grade::D
+  }
+}
@@ -0,0 +1,523 @@
+use crate::beatmap::{Beatmap, SliderEventKind, SliderTiming};
+use crate::data::{HitCounts, Mods};
+use crate::hitobject::HitObjectKind;
+use crate::replay::{ReplayActionData, Timeline};
+use crate::spline::Spline;
+
+use super::{HitResult, Judgement, JudgementKind, SimulationResult};
+
+/// The width of the playfield in osu!pixels.
+const PLAYFIELD_WIDTH: f64 = 512.0;
+
+/// The width of the catcher at a circle size of 5.
+const CATCHER_BASE_SIZE: f64 = 106.75;
+
+/// Only this portion of the catcher can catch anything.
+const ALLOWED_CATCH_RANGE: f64 = 0.8;
+
+/// How fast the catcher moves while dashing, in osu!pixels per millisecond.
+const BASE_DASH_SPEED: f64 = 1.0;
+
+/// The seed of the random number generator that places bananas and tiny droplets.
+const RNG_SEED: i32 = 1337;
+
+/// The kind of a [`CatchObject`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CatchObjectKind {
+  /// A fruit, either on its own or at the head, repeats or tail of a juice stream
+  Fruit,
+
+  /// A large droplet, at the ticks of a juice stream
+  Droplet,
+
+  /// A tiny droplet, between the fruits and droplets of a juice stream
+  TinyDroplet,
+
+  /// A banana, part of a banana shower
+  Banana,
+}
+
+/// A single catchable object.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CatchObject {
+  /// The index of the hit object this is a part of in `Beatmap::hit_objects`.
+  pub object_index: usize,
+
+  /// What kind of object this is.
+  pub kind: CatchObjectKind,
+
+  /// The time (in milliseconds) at which this object reaches the catcher.
+  pub time: f64,
+
+  /// The horizontal position of this object, after all offsets have been applied.
+  pub x: f64,
+
+  /// Whether the catcher has to hyperdash to get from this object to the next fruit or droplet.
+  pub hyper_dash: bool,
+}
+
+/// Reproduces osu!catch's judgement of a replay.
+///
+/// In osu!catch replays, the x coordinate of every frame is the position of the catcher.
+/// Objects are caught if the catcher, interpolated between frames, is close enough to them at
+/// the moment they reach it. Circles are fruits, sliders are juice streams and spinners are
+/// banana showers. The positions of tiny droplets and bananas, and the Hard Rock offsets of
+/// fruits, are reproduced with the same random number generator osu!stable uses.
+///
+/// Caught fruits are counted as 300s, droplets as 100s and tiny droplets as 50s. Missed tiny
+/// droplets are counted as katus, and missed fruits and droplets as misses. Tiny droplets and
+/// bananas don't affect the combo. Bananas aren't counted, and neither are gekis.
+#[derive(Clone, Debug)]
+pub struct CatchSimulator {
+  objects: Vec<CatchObject>,
+  parents: Vec<Parent>,
+  catch_width: f64,
+}
+
+/// What a hit object turned into.
+#[derive(Clone, Debug)]
+enum Parent {
+  Fruit,
+  JuiceStream,
+  BananaShower,
+}
+
+impl CatchSimulator {
+  /// Prepares a simulator for the given beatmap played with the given mods.
+  pub fn new(beatmap: &Beatmap, mods: Mods) -> CatchSimulator {
+    let difficulty = beatmap.difficulty.apply_mods(mods);
+    let scale = 1.0 - 0.7 * (difficulty.circle_size as f64 - 5.0) / 5.0;
+    let catch_width = CATCHER_BASE_SIZE * scale.abs() * ALLOWED_CATCH_RANGE;
+
+    let mut generator = Generator {
+      rng: LegacyRandom::new(RNG_SEED),
+      hard_rock: mods.contains(Mods::HardRock),
+      last_position: None,
+      last_start_time: 0.0,
+      objects: Vec::new(),
+    };
+    let parents = beatmap
+      .hit_objects
+      .iter()
+      .enumerate()
+      .map(|(index, ho)| {
+        let start_time = ho.start_time.0 as f64;
+        match &ho.kind {
+          HitObjectKind::Slider(info) => {
+            let timing = match beatmap.slider_timing(ho) {
+              Some(timing) => timing,
+              None => {
+                generator.fruit(index, start_time, ho.pos.x as f32);
+                return Parent::Fruit;
+              }
+            };
+
+            let mut control_points = vec![ho.pos];
+            control_points.extend(&info.control_points);
+            let spline = Spline::from_control(
+              info.kind,
+              &control_points,
+              Some(info.pixel_length),
+            );
+            let x_at = |progress: f64| {
+              spline
+                .point_at_length(progress * timing.pixel_length)
+                .x
+                .clamp(0.0, PLAYFIELD_WIDTH)
+            };
+
+            // stable uses the last control point rather than the actual end of the path
+            let last_x =
+              control_points.last().map_or(ho.pos.x, |point| point.x);
+            generator.juice_stream(index, &timing, x_at);
+            generator.last_position = Some(last_x as f32);
+            generator.last_start_time = start_time;
+            Parent::JuiceStream
+          }
+          HitObjectKind::Spinner(info) => {
+            generator.banana_shower(index, start_time, info.end_time.0 as f64);
+            Parent::BananaShower
+          }
+          _ => {
+            generator.fruit(index, start_time, ho.pos.x as f32);
+            Parent::Fruit
+          }
+        }
+      })
+      .collect();
+
+    let mut objects = generator.objects;
+    objects.sort_by(|a, b| a.time.total_cmp(&b.time));
+    assign_hyper_dashes(&mut objects, catch_width / 2.0 / ALLOWED_CATCH_RANGE);
+
+    CatchSimulator {
+      objects,
+      parents,
+      catch_width,
+    }
+  }
+
+  /// Every catchable object of the map, in chronological order.
+  pub fn objects(&self) -> &[CatchObject] {
+    &self.objects
+  }
+
+  /// The width of the area of the catcher that can catch objects, after mods have been applied.
+  pub fn catch_width(&self) -> f64 {
+    self.catch_width
+  }
+
+  /// Simulates the given replay actions.
+  pub fn simulate(&self, actions: &ReplayActionData) -> SimulationResult {
+    let timeline = Timeline::new(actions);
+
+    let mut judgements = Vec::new();
+    let mut counts = HitCounts::default();
+    let mut parts_missed = vec![false; self.parents.len()];
+    let mut last_part_time = vec![0.0; self.parents.len()];
+    let half_width = self.catch_width / 2.0;
+
+    for object in self.objects.iter() {
+      let catcher = timeline
+        .position_at(object.time)
+        .map_or(PLAYFIELD_WIDTH / 2.0, |pos| pos.x);
+      let caught = (object.x - catcher).abs() <= half_width;
+
+      let parent = &self.parents[object.object_index];
+      let kind = match (object.kind, parent) {
+        (CatchObjectKind::Fruit, Parent::Fruit) => JudgementKind::Object,
+        (CatchObjectKind::Fruit, _) => JudgementKind::Fruit,
+        (CatchObjectKind::Droplet, _) => JudgementKind::Droplet,
+        (CatchObjectKind::TinyDroplet, _) => JudgementKind::TinyDroplet,
+        (CatchObjectKind::Banana, _) => JudgementKind::Banana,
+      };
+
+      let result = match (object.kind, caught) {
+        (CatchObjectKind::Fruit, true) => {
+          counts.count_300 += 1;
+          HitResult::Hit300
+        }
+        (CatchObjectKind::Droplet, true) => {
+          counts.count_100 += 1;
+          HitResult::Hit100
+        }
+        (CatchObjectKind::TinyDroplet, true) => {
+          counts.count_50 += 1;
+          HitResult::Hit50
+        }
+        (CatchObjectKind::TinyDroplet, false) => {
+          counts.count_katu += 1;
+          HitResult::Miss
+        }
+        (CatchObjectKind::Banana, true) => HitResult::Hit,
+        (_, false) => {
+          if object.kind != CatchObjectKind::Banana {
+            counts.count_miss += 1;
+          }
+          HitResult::Miss
+        }
+      };
+
+      if !caught && kind.affects_combo() {
+        parts_missed[object.object_index] = true;
+      }
+      last_part_time[object.object_index] = object.time;
+      judgements.push(Judgement {
+        object_index: object.object_index,
+        kind,
+        result,
+        time: object.time,
+        offset: None,
+      });
+    }
+
+    // juice streams and banana showers are judged as a whole once their last part is done
+    for (index, parent) in self.parents.iter().enumerate() {
+      let (kind, result) = match parent {
+        Parent::Fruit => continue,
+        Parent::JuiceStream if parts_missed[index] => {
+          (JudgementKind::Object, HitResult::Miss)
+        }
+        Parent::JuiceStream => (JudgementKind::Object, HitResult::Hit),
+        Parent::BananaShower => (JudgementKind::Bonus, HitResult::Hit),
+      };
+      judgements.push(Judgement {
+        object_index: index,
+        kind,
+        result,
+        time: last_part_time[index],
+        offset: None,
+      });
+    }
+
+    SimulationResult::new(judgements, counts)
+  }
+}
+
+/// Generates the catchable objects of a map, in the order osu!stable does.
+struct Generator {
+  rng: LegacyRandom,
+  hard_rock: bool,
+  last_position: Option<f32>,
+  last_start_time: f64,
+  objects: Vec<CatchObject>,
+}
+
+impl Generator {
+  fn push(
+    &mut self,
+    object_index: usize,
+    kind: CatchObjectKind,
+    time: f64,
+    x: f64,
+  ) {
+    self.objects.push(CatchObject {
+      object_index,
+      kind,
+      time,
+      x,
+      hyper_dash: false,
+    });
+  }
+
+  fn fruit(&mut self, index: usize, time: f64, x: f32) {
+    let x = if self.hard_rock {
+      self.hard_rock_offset(time, x)
+    } else {
+      x
+    };
+    self.push(index, CatchObjectKind::Fruit, time, x as f64);
+  }
+
+  /// Moves fruits around like Hard Rock does.
+  fn hard_rock_offset(&mut self, time: f64, original_x: f32) -> f32 {
+    let mut x = original_x;
+    let last_position = match self.last_position {
+      Some(last_position) => last_position,
+      None => {
+        self.last_position = Some(x);
+        self.last_start_time = time;
+        return x;
+      }
+    };
+
+    let position_diff = x - last_position;
+    // stable calculates the time difference as an integer
+    let time_diff = (time - self.last_start_time) as i32;
+    if time_diff > 1000 {
+      self.last_position = Some(x);
+      self.last_start_time = time;
+      return x;
+    }
+
+    if position_diff == 0.0 {
+      // the position isn't remembered in this case
+      let right = self.rng.next_bool();
+      // the maximum offset is worked out with integer division too
+      let max_offset = (time_diff / 4).max(0);
+      let rand = (self.rng.next_int_range(0, max_offset) as f32).min(20.0);
+      let width = PLAYFIELD_WIDTH as f32;
+      if right {
+        x = if x + rand <= width {
+          x + rand
+        } else {
+          x - rand
+        };
+      } else {
+        x = if x - rand >= 0.0 { x - rand } else { x + rand };
+      }
+      return x;
+    }
+
+    if position_diff.abs() < (time_diff / 3) as f32 {
+      if position_diff > 0.0 {
+        if x + position_diff < PLAYFIELD_WIDTH as f32 {
+          x += position_diff;
+        }
+      } else if x + position_diff > 0.0 {
+        x += position_diff;
+      }
+    }
+
+    self.last_position = Some(x);
+    self.last_start_time = time;
+    x
+  }
+
+  fn juice_stream(
+    &mut self,
+    index: usize,
+    timing: &SliderTiming,
+    x_at: impl Fn(f64) -> f64,
+  ) {
+    // the head is a fruit, just like repeats and the tail
+    let head = (false, timing.start_time, 0.0);
+    let events =
+      std::iter::once(head).chain(timing.events().into_iter().map(|event| {
+        (
+          event.kind == SliderEventKind::Tick,
+          event.time,
+          event.progress,
+        )
+      }));
+
+    let mut last: Option<(f64, f64)> = None;
+    for (is_droplet, time, progress) in events {
+      if let Some((last_time, last_progress)) = last {
+        // stable truncates the times before working out the tiny droplets
+        let since_last = (time as i32 - last_time as i32) as f64;
+        if since_last > 80.0 {
+          let mut interval = since_last;
+          while interval > 100.0 {
+            interval /= 2.0;
+          }
+
+          let mut t = interval;
+          while t < since_last {
+            let tiny_progress =
+              last_progress + t / since_last * (progress - last_progress);
+            let original_x = x_at(tiny_progress);
+            let offset = self.rng.next_int_range(-20, 20) as f64;
+            let x = original_x
+              + offset.clamp(-original_x, PLAYFIELD_WIDTH - original_x);
+            self.push(index, CatchObjectKind::TinyDroplet, t + last_time, x);
+            t += interval;
+          }
+        }
+      }
+      last = Some((time, progress));
+
+      let x = x_at(progress);
+      if is_droplet {
+        // stable picks a random rotation for every droplet
+        self.rng.next();
+        self.push(index, CatchObjectKind::Droplet, time, x);
+      } else {
+        self.push(index, CatchObjectKind::Fruit, time, x);
+      }
+    }
+  }
+
+  fn banana_shower(&mut self, index: usize, start_time: f64, end_time: f64) {
+    let mut spacing = end_time - start_time;
+    while spacing > 100.0 {
+      spacing /= 2.0;
+    }
+    if spacing <= 0.0 {
+      return;
+    }
+
+    let mut time = start_time;
+    while time <= end_time {
+      let x = (self.rng.next() % PLAYFIELD_WIDTH as i32) as f64;
+      // stable picks a random type, rotation and colour for every banana
+      self.rng.next();
+      self.rng.next();
+      self.rng.next();
+      self.push(index, CatchObjectKind::Banana, time, x);
+      time += spacing;
+    }
+  }
+}
+
+/// Marks the fruits and droplets that can't be reached from the previous one without
+/// hyperdashing.
+fn assign_hyper_dashes(objects: &mut [CatchObject], half_catcher_width: f64) {
+  let palpable = objects
+    .iter()
+    .enumerate()
+    .filter(|(_, object)| {
+      matches!(
+        object.kind,
+        CatchObjectKind::Fruit | CatchObjectKind::Droplet
+      )
+    })
+    .map(|(index, _)| index)
+    .collect::<Vec<_>>();
+
+  let mut last_direction = 0;
+  let mut last_excess = half_catcher_width;
+  for pair in palpable.windows(2) {
+    let current = &objects[pair[0]];
+    let next = &objects[pair[1]];
+    let direction = if next.x > current.x { 1 } else { -1 };
+
+    // a quarter of a frame of grace time
+    let time_to_next = next.time - current.time - 1000.0 / 60.0 / 4.0;
+    let distance_to_next = (next.x - current.x).abs()
+      - if last_direction == direction {
+        last_excess
+      } else {
+        half_catcher_width
+      };
+    let distance_to_hyper =
+      (time_to_next * BASE_DASH_SPEED - distance_to_next) as f32;
+
+    if distance_to_hyper < 0.0 {
+      objects[pair[0]].hyper_dash = true;
+      last_excess = half_catcher_width;
+    } else {
+      last_excess = (distance_to_hyper as f64).clamp(0.0, half_catcher_width);
+    }
+    last_direction = direction;
+  }
+}
+
+/// The random number generator used by osu!stable.
+struct LegacyRandom {
+  x: u32,
+  y: u32,
+  z: u32,
+  w: u32,
+  bit_buffer: u32,
+  bit_index: u32,
+}
+
+impl LegacyRandom {
+  fn new(seed: i32) -> Self {
+    LegacyRandom {
+      x: seed as u32,
+      y: 842502087,
+      z: 3579807591,
+      w: 273326509,
+      bit_buffer: 0,
+      bit_index: 32,
+    }
+  }
+
+  fn next_u32(&mut self) -> u32 {
+    let t = self.x ^ (self.x << 11);
+    self.x = self.y;
+    self.y = self.z;
+    self.z = self.w;
+    self.w = self.w ^ (self.w >> 19) ^ t ^ (t >> 8);
+    self.w
+  }
+
+  fn next(&mut self) -> i32 {
+    (self.next_u32() & 0x7FFF_FFFF) as i32
+  }
+
+  fn next_double(&mut self) -> f64 {
+    self.next() as f64 / (i32::MAX as f64 + 1.0)
+  }
+
+  /// A random integer from `lower` up to but not including `upper`, like stable's
+  /// `Next(int, int)`.
+  fn next_int_range(&mut self, lower: i32, upper: i32) -> i32 {
+    (lower as f64 + self.next_double() * (upper - lower) as f64) as i32
+  }
+
+  fn next_bool(&mut self) -> bool {
+    if self.bit_index == 32 {
+      self.bit_buffer = self.next_u32();
+      self.bit_index = 1;
+      return self.bit_buffer & 1 == 1;
+    }
+    self.bit_index += 1;
+    self.bit_buffer >>= 1;
+    self.bit_buffer & 1 == 1
+  }
+}
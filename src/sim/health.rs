@@ -0,0 +1,395 @@
+use crate::beatmap::{difficulty_range, Beatmap, SliderEventKind};
+use crate::data::Mods;
+use crate::events::Event;
+use crate::hitobject::HitObjectKind;
+
+use super::{HitResult, Judgement, JudgementKind};
+
+/// How often osu!stable adds a point to the life graph, in milliseconds.
+const LIFE_GRAPH_INTERVAL: f64 = 2000.0;
+
+/// The drain rate (in life per millisecond) that the search for the map's drain rate starts at,
+/// which empties the bar in a second.
+const INITIAL_DRAIN_RATE: f64 = 0.001;
+
+/// Gives up on lowering the drain rate after this many tries, for maps that can't be passed even
+/// without drain.
+const MAX_DRAIN_RATE_STEPS: usize = 1000;
+
+/// Life gained for judgements in osu!standard, as a share of the whole bar, before the map's
+/// health multiplier is applied.
+const HP_HIT_300: f64 = 0.03;
+const HP_HIT_100: f64 = 0.011;
+const HP_HIT_50: f64 = 0.002;
+const HP_SLIDER_REPEAT: f64 = 0.02;
+const HP_SLIDER_TICK: f64 = 0.015;
+const HP_SPINNER_SPIN: f64 = 0.0085;
+const HP_SPINNER_BONUS: f64 = 0.01;
+
+/// Reproduces how osu!stable drains and refills the health bar in osu!standard, to work out the
+/// life graph of a play and when it failed.
+///
+/// Life goes from 0 to 1 and starts full. Between the start of the first object and the end of
+/// the last one, it drains at a steady rate, except during breaks. Every judgement then adds or
+/// takes away life depending on its result.
+///
+/// Like the game, the drain rate isn't taken directly from the HP drain rate setting of the map.
+/// Instead, it's lowered until a perfect play never drops below a certain amount of life, which
+/// is higher for maps with lower HP drain rates. Along the way, the life gained from hits may be
+/// raised a bit as well. The life gained at the end of each combo for gekis and katus isn't
+/// included.
+///
+/// ```no_run
+/// # use libosu::{beatmap::Beatmap, replay::Replay, sim::{HealthSimulator, OsuSimulator}};
+/// # #[cfg(feature = "replay-data")]
+/// # fn invisible(beatmap: Beatmap, replay: Replay) -> anyhow::Result<()> {
+/// #
+/// let action_data = replay.parse_action_data()?;
+/// let result = OsuSimulator::new(&beatmap, replay.mods).simulate(&action_data);
+/// let health = HealthSimulator::new(&beatmap, replay.mods).simulate(&result.judgements);
+/// if let Some(fail_time) = health.fail_time {
+///     println!("failed at {}ms", fail_time);
+/// }
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HealthSimulator {
+  mods: Mods,
+  hp_drain_rate: f64,
+  drain_rate: f64,
+  hit_multiplier: f64,
+
+  /// The times at which the health drains, as sorted and non-overlapping ranges
+  drain_periods: Vec<(f64, f64)>,
+}
+
+/// The outcome of simulating the health bar.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HealthResult {
+  /// The life at every point of the graph, in the same format as [`Replay::life_graph`]: pairs of
+  /// times in milliseconds and life between 0 and 1, rounded to two decimals like osu!stable
+  /// does.
+  ///
+  /// [`Replay::life_graph`]: crate::replay::Replay::life_graph
+  pub life_graph: Vec<(i32, f64)>,
+
+  /// The time at which the play failed, in milliseconds, or `None` if it passed. With No Fail,
+  /// plays never fail.
+  pub fail_time: Option<f64>,
+
+  /// The life at the end of the play, or when it failed.
+  pub final_health: f64,
+
+  /// The lowest the life got during the play.
+  pub min_health: f64,
+}
+
+impl HealthSimulator {
+  /// Prepares to simulate the health bar for the given beatmap played with the given mods,
+  /// working out its drain rate.
+  pub fn new(beatmap: &Beatmap, mods: Mods) -> HealthSimulator {
+    let hp_drain_rate =
+      beatmap.difficulty.apply_mods(mods).hp_drain_rate as f64;
+
+    let objects = beatmap
+      .hit_objects
+      .iter()
+      .map(|ho| {
+        let start_time = ho.start_time.0 as f64;
+        match &ho.kind {
+          HitObjectKind::Slider(_) => match beatmap.slider_timing(ho) {
+            Some(timing) => {
+              let parts = timing
+                .events()
+                .iter()
+                .map(|event| match event.kind {
+                  SliderEventKind::Tick => HP_SLIDER_TICK,
+                  SliderEventKind::Repeat | SliderEventKind::Tail => {
+                    HP_SLIDER_REPEAT
+                  }
+                })
+                .sum::<f64>();
+              (start_time, timing.end_time(), HP_SLIDER_REPEAT + parts)
+            }
+            None => (start_time, start_time, 0.0),
+          },
+          HitObjectKind::Spinner(info) => {
+            (start_time, info.end_time.0 as f64, 0.0)
+          }
+          HitObjectKind::HoldNote(info) => {
+            (start_time, info.end_time.0 as f64, 0.0)
+          }
+          HitObjectKind::Circle => (start_time, start_time, 0.0),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let breaks = beatmap
+      .events
+      .iter()
+      .filter_map(|event| match event {
+        Event::Break(info) => {
+          Some((info.start_time.0 as f64, info.end_time.0 as f64))
+        }
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    let drain_periods = match (objects.first(), objects.last()) {
+      (Some(first), Some(last)) => {
+        let end_time =
+          objects.iter().map(|object| object.1).fold(last.1, f64::max);
+        drain_periods(first.0, end_time, breaks)
+      }
+      _ => Vec::new(),
+    };
+
+    let mut sim = HealthSimulator {
+      mods,
+      hp_drain_rate,
+      drain_rate: INITIAL_DRAIN_RATE,
+      hit_multiplier: 1.0,
+      drain_periods,
+    };
+    sim.fit_drain_rate(&objects);
+    sim
+  }
+
+  /// How much life drains every millisecond outside of breaks.
+  pub fn drain_rate(&self) -> f64 {
+    self.drain_rate
+  }
+
+  /// How much the life gained from hits is multiplied by on this map.
+  pub fn hit_multiplier(&self) -> f64 {
+    self.hit_multiplier
+  }
+
+  /// How much life the given judgement adds (or takes away, if negative).
+  pub fn health_change(&self, judgement: &Judgement) -> f64 {
+    let miss =
+      |min, mid, max| -difficulty_range(self.hp_drain_rate, min, mid, max);
+    let gain = match (judgement.kind, judgement.result) {
+      (JudgementKind::SliderTail, HitResult::Miss) => 0.0,
+      (JudgementKind::Object, HitResult::Miss) => {
+        return miss(0.03, 0.125, 0.2)
+      }
+      (_, HitResult::Miss) if judgement.kind.affects_combo() => {
+        return miss(0.02, 0.075, 0.14)
+      }
+      (_, HitResult::Miss) => 0.0,
+      (JudgementKind::Object, result) => match result {
+        HitResult::Hit320 | HitResult::Hit300 => HP_HIT_300,
+        HitResult::Hit200 | HitResult::Hit100 => HP_HIT_100,
+        HitResult::Hit50 => HP_HIT_50,
+        _ => 0.0,
+      },
+      (JudgementKind::SliderHead, _)
+      | (JudgementKind::SliderRepeat, _)
+      | (JudgementKind::SliderTail, _) => HP_SLIDER_REPEAT,
+      (JudgementKind::SliderTick, _) => HP_SLIDER_TICK,
+      (JudgementKind::SpinnerSpin, _) => HP_SPINNER_SPIN,
+      (JudgementKind::SpinnerBonus, _) => HP_SPINNER_BONUS,
+      _ => 0.0,
+    };
+    gain * self.hit_multiplier
+  }
+
+  /// Runs the health bar through the given judgements, which are expected to be in
+  /// chronological order like the ones in a [`SimulationResult`][super::SimulationResult].
+  ///
+  /// With Sudden Death, the play fails on the first judgement that breaks the combo, and with
+  /// Perfect also on the first object that isn't a 300. With No Fail, it never fails and the
+  /// life just stays at 0 until it's raised again.
+  pub fn simulate(&self, judgements: &[Judgement]) -> HealthResult {
+    let no_fail = self.mods.contains(Mods::NoFail);
+    let mut state = HealthState {
+      sim: self,
+      time: self.drain_periods.first().map_or(0.0, |period| period.0),
+      health: 1.0,
+      min_health: 1.0,
+      next_sample: None,
+      life_graph: Vec::new(),
+    };
+    state.next_sample = Some(state.time + LIFE_GRAPH_INTERVAL);
+
+    let mut fail_time = None;
+    for judgement in judgements {
+      if let Some(time) = state.drain_to(judgement.time) {
+        if !no_fail {
+          fail_time = Some(time);
+          break;
+        }
+      }
+
+      let health = state.health + self.health_change(judgement);
+      state.health = health.clamp(0.0, 1.0);
+      state.min_health = state.min_health.min(state.health);
+      if (health <= 0.0 || self.fails_on(judgement)) && !no_fail {
+        state.health = 0.0;
+        state.min_health = 0.0;
+        fail_time = Some(judgement.time);
+        break;
+      }
+    }
+
+    if fail_time.is_none() {
+      let end_time = self.drain_periods.last().map_or(0.0, |period| period.1);
+      if let Some(time) = state.drain_to(end_time) {
+        if !no_fail {
+          fail_time = Some(time);
+        }
+      }
+    }
+    if let Some(fail_time) = fail_time {
+      state.life_graph.push((fail_time.round() as i32, 0.0));
+    }
+
+    HealthResult {
+      life_graph: state.life_graph,
+      fail_time,
+      final_health: state.health,
+      min_health: state.min_health,
+    }
+  }
+
+  /// Whether the given judgement fails the play because of Sudden Death or Perfect.
+  fn fails_on(&self, judgement: &Judgement) -> bool {
+    let perfect = self.mods.contains(Mods::Perfect);
+    let sudden_death = perfect || self.mods.contains(Mods::SuddenDeath);
+    let imperfect = judgement.kind == JudgementKind::Object
+      && matches!(
+        judgement.result,
+        HitResult::Hit200 | HitResult::Hit100 | HitResult::Hit50
+      );
+    (sudden_death && judgement.breaks_combo()) || (perfect && imperfect)
+  }
+
+  /// The number of milliseconds the health drains for between two times.
+  fn drain_time(&self, start: f64, end: f64) -> f64 {
+    self
+      .drain_periods
+      .iter()
+      .map(|(period_start, period_end)| {
+        (end.min(*period_end) - start.max(*period_start)).max(0.0)
+      })
+      .sum()
+  }
+
+  /// Lowers the drain rate and raises the life gained from hits until a perfect play of the
+  /// given objects (start and end times, and life gained from slider parts) stays high enough.
+  fn fit_drain_rate(&mut self, objects: &[(f64, f64, f64)]) {
+    let lowest_ever = difficulty_range(self.hp_drain_rate, 0.975, 0.8, 0.3);
+    let lowest_end = difficulty_range(self.hp_drain_rate, 0.99, 0.9, 0.4);
+
+    for _ in 0..MAX_DRAIN_RATE_STEPS {
+      let mut health = 1.0;
+      let mut last_time = objects.first().map_or(0.0, |object| object.0);
+      let mut too_low = false;
+      for &(start_time, end_time, parts) in objects {
+        health -= self.drain_time(last_time, start_time) * self.drain_rate;
+        if health <= lowest_ever {
+          too_low = true;
+          break;
+        }
+        health -= self.drain_time(start_time, end_time) * self.drain_rate;
+        last_time = end_time;
+        health = (health + (HP_HIT_300 + parts) * self.hit_multiplier).min(1.0);
+      }
+
+      if too_low {
+        self.drain_rate *= 0.96;
+      } else if health < lowest_end {
+        self.drain_rate *= 0.94;
+        self.hit_multiplier *= 1.01;
+      } else {
+        return;
+      }
+    }
+  }
+}
+
+/// Keeps track of the health bar while simulating.
+struct HealthState<'a> {
+  sim: &'a HealthSimulator,
+  time: f64,
+  health: f64,
+  min_health: f64,
+  next_sample: Option<f64>,
+  life_graph: Vec<(i32, f64)>,
+}
+
+impl HealthState<'_> {
+  /// Drains the health up to the given time, adding points to the life graph along the way.
+  /// Returns the time at which the health ran out, if it did.
+  fn drain_to(&mut self, time: f64) -> Option<f64> {
+    let mut emptied = None;
+    while self.time < time {
+      let step_end = match self.next_sample {
+        Some(sample) if sample <= time => sample,
+        _ => time,
+      };
+
+      let drained =
+        self.sim.drain_time(self.time, step_end) * self.sim.drain_rate;
+      if emptied.is_none() && drained >= self.health && self.health > 0.0 {
+        emptied = Some(self.empty_time(step_end));
+      }
+      self.health = (self.health - drained).max(0.0);
+      self.min_health = self.min_health.min(self.health);
+      self.time = step_end;
+
+      if self.next_sample == Some(step_end) {
+        let life = (self.health * 100.0).round() / 100.0;
+        self.life_graph.push((step_end.round() as i32, life));
+        self.next_sample = Some(step_end + LIFE_GRAPH_INTERVAL);
+      }
+      if emptied.is_some() && !self.sim.mods.contains(Mods::NoFail) {
+        break;
+      }
+    }
+    emptied
+  }
+
+  /// The time before `end` at which draining from the current time empties the health.
+  fn empty_time(&self, end: f64) -> f64 {
+    let mut remaining = self.health / self.sim.drain_rate;
+    for &(start, period_end) in self.sim.drain_periods.iter() {
+      let from = start.max(self.time);
+      let to = period_end.min(end);
+      if to <= from {
+        continue;
+      }
+      if to - from >= remaining {
+        return from + remaining;
+      }
+      remaining -= to - from;
+    }
+    end
+  }
+}
+
+/// Splits the time from `start` to `end` into the periods that aren't covered by the given
+/// breaks.
+fn drain_periods(
+  start: f64,
+  end: f64,
+  mut breaks: Vec<(f64, f64)>,
+) -> Vec<(f64, f64)> {
+  breaks.sort_by(|a, b| a.0.total_cmp(&b.0));
+  let mut periods = Vec::new();
+  let mut time = start;
+  for (break_start, break_end) in breaks {
+    if break_start > time {
+      periods.push((time, break_start.min(end)));
+    }
+    time = time.max(break_end);
+  }
+  if time < end {
+    periods.push((time, end));
+  }
+  periods.retain(|(start, end)| start < end);
+  periods
+}
@@ -0,0 +1,382 @@
+use crate::beatmap::Beatmap;
+use crate::data::{HitCounts, Mods};
+use crate::hitobject::HitObjectKind;
+use crate::replay::{ReplayActionData, Timeline};
+
+use super::{
+  HitResult, HitWindows, Judgement, JudgementKind, SimulationResult,
+};
+
+/// The windows for releasing a hold note are this many times larger than the ones for pressing.
+const RELEASE_LENIENCE: f64 = 1.5;
+
+/// Reproduces osu!mania's judgement of a replay.
+///
+/// In osu!mania replays, the x coordinate of every frame holds the pressed columns as a bitmask,
+/// with the lowest bit being the leftmost column. The simulator follows the rules of osu!stable:
+///
+/// - Each newly pressed column hits the earliest note in that column that hasn't been judged
+///   yet. Pressing before a note but outside of its 50 window counts as a miss.
+/// - A hold note whose head was hit is judged by the head offset and the release offset
+///   combined. Releasing it too early breaks the combo and gives a 50, and holding it past the
+///   release window counts as a perfect release.
+/// - A hold note whose head was missed gives a 50 if the column is held when it ends.
+///
+/// Only maps made for osu!mania are supported, converted maps and the Random mod aren't. Hold
+/// notes give one combo for their head and one for their release, the ticks along their body
+/// aren't simulated.
+#[derive(Clone, Debug)]
+pub struct ManiaSimulator {
+  keys: usize,
+  pub(crate) notes: Vec<Note>,
+  columns: Vec<Vec<usize>>,
+  windows: HitWindows,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Note {
+  pub(crate) column: usize,
+  pub(crate) start_time: f64,
+  pub(crate) end_time: Option<f64>,
+}
+
+/// The mutable state of a single note during the simulation.
+#[derive(Clone, Debug, Default)]
+struct NoteState {
+  head_hit: bool,
+  head_offset: f64,
+  broken: bool,
+}
+
+/// The mutable state of a single column during the simulation.
+#[derive(Clone, Debug, Default)]
+struct ColumnState {
+  /// The position in the column of the first note whose head hasn't been judged
+  next: usize,
+
+  /// The hold note that has been started but not finished yet
+  active: Option<usize>,
+
+  held: bool,
+}
+
+struct Simulation<'a> {
+  sim: &'a ManiaSimulator,
+  notes: Vec<NoteState>,
+  columns: Vec<ColumnState>,
+  judgements: Vec<Judgement>,
+  results: Vec<Option<HitResult>>,
+}
+
+impl ManiaSimulator {
+  /// Prepares a simulator for the given beatmap played with the given mods.
+  ///
+  /// The number of columns is taken from the circle size of the map.
+  pub fn new(beatmap: &Beatmap, mods: Mods) -> ManiaSimulator {
+    let keys = (beatmap.difficulty.circle_size.round() as usize).max(1);
+    let windows =
+      HitWindows::mania(beatmap.difficulty.overall_difficulty, mods);
+
+    let notes = beatmap
+      .hit_objects
+      .iter()
+      .map(|ho| {
+        let column = ((ho.pos.x.max(0) as usize * keys) / 512).min(keys - 1);
+        let end_time = match &ho.kind {
+          HitObjectKind::HoldNote(info) => Some(info.end_time.0 as f64),
+          _ => None,
+        };
+        Note {
+          column,
+          start_time: ho.start_time.0 as f64,
+          end_time,
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let mut columns = vec![Vec::new(); keys];
+    for (index, note) in notes.iter().enumerate() {
+      columns[note.column].push(index);
+    }
+    for column in columns.iter_mut() {
+      column
+        .sort_by(|a, b| notes[*a].start_time.total_cmp(&notes[*b].start_time));
+    }
+
+    ManiaSimulator {
+      keys,
+      notes,
+      columns,
+      windows,
+    }
+  }
+
+  /// The number of columns (keys) of the map.
+  pub fn keys(&self) -> usize {
+    self.keys
+  }
+
+  /// The column of the hit object with the given index, counting from the left.
+  pub fn column(&self, object_index: usize) -> Option<usize> {
+    self.notes.get(object_index).map(|note| note.column)
+  }
+
+  /// The hit windows used by this simulator, after mods have been applied.
+  pub fn hit_windows(&self) -> HitWindows {
+    self.windows
+  }
+
+  /// Groups the offsets of the key presses that hit a note by column.
+  ///
+  /// Releases of hold notes and key presses that caused a miss aren't included.
+  pub fn column_hit_errors(&self, result: &SimulationResult) -> Vec<Vec<f64>> {
+    let mut errors = vec![Vec::new(); self.keys];
+    for judgement in result.judgements.iter() {
+      if !judgement.is_hit_error() {
+        continue;
+      }
+      if let (Some(offset), Some(column)) =
+        (judgement.offset, self.column(judgement.object_index))
+      {
+        errors[column].push(offset);
+      }
+    }
+    errors
+  }
+
+  /// Simulates the given replay actions.
+  pub fn simulate(&self, actions: &ReplayActionData) -> SimulationResult {
+    let mut sim = Simulation {
+      sim: self,
+      notes: vec![NoteState::default(); self.notes.len()],
+      columns: vec![ColumnState::default(); self.keys],
+      judgements: Vec::new(),
+      results: vec![None; self.notes.len()],
+    };
+
+    for frame in Timeline::new(actions).frames() {
+      let time = frame.time as f64;
+      // the pressed columns are stored in the x coordinate
+      let pressed = frame.pos.x as u32;
+      for column in 0..self.keys {
+        sim.expire(column, time);
+
+        let down = pressed & (1 << column) != 0;
+        let held = sim.columns[column].held;
+        if down && !held {
+          sim.press(column, time);
+        } else if !down && held {
+          sim.release(column, time);
+        }
+        sim.columns[column].held = down;
+      }
+    }
+    sim.finish();
+
+    let counts = sim.counts();
+    SimulationResult::new(sim.judgements, counts)
+  }
+}
+
+impl Simulation<'_> {
+  fn judge(
+    &mut self,
+    index: usize,
+    kind: JudgementKind,
+    result: HitResult,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    if kind == JudgementKind::Object {
+      self.results[index] = Some(result);
+    }
+    self.judgements.push(Judgement {
+      object_index: index,
+      kind,
+      result,
+      time,
+      offset,
+    });
+  }
+
+  fn judge_head(
+    &mut self,
+    column: usize,
+    index: usize,
+    result: HitResult,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    let sim = self.sim;
+    self.columns[column].next += 1;
+    if sim.notes[index].end_time.is_none() {
+      self.judge(index, JudgementKind::Object, result, time, offset);
+      return;
+    }
+
+    self.notes[index].head_hit = !result.is_miss();
+    self.notes[index].head_offset = offset.unwrap_or(0.0);
+    self.judge(index, JudgementKind::HoldNoteHead, result, time, offset);
+
+    // overlapping hold notes can't be held at the same time
+    self.expire_hold(column, f64::INFINITY);
+    self.columns[column].active = Some(index);
+  }
+
+  /// Judges everything in the column that can't be affected by key presses anymore.
+  fn expire(&mut self, column: usize, time: f64) {
+    let sim = self.sim;
+    self.expire_hold(column, time);
+
+    let late_limit = sim.windows.late_limit();
+    while let Some(&index) = sim.columns[column].get(self.columns[column].next)
+    {
+      let expires = sim.notes[index].start_time + late_limit;
+      if expires > time {
+        break;
+      }
+      self.judge_head(column, index, HitResult::Miss, expires, None);
+      self.expire_hold(column, time);
+    }
+  }
+
+  /// Finishes the active hold note of the column if it has ended by the given time.
+  fn expire_hold(&mut self, column: usize, time: f64) {
+    let sim = self.sim;
+    let index = match self.columns[column].active {
+      Some(index) => index,
+      None => return,
+    };
+    let end_time = sim.notes[index].end_time.unwrap_or_default();
+    let state = &self.notes[index];
+
+    if state.head_hit && !state.broken {
+      // still held after the release window
+      let deadline = end_time + sim.windows.hit50 * RELEASE_LENIENCE;
+      if deadline <= time {
+        self.release_hold(column, index, deadline, None);
+      }
+    } else if end_time <= time {
+      let result = if state.broken {
+        HitResult::Hit50
+      } else if self.columns[column].held {
+        self.judge(
+          index,
+          JudgementKind::HoldNoteTail,
+          HitResult::Hit,
+          end_time,
+          None,
+        );
+        HitResult::Hit50
+      } else {
+        self.judge(
+          index,
+          JudgementKind::HoldNoteTail,
+          HitResult::Miss,
+          end_time,
+          None,
+        );
+        HitResult::Miss
+      };
+      self.judge(index, JudgementKind::Object, result, end_time, None);
+      self.columns[column].active = None;
+    }
+  }
+
+  /// Judges a hold note whose head was hit and that was released in time.
+  fn release_hold(
+    &mut self,
+    column: usize,
+    index: usize,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    let windows = self.sim.windows;
+    let head = self.notes[index].head_offset.abs();
+    let combined = head + offset.unwrap_or(0.0).abs();
+    let result =
+      if head < windows.hit320 * 1.2 && combined < windows.hit320 * 2.4 {
+        HitResult::Hit320
+      } else if head < windows.hit300 * 1.1 && combined < windows.hit300 * 2.2 {
+        HitResult::Hit300
+      } else if head < windows.hit200 && combined < windows.hit200 * 2.0 {
+        HitResult::Hit200
+      } else if head < windows.hit100 && combined < windows.hit100 * 2.0 {
+        HitResult::Hit100
+      } else {
+        HitResult::Hit50
+      };
+
+    self.judge(
+      index,
+      JudgementKind::HoldNoteTail,
+      HitResult::Hit,
+      time,
+      offset,
+    );
+    self.judge(index, JudgementKind::Object, result, time, None);
+    self.columns[column].active = None;
+  }
+
+  fn press(&mut self, column: usize, time: f64) {
+    let sim = self.sim;
+    let index = match sim.columns[column].get(self.columns[column].next) {
+      Some(&index) => index,
+      None => return,
+    };
+
+    let offset = time - sim.notes[index].start_time;
+    if let Some(result) = sim.windows.result_for(offset) {
+      self.judge_head(column, index, result, time, Some(offset));
+    }
+  }
+
+  fn release(&mut self, column: usize, time: f64) {
+    let sim = self.sim;
+    let index = match self.columns[column].active {
+      Some(index) => index,
+      None => return,
+    };
+    let state = &self.notes[index];
+    if !state.head_hit || state.broken {
+      return;
+    }
+
+    let offset = time - sim.notes[index].end_time.unwrap_or_default();
+    if offset <= -sim.windows.hit50 * RELEASE_LENIENCE {
+      // released too early, the rest of the hold note is judged when it ends
+      self.notes[index].broken = true;
+      self.judge(
+        index,
+        JudgementKind::HoldNoteTail,
+        HitResult::Miss,
+        time,
+        Some(offset),
+      );
+    } else {
+      self.release_hold(column, index, time, Some(offset));
+    }
+  }
+
+  /// Judges everything that's left after the replay has ended.
+  fn finish(&mut self) {
+    for column in 0..self.sim.keys {
+      self.expire(column, f64::INFINITY);
+    }
+  }
+
+  fn counts(&self) -> HitCounts {
+    let mut counts = HitCounts::default();
+    for result in self.results.iter() {
+      match result.unwrap_or(HitResult::Miss) {
+        HitResult::Hit320 => counts.count_geki += 1,
+        HitResult::Hit300 => counts.count_300 += 1,
+        HitResult::Hit200 => counts.count_katu += 1,
+        HitResult::Hit100 => counts.count_100 += 1,
+        HitResult::Hit50 => counts.count_50 += 1,
+        _ => counts.count_miss += 1,
+      }
+    }
+    counts
+  }
+}
@@ -0,0 +1,428 @@
+//! Reproducing the game's judgement of replays
+//!
+//! The simulators in this module take a [`Beatmap`][crate::beatmap::Beatmap] and the action data
+//! of a replay, and work out which judgement the game would have given every hit object. The
+//! resulting counts can be compared against the ones stored in the [`Replay`] itself, and the
+//! judgements can be fed to [`HealthSimulator`] to get the life graph of the play.
+//!
+//! Example
+//! -------
+//!
+//! ```no_run
+//! # use libosu::{beatmap::Beatmap, replay::Replay, sim::OsuSimulator};
+//! # #[cfg(feature = "replay-data")]
+//! # fn invisible(beatmap: Beatmap, replay: Replay) -> anyhow::Result<()> {
+//! #
+//! let action_data = replay.parse_action_data()?;
+//! let result = OsuSimulator::new(&beatmap, replay.mods).simulate(&action_data);
+//! println!("{:?} (max combo {})", result.counts, result.max_combo);
+//! assert!(result.matches_replay(&replay));
+//!
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! All times in this module are measured in milliseconds of map time, so rate changing mods don't
+//! affect hit windows or durations, except in osu!mania (see [`HitWindows::mania`]).
+
+mod catch;
+mod health;
+pub(crate) mod mania;
+pub(crate) mod osu;
+pub(crate) mod taiko;
+
+use std::collections::HashSet;
+
+use crate::beatmap::difficulty_range;
+use crate::beatmap::Beatmap;
+use crate::data::{HitCounts, Mode, Mods};
+use crate::replay::{Buttons, Replay, ReplayActionData};
+
+pub use self::catch::{CatchObject, CatchObjectKind, CatchSimulator};
+pub use self::health::{HealthResult, HealthSimulator};
+pub use self::mania::ManiaSimulator;
+pub use self::osu::OsuSimulator;
+pub use self::taiko::TaikoSimulator;
+
+/// Simulates a replay with the simulator for its game mode.
+pub fn simulate_replay(
+  beatmap: &Beatmap,
+  replay: &Replay,
+  actions: &ReplayActionData,
+) -> SimulationResult {
+  match replay.mode {
+    Mode::Osu => OsuSimulator::new(beatmap, replay.mods).simulate(actions),
+    Mode::Taiko => TaikoSimulator::new(beatmap, replay.mods).simulate(actions),
+    Mode::Catch => CatchSimulator::new(beatmap, replay.mods).simulate(actions),
+    Mode::Mania => ManiaSimulator::new(beatmap, replay.mods).simulate(actions),
+  }
+}
+
+/// The result of a single judgement.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HitResult {
+  /// The object (or the part of it) was missed.
+  Miss,
+
+  /// A 50
+  Hit50,
+
+  /// A 100
+  Hit100,
+
+  /// A 200 (osu!mania only)
+  Hit200,
+
+  /// A 300
+  Hit300,
+
+  /// A MAX, also known as a rainbow 300 (osu!mania only)
+  Hit320,
+
+  /// A part of a hit object that isn't graded on accuracy, such as a slider tick, was hit.
+  Hit,
+}
+
+impl HitResult {
+  /// Whether this result is a miss.
+  pub fn is_miss(&self) -> bool {
+    matches!(self, HitResult::Miss)
+  }
+}
+
+/// What part of a hit object a [`Judgement`] was given for.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JudgementKind {
+  /// The hit object as a whole. Exactly one of these or [`Bonus`][JudgementKind::Bonus] is
+  /// given to every hit object.
+  Object,
+
+  /// The hit object as a whole, for objects that only give bonus score and don't affect the
+  /// combo or the hit counts, like osu!taiko drumrolls and swells.
+  Bonus,
+
+  /// The head of a slider
+  SliderHead,
+
+  /// A tick along the body of a slider
+  SliderTick,
+
+  /// A slider repeat (reverse arrow)
+  SliderRepeat,
+
+  /// The end of a slider
+  SliderTail,
+
+  /// The start of an osu!mania hold note
+  HoldNoteHead,
+
+  /// The release of an osu!mania hold note
+  HoldNoteTail,
+
+  /// The second key press on an osu!taiko big note
+  StrongHit,
+
+  /// A tick of an osu!taiko drumroll
+  DrumRollTick,
+
+  /// A hit on an osu!taiko swell
+  SwellTick,
+
+  /// A fruit that's part of an osu!catch juice stream
+  Fruit,
+
+  /// A large droplet of an osu!catch juice stream
+  Droplet,
+
+  /// A tiny droplet of an osu!catch juice stream
+  TinyDroplet,
+
+  /// A banana of an osu!catch banana shower
+  Banana,
+
+  /// A whole rotation of an osu!standard spinner
+  SpinnerSpin,
+
+  /// A rotation of an osu!standard spinner that gave bonus score, after it was cleared. These
+  /// come along with the [`SpinnerSpin`][JudgementKind::SpinnerSpin] for the same rotation.
+  SpinnerBonus,
+}
+
+impl JudgementKind {
+  /// Whether judgements of this kind can affect the combo.
+  pub fn affects_combo(&self) -> bool {
+    !matches!(
+      self,
+      JudgementKind::Bonus
+        | JudgementKind::StrongHit
+        | JudgementKind::DrumRollTick
+        | JudgementKind::SwellTick
+        | JudgementKind::TinyDroplet
+        | JudgementKind::Banana
+        | JudgementKind::SpinnerSpin
+        | JudgementKind::SpinnerBonus
+    )
+  }
+
+  /// Whether this is the judgement of a hit object as a whole.
+  pub fn is_whole_object(&self) -> bool {
+    matches!(self, JudgementKind::Object | JudgementKind::Bonus)
+  }
+}
+
+/// A single judgement given to a hit object, or to a part of it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Judgement {
+  /// The index of the judged hit object in `Beatmap::hit_objects`.
+  pub object_index: usize,
+
+  /// Which part of the hit object was judged.
+  pub kind: JudgementKind,
+
+  /// The result of the judgement.
+  pub result: HitResult,
+
+  /// The time at which the judgement was given.
+  pub time: f64,
+
+  /// How early (negative) or late (positive) the key press that caused this judgement was.
+  ///
+  /// This is only present for judgements caused by pressing a key, or by releasing one in the
+  /// case of osu!mania hold notes.
+  pub offset: Option<f64>,
+}
+
+impl Judgement {
+  /// Whether this judgement increases the combo.
+  pub fn increases_combo(&self) -> bool {
+    self.kind.affects_combo() && !self.result.is_miss()
+  }
+
+  /// Whether this judgement was given for hitting an object with a key press, so its offset
+  /// counts towards the hit error.
+  ///
+  /// This is the case for circles, the heads of sliders and hold notes, and osu!taiko notes,
+  /// unless they were missed.
+  pub fn is_hit_error(&self) -> bool {
+    let is_hit = matches!(
+      self.kind,
+      JudgementKind::Object
+        | JudgementKind::SliderHead
+        | JudgementKind::HoldNoteHead
+    );
+    is_hit && !self.result.is_miss() && self.offset.is_some()
+  }
+
+  /// Whether this judgement resets the combo.
+  pub fn breaks_combo(&self) -> bool {
+    // missing the end of a slider only loses the combo it would have given
+    self.kind.affects_combo()
+      && self.result.is_miss()
+      && self.kind != JudgementKind::SliderTail
+  }
+}
+
+/// The hit windows of a beatmap, in milliseconds.
+///
+/// A key press gets a judgement if the absolute difference between its time and the object's
+/// time is strictly less than the window. Windows are truncated to whole milliseconds like the
+/// game does. Windows that don't exist in a mode are set to 0.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HitWindows {
+  /// Window for a MAX (osu!mania only)
+  pub hit320: f64,
+
+  /// Window for a 300
+  pub hit300: f64,
+
+  /// Window for a 200 (osu!mania only)
+  pub hit200: f64,
+
+  /// Window for a 100
+  pub hit100: f64,
+
+  /// Window for a 50
+  pub hit50: f64,
+
+  /// Key presses within this window that are outside of every other window count as a miss.
+  pub miss: f64,
+}
+
+impl HitWindows {
+  /// Hit windows for osu!standard with the given overall difficulty.
+  pub fn osu(overall_difficulty: f32) -> HitWindows {
+    let od = overall_difficulty as f64;
+    HitWindows {
+      hit320: 0.0,
+      hit300: difficulty_range(od, 80.0, 50.0, 20.0).floor(),
+      hit200: 0.0,
+      hit100: difficulty_range(od, 140.0, 100.0, 60.0).floor(),
+      hit50: difficulty_range(od, 200.0, 150.0, 100.0).floor(),
+      miss: 400.0,
+    }
+  }
+
+  /// Hit windows for osu!mania with the given overall difficulty and mods.
+  ///
+  /// Hard Rock and Easy divide and multiply the windows by 1.4 instead of changing the overall
+  /// difficulty. Unlike the other modes, the windows stay the same in real time when the rate
+  /// is changed, so they're scaled by the speed multiplier when converted to map time.
+  pub fn mania(overall_difficulty: f32, mods: Mods) -> HitWindows {
+    let od = overall_difficulty as f64;
+    let scale = if mods.contains(Mods::HardRock) {
+      1.0 / 1.4
+    } else if mods.contains(Mods::Easy) {
+      1.4
+    } else {
+      1.0
+    };
+    let window = |base: f64| (base * scale).floor() * mods.speed_multiplier();
+    HitWindows {
+      hit320: window(16.0),
+      hit300: window(64.0 - 3.0 * od),
+      hit200: window(97.0 - 3.0 * od),
+      hit100: window(127.0 - 3.0 * od),
+      hit50: window(151.0 - 3.0 * od),
+      miss: window(188.0 - 3.0 * od),
+    }
+  }
+
+  /// Hit windows for osu!taiko with the given overall difficulty.
+  ///
+  /// osu!taiko only has greats (300), goods (100) and misses.
+  pub fn taiko(overall_difficulty: f32) -> HitWindows {
+    let od = overall_difficulty as f64;
+    HitWindows {
+      hit320: 0.0,
+      hit300: difficulty_range(od, 50.0, 35.0, 20.0).floor(),
+      hit200: 0.0,
+      hit100: difficulty_range(od, 120.0, 80.0, 50.0).floor(),
+      hit50: 0.0,
+      miss: difficulty_range(od, 135.0, 95.0, 70.0).floor(),
+    }
+  }
+
+  /// Returns the result of a key press that was `offset` milliseconds away from the object, or
+  /// `None` if it was too far away to affect the object at all.
+  pub fn result_for(&self, offset: f64) -> Option<HitResult> {
+    let offset = offset.abs();
+    let windows = [
+      (self.hit320, HitResult::Hit320),
+      (self.hit300, HitResult::Hit300),
+      (self.hit200, HitResult::Hit200),
+      (self.hit100, HitResult::Hit100),
+      (self.hit50, HitResult::Hit50),
+      (self.miss, HitResult::Miss),
+    ];
+    windows
+      .iter()
+      .find(|(window, _)| offset < *window)
+      .map(|(_, result)| *result)
+  }
+
+  /// The last moment (relative to the object) at which a key press still gives a judgement
+  /// better than a miss.
+  pub fn late_limit(&self) -> f64 {
+    self
+      .hit320
+      .max(self.hit300)
+      .max(self.hit200)
+      .max(self.hit100)
+      .max(self.hit50)
+  }
+}
+
+/// The outcome of simulating a replay.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SimulationResult {
+  /// Every judgement that was given, in chronological order.
+  pub judgements: Vec<Judgement>,
+
+  /// The judgement counts, in the same format as they are stored in replays.
+  pub counts: HitCounts,
+
+  /// The highest combo reached.
+  pub max_combo: u32,
+}
+
+impl SimulationResult {
+  /// Builds the result from a list of judgements, computing the combo along the way.
+  ///
+  /// If an object has judgements for its parts that affect the combo (like the ticks of a
+  /// slider), only those affect the combo, and the judgement of the object as a whole doesn't.
+  pub(crate) fn new(mut judgements: Vec<Judgement>, counts: HitCounts) -> Self {
+    judgements.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let objects_with_parts = judgements
+      .iter()
+      .filter(|judgement| {
+        !judgement.kind.is_whole_object() && judgement.kind.affects_combo()
+      })
+      .map(|judgement| judgement.object_index)
+      .collect::<HashSet<_>>();
+
+    let mut combo = 0;
+    let mut max_combo = 0;
+    for judgement in judgements.iter() {
+      if judgement.kind == JudgementKind::Object
+        && objects_with_parts.contains(&judgement.object_index)
+      {
+        continue;
+      }
+
+      if judgement.breaks_combo() {
+        combo = 0;
+      } else if judgement.increases_combo() {
+        combo += 1;
+        max_combo = max_combo.max(combo);
+      }
+    }
+
+    SimulationResult {
+      judgements,
+      counts,
+      max_combo,
+    }
+  }
+
+  /// Checks whether the simulated counts and max combo are the same as the ones stored in the
+  /// replay.
+  pub fn matches_replay(&self, replay: &Replay) -> bool {
+    self.counts == replay.hit_counts()
+      && self.max_combo == replay.max_combo as u32
+  }
+
+  /// Iterates over the judgements of whole hit objects, ignoring judgements of their parts.
+  pub fn object_judgements(&self) -> impl Iterator<Item = &Judgement> {
+    self
+      .judgements
+      .iter()
+      .filter(|judgement| judgement.kind.is_whole_object())
+  }
+
+  /// Iterates over the offsets of the key presses that hit an object. See
+  /// [`Judgement::is_hit_error`].
+  pub fn hit_errors(&self) -> impl Iterator<Item = f64> + '_ {
+    self
+      .judgements
+      .iter()
+      .filter(|judgement| judgement.is_hit_error())
+      .filter_map(|judgement| judgement.offset)
+  }
+}
+
+/// Whether the given buttons contain the first (left) key, either from the mouse or keyboard.
+pub(crate) fn left_pressed(buttons: &Buttons) -> bool {
+  buttons.intersects(Buttons::M1 | Buttons::K1)
+}
+
+/// Whether the given buttons contain the second (right) key, either from the mouse or keyboard.
+pub(crate) fn right_pressed(buttons: &Buttons) -> bool {
+  buttons.intersects(Buttons::M2 | Buttons::K2)
+}
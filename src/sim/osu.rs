@@ -0,0 +1,742 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::beatmap::{
+  difficulty_range, Beatmap, SliderEvent, SliderEventKind, SliderTiming,
+};
+use crate::data::{HitCounts, Mods};
+use crate::hitobject::{HitObject, HitObjectKind};
+use crate::math::Point;
+use crate::replay::{ReplayActionData, Timeline, TimelineFrame};
+use crate::spline::Spline;
+
+use super::{
+  left_pressed, right_pressed, HitResult, HitWindows, Judgement, JudgementKind,
+  SimulationResult,
+};
+
+/// The follow circle grows to this many times the size of a hit circle while a slider is being
+/// tracked.
+const FOLLOW_CIRCLE_SCALE: f64 = 2.4;
+
+/// The end of a slider is checked this long before the slider actually ends.
+const SLIDER_TAIL_LENIENCY: f64 = 36.0;
+
+/// The fastest that a spinner can be spun, in rotations per minute.
+const SPINNER_MAX_RPM: f64 = 477.0;
+
+/// The number of extra rotations after clearing a spinner before bonus spins start counting.
+pub(crate) const BONUS_SPIN_GAP: u32 = 2;
+
+/// Spinners are always spun around the center of the playfield.
+pub(crate) const SPINNER_CENTER: Point<f64> = Point::new(256.0, 192.0);
+
+/// Reproduces osu!standard's judgement of a replay.
+///
+/// The simulator follows the rules of osu!stable:
+///
+/// - Each newly pressed key (mouse buttons and keyboard keys on the same side count as the same
+///   key) hits at most one circle or slider head under the cursor. Pressing up to 400ms before an
+///   object but outside of its 50 window counts as a miss.
+/// - Note lock: objects can't be hit while an earlier object is still waiting to be hit. Key
+///   presses on them are ignored.
+/// - A slider is tracked while a key is held and the cursor is within the follow circle. Ticks,
+///   repeats and the tail are hit if the slider is tracked at the first frame at or after their
+///   time. The tail is checked 36ms before the slider ends. The slider is judged by the
+///   proportion of its parts (including the head) that were hit.
+/// - Spinners count the rotation of the cursor around the center of the playfield while a key is
+///   held, capped at 477 RPM. Every whole rotation is judged as a
+///   [`SpinnerSpin`][JudgementKind::SpinnerSpin], and the ones more than two past the rotations
+///   needed to clear the spinner also as a [`SpinnerBonus`][JudgementKind::SpinnerBonus].
+///
+/// Relax and Autopilot plays are not supported.
+#[derive(Clone, Debug)]
+pub struct OsuSimulator {
+  pub(crate) objects: Vec<Object>,
+  windows: HitWindows,
+  radius: f64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Object {
+  pub(crate) start_time: f64,
+  pub(crate) pos: Point<f64>,
+  new_combo: bool,
+  pub(crate) kind: ObjectKind,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum ObjectKind {
+  Circle,
+  Slider(Box<Slider>),
+  Spinner {
+    end_time: f64,
+    required_rotations: f64,
+  },
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Slider {
+  pub(crate) timing: SliderTiming,
+  spline: Spline,
+  stack_offset: Point<f64>,
+  events: Vec<SliderEvent>,
+}
+
+impl Slider {
+  pub(crate) fn ball_position(&self, time: f64) -> Point<f64> {
+    let progress = self.timing.progress_at(time);
+    self
+      .spline
+      .point_at_length(progress * self.timing.pixel_length)
+      + self.stack_offset
+  }
+
+  /// The time at which the given event is checked.
+  fn check_time(&self, event: &SliderEvent) -> f64 {
+    match event.kind {
+      SliderEventKind::Tail => (self.timing.end_time() - SLIDER_TAIL_LENIENCY)
+        .max(self.timing.start_time + self.timing.duration() / 2.0),
+      _ => event.time,
+    }
+  }
+}
+
+/// The mutable state of a single object during the simulation.
+#[derive(Clone, Debug, Default)]
+struct ObjectState {
+  head_judged: bool,
+  finished: bool,
+
+  // sliders
+  tracking: bool,
+  next_event: usize,
+  parts_hit: usize,
+
+  // spinners
+  rotation: f64,
+  last_angle: Option<f64>,
+  last_time: f64,
+}
+
+struct Simulation<'a> {
+  sim: &'a OsuSimulator,
+  states: Vec<ObjectState>,
+  judgements: Vec<Judgement>,
+  results: Vec<Option<HitResult>>,
+
+  /// Every object before this one has had its head judged (or doesn't have one)
+  first_pending: usize,
+
+  /// Objects before this one have started
+  next_active: usize,
+
+  /// Sliders and spinners that have started but aren't finished yet
+  active: Vec<usize>,
+}
+
+impl OsuSimulator {
+  /// Prepares a simulator for the given beatmap played with the given mods.
+  pub fn new(beatmap: &Beatmap, mods: Mods) -> OsuSimulator {
+    let mut beatmap = beatmap.clone();
+    beatmap.difficulty = beatmap.difficulty.apply_mods(mods);
+    if mods.contains(Mods::HardRock) {
+      beatmap.hit_objects.iter_mut().for_each(flip_vertically);
+    }
+
+    let windows = HitWindows::osu(beatmap.difficulty.overall_difficulty);
+    let radius = beatmap.difficulty.circle_size_osupx() as f64;
+
+    let objects = beatmap
+      .stacked_positions()
+      .into_iter()
+      .zip(beatmap.hit_objects.iter())
+      .map(|(pos, ho)| {
+        let kind = match &ho.kind {
+          // hold notes only show up in osu!mania maps
+          HitObjectKind::Circle | HitObjectKind::HoldNote(_) => {
+            ObjectKind::Circle
+          }
+          HitObjectKind::Slider(info) => match beatmap.slider_timing(ho) {
+            Some(timing) => {
+              let mut control_points = vec![ho.pos];
+              control_points.extend(&info.control_points);
+              let spline = Spline::from_control(
+                info.kind,
+                &control_points,
+                Some(info.pixel_length),
+              );
+              let stack_offset =
+                pos - ho.pos.to_float().expect("f64 converts to float");
+              let events = timing.events();
+              ObjectKind::Slider(Box::new(Slider {
+                timing,
+                spline,
+                stack_offset,
+                events,
+              }))
+            }
+
+            // without timing information, there's no way to know how the slider moves
+            None => ObjectKind::Circle,
+          },
+          HitObjectKind::Spinner(info) => {
+            let duration = (info.end_time.0 - ho.start_time.0) as f64;
+            ObjectKind::Spinner {
+              end_time: info.end_time.0 as f64,
+              required_rotations: required_spinner_rotations(
+                duration,
+                beatmap.difficulty.overall_difficulty,
+              ),
+            }
+          }
+        };
+
+        Object {
+          start_time: ho.start_time.0 as f64,
+          pos,
+          new_combo: ho.new_combo,
+          kind,
+        }
+      })
+      .collect();
+
+    OsuSimulator {
+      objects,
+      windows,
+      radius,
+    }
+  }
+
+  /// The hit windows used by this simulator, after mods have been applied.
+  pub fn hit_windows(&self) -> HitWindows {
+    self.windows
+  }
+
+  /// The radius of hit circles in osu!pixels, after mods have been applied.
+  pub fn circle_radius(&self) -> f64 {
+    self.radius
+  }
+
+  /// The position of the hit object with the given index, after stacking and mods have been
+  /// applied.
+  pub fn object_position(&self, object_index: usize) -> Option<Point<f64>> {
+    self.objects.get(object_index).map(|object| object.pos)
+  }
+
+  /// The position at which the hit object with the given index ends, which is the end of the
+  /// body for sliders and the same as [`object_position`][Self::object_position] otherwise.
+  pub fn object_end_position(&self, object_index: usize) -> Option<Point<f64>> {
+    let object = self.objects.get(object_index)?;
+    Some(match &object.kind {
+      ObjectKind::Slider(slider) => {
+        slider.ball_position(slider.timing.end_time())
+      }
+      _ => object.pos,
+    })
+  }
+
+  /// Simulates the given replay actions.
+  pub fn simulate(&self, actions: &ReplayActionData) -> SimulationResult {
+    let mut sim = self.start();
+
+    let mut left_held = false;
+    let mut right_held = false;
+    for frame in Timeline::new(actions).frames() {
+      let time = frame.time as f64;
+      sim.expire_heads(time);
+
+      let left = left_pressed(&frame.buttons);
+      let right = right_pressed(&frame.buttons);
+      if left && !left_held {
+        sim.press(time, frame.pos);
+      }
+      if right && !right_held {
+        sim.press(time, frame.pos);
+      }
+      left_held = left;
+      right_held = right;
+
+      sim.update(frame, left || right);
+    }
+    sim.finish();
+
+    let counts = sim.counts();
+    SimulationResult::new(sim.judgements, counts)
+  }
+
+  /// The result of a play where every object is hit perfectly, every part of every slider is
+  /// hit and spinners are cleared without any extra rotations. This is what the simulation of an
+  /// autoplay replay comes out as, apart from the spinner rotations.
+  pub fn perfect_result(&self) -> SimulationResult {
+    let mut sim = self.start();
+    for (index, object) in self.objects.iter().enumerate() {
+      match &object.kind {
+        ObjectKind::Circle => sim.judge(
+          index,
+          JudgementKind::Object,
+          HitResult::Hit300,
+          object.start_time,
+          Some(0.0),
+        ),
+        ObjectKind::Slider(slider) => {
+          sim.judge(
+            index,
+            JudgementKind::SliderHead,
+            HitResult::Hit300,
+            object.start_time,
+            Some(0.0),
+          );
+          sim.judge_slider_events(index, slider, f64::INFINITY, true);
+          sim.judge(
+            index,
+            JudgementKind::Object,
+            HitResult::Hit300,
+            slider.timing.end_time(),
+            None,
+          );
+        }
+        ObjectKind::Spinner { end_time, .. } => sim.judge(
+          index,
+          JudgementKind::Object,
+          HitResult::Hit300,
+          *end_time,
+          None,
+        ),
+      }
+    }
+
+    let counts = sim.counts();
+    SimulationResult::new(sim.judgements, counts)
+  }
+
+  fn start(&self) -> Simulation<'_> {
+    Simulation {
+      sim: self,
+      states: vec![ObjectState::default(); self.objects.len()],
+      judgements: Vec::new(),
+      results: vec![None; self.objects.len()],
+      first_pending: 0,
+      next_active: 0,
+      active: Vec::new(),
+    }
+  }
+}
+
+impl Simulation<'_> {
+  fn judge(
+    &mut self,
+    index: usize,
+    kind: JudgementKind,
+    result: HitResult,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    if kind == JudgementKind::Object {
+      self.results[index] = Some(result);
+    }
+    self.judgements.push(Judgement {
+      object_index: index,
+      kind,
+      result,
+      time,
+      offset,
+    });
+  }
+
+  fn judge_head(
+    &mut self,
+    index: usize,
+    result: HitResult,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    let sim = self.sim;
+    self.states[index].head_judged = true;
+    match sim.objects[index].kind {
+      ObjectKind::Slider(_) => {
+        if !result.is_miss() {
+          self.states[index].parts_hit += 1;
+        }
+        self.judge(index, JudgementKind::SliderHead, result, time, offset);
+        self.try_finish_slider(index, time);
+      }
+      _ => {
+        self.states[index].finished = true;
+        self.judge(index, JudgementKind::Object, result, time, offset);
+      }
+    }
+
+    while self.first_pending < sim.objects.len() {
+      let object = &sim.objects[self.first_pending];
+      if !matches!(object.kind, ObjectKind::Spinner { .. })
+        && !self.states[self.first_pending].head_judged
+      {
+        break;
+      }
+      self.first_pending += 1;
+    }
+  }
+
+  /// Misses every head that can't be hit anymore at the given time.
+  fn expire_heads(&mut self, time: f64) {
+    let sim = self.sim;
+    let late_limit = sim.windows.late_limit();
+    let mut index = self.first_pending;
+    while let Some(object) = sim.objects.get(index) {
+      let expires = object.start_time + late_limit;
+      if expires > time {
+        break;
+      }
+
+      if !matches!(object.kind, ObjectKind::Spinner { .. })
+        && !self.states[index].head_judged
+      {
+        self.judge_head(index, HitResult::Miss, expires, None);
+      }
+      index += 1;
+    }
+  }
+
+  fn press(&mut self, time: f64, pos: Point<f64>) {
+    let sim = self.sim;
+    let mut note_locked = false;
+    for index in self.first_pending..sim.objects.len() {
+      let object = &sim.objects[index];
+      if object.start_time - sim.windows.miss > time {
+        break;
+      }
+      if matches!(object.kind, ObjectKind::Spinner { .. })
+        || self.states[index].head_judged
+      {
+        continue;
+      }
+
+      if object.pos.distance(pos) <= sim.radius {
+        // an earlier object is still waiting to be hit, so this one shakes instead
+        if note_locked {
+          return;
+        }
+
+        let offset = time - object.start_time;
+        if let Some(result) = sim.windows.result_for(offset) {
+          self.judge_head(index, result, time, Some(offset));
+        }
+        return;
+      }
+
+      note_locked = true;
+    }
+  }
+
+  fn update(&mut self, frame: &TimelineFrame, held: bool) {
+    let sim = self.sim;
+    let time = frame.time as f64;
+    while let Some(object) = sim.objects.get(self.next_active) {
+      if object.start_time > time {
+        break;
+      }
+      if !matches!(object.kind, ObjectKind::Circle) {
+        self.active.push(self.next_active);
+      }
+      self.next_active += 1;
+    }
+
+    for index in self.active.clone() {
+      match &sim.objects[index].kind {
+        ObjectKind::Slider(slider) => {
+          self.update_slider(index, slider, frame, held)
+        }
+        ObjectKind::Spinner {
+          end_time,
+          required_rotations,
+        } => self.update_spinner(
+          index,
+          *end_time,
+          *required_rotations,
+          frame,
+          held,
+        ),
+        ObjectKind::Circle => {}
+      }
+    }
+
+    let states = &self.states;
+    self.active.retain(|index| !states[*index].finished);
+  }
+
+  fn update_slider(
+    &mut self,
+    index: usize,
+    slider: &Slider,
+    frame: &TimelineFrame,
+    held: bool,
+  ) {
+    let time = frame.time as f64;
+    let state = &mut self.states[index];
+    let ball = slider.ball_position(time.min(slider.timing.end_time()));
+    let follow_radius = if state.tracking {
+      self.sim.radius * FOLLOW_CIRCLE_SCALE
+    } else {
+      self.sim.radius
+    };
+    state.tracking = held && ball.distance(frame.pos) <= follow_radius;
+
+    let tracking = state.tracking;
+    self.judge_slider_events(index, slider, time, tracking);
+    self.try_finish_slider(index, time);
+  }
+
+  /// Judges every event of the slider that's due by the given time.
+  fn judge_slider_events(
+    &mut self,
+    index: usize,
+    slider: &Slider,
+    time: f64,
+    tracking: bool,
+  ) {
+    while let Some(event) = slider.events.get(self.states[index].next_event) {
+      let check_time = slider.check_time(event);
+      if check_time > time {
+        break;
+      }
+
+      let kind = match event.kind {
+        SliderEventKind::Tick => JudgementKind::SliderTick,
+        SliderEventKind::Repeat => JudgementKind::SliderRepeat,
+        SliderEventKind::Tail => JudgementKind::SliderTail,
+      };
+      let result = if tracking {
+        self.states[index].parts_hit += 1;
+        HitResult::Hit
+      } else {
+        HitResult::Miss
+      };
+      self.states[index].next_event += 1;
+      self.judge(index, kind, result, check_time, None);
+    }
+  }
+
+  fn try_finish_slider(&mut self, index: usize, time: f64) {
+    let sim = self.sim;
+    let slider = match &sim.objects[index].kind {
+      ObjectKind::Slider(slider) => slider,
+      _ => return,
+    };
+    let state = &self.states[index];
+    if state.finished
+      || !state.head_judged
+      || state.next_event < slider.events.len()
+    {
+      return;
+    }
+
+    let total = slider.events.len() + 1;
+    let result = if state.parts_hit == total {
+      HitResult::Hit300
+    } else if state.parts_hit * 2 >= total {
+      HitResult::Hit100
+    } else if state.parts_hit > 0 {
+      HitResult::Hit50
+    } else {
+      HitResult::Miss
+    };
+
+    let end_time = slider.timing.end_time().max(time);
+    self.states[index].finished = true;
+    self.judge(index, JudgementKind::Object, result, end_time, None);
+  }
+
+  fn update_spinner(
+    &mut self,
+    index: usize,
+    end_time: f64,
+    required_rotations: f64,
+    frame: &TimelineFrame,
+    held: bool,
+  ) {
+    let time = (frame.time as f64).min(end_time);
+    let state = &mut self.states[index];
+    let spins_before = (state.rotation / TAU).floor() as u32;
+
+    if held {
+      let angle = spinner_angle(frame.pos);
+      if let Some(last_angle) = state.last_angle {
+        state.rotation +=
+          spinner_rotation(last_angle, angle, time - state.last_time);
+      }
+      state.last_angle = Some(angle);
+    } else {
+      state.last_angle = None;
+    }
+    state.last_time = time;
+
+    let spins = (state.rotation / TAU).floor() as u32;
+    for spin in spins_before + 1..=spins {
+      self.judge(
+        index,
+        JudgementKind::SpinnerSpin,
+        HitResult::Hit,
+        time,
+        None,
+      );
+      if spin as f64 > required_rotations + BONUS_SPIN_GAP as f64 {
+        self.judge(
+          index,
+          JudgementKind::SpinnerBonus,
+          HitResult::Hit,
+          time,
+          None,
+        );
+      }
+    }
+
+    if frame.time as f64 >= end_time {
+      self.finish_spinner(index, end_time, required_rotations);
+    }
+  }
+
+  fn finish_spinner(
+    &mut self,
+    index: usize,
+    end_time: f64,
+    required_rotations: f64,
+  ) {
+    let state = &mut self.states[index];
+    state.finished = true;
+
+    let result = spinner_result(state.rotation / TAU, required_rotations);
+    self.judge(index, JudgementKind::Object, result, end_time, None);
+  }
+
+  /// Judges everything that's left after the replay has ended.
+  fn finish(&mut self) {
+    let sim = self.sim;
+    self.expire_heads(f64::INFINITY);
+    for index in 0..sim.objects.len() {
+      if self.states[index].finished {
+        continue;
+      }
+
+      match &sim.objects[index].kind {
+        ObjectKind::Slider(slider) => {
+          self.judge_slider_events(index, slider, f64::INFINITY, false);
+          self.try_finish_slider(index, slider.timing.end_time());
+        }
+        ObjectKind::Spinner {
+          end_time,
+          required_rotations,
+        } => self.finish_spinner(index, *end_time, *required_rotations),
+        ObjectKind::Circle => {}
+      }
+    }
+  }
+
+  /// Counts the results, including gekis and katus which are given at the end of every combo.
+  fn counts(&self) -> HitCounts {
+    let mut counts = HitCounts::default();
+    let mut combo_has_100 = false;
+    let mut combo_has_50_or_miss = false;
+
+    for (i, object) in self.sim.objects.iter().enumerate() {
+      let result = self.results[i].unwrap_or(HitResult::Miss);
+      match result {
+        HitResult::Hit300 => counts.count_300 += 1,
+        HitResult::Hit100 => {
+          counts.count_100 += 1;
+          combo_has_100 = true;
+        }
+        HitResult::Hit50 => {
+          counts.count_50 += 1;
+          combo_has_50_or_miss = true;
+        }
+        _ => {
+          counts.count_miss += 1;
+          combo_has_50_or_miss = true;
+        }
+      }
+
+      let is_spinner =
+        |object: &Object| matches!(object.kind, ObjectKind::Spinner { .. });
+      let ends_combo = match self.sim.objects.get(i + 1) {
+        Some(next) => next.new_combo || is_spinner(next) || is_spinner(object),
+        None => true,
+      };
+      if ends_combo {
+        if !combo_has_50_or_miss {
+          if combo_has_100 {
+            counts.count_katu += 1;
+          } else {
+            counts.count_geki += 1;
+          }
+        }
+        combo_has_100 = false;
+        combo_has_50_or_miss = false;
+      }
+    }
+
+    counts
+  }
+}
+
+/// Flips a hit object upside down, like Hard Rock does.
+fn flip_vertically(ho: &mut HitObject) {
+  ho.pos.y = 384 - ho.pos.y;
+  if let HitObjectKind::Slider(info) = &mut ho.kind {
+    for point in info.control_points.iter_mut() {
+      point.y = 384 - point.y;
+    }
+  }
+}
+
+/// The number of rotations needed to clear a spinner of the given duration at the given overall
+/// difficulty.
+pub(crate) fn required_spinner_rotations(
+  duration: f64,
+  overall_difficulty: f32,
+) -> f64 {
+  let spins_per_second =
+    difficulty_range(overall_difficulty as f64, 3.0, 5.0, 7.5);
+  (duration / 1000.0 * spins_per_second).floor()
+}
+
+/// The angle of the cursor around the center of a spinner, in radians.
+pub(crate) fn spinner_angle(pos: Point<f64>) -> f64 {
+  let relative = pos - SPINNER_CENTER;
+  relative.y.atan2(relative.x)
+}
+
+/// How far a spinner is turned, in radians, by the cursor moving from one angle to another over
+/// the given number of milliseconds. The cursor is assumed to take the shorter way around, and
+/// the rotation is capped at 477 RPM.
+pub(crate) fn spinner_rotation(from: f64, to: f64, elapsed: f64) -> f64 {
+  let mut delta = to - from;
+  if delta > PI {
+    delta -= TAU;
+  } else if delta < -PI {
+    delta += TAU;
+  }
+
+  let max_delta = SPINNER_MAX_RPM / 60_000.0 * TAU * elapsed;
+  delta.abs().min(max_delta)
+}
+
+/// The judgement of a spinner that was turned the given number of times.
+pub(crate) fn spinner_result(
+  rotations: f64,
+  required_rotations: f64,
+) -> HitResult {
+  let ratio = if required_rotations > 0.0 {
+    rotations / required_rotations
+  } else {
+    1.0
+  };
+  if ratio >= 1.0 {
+    HitResult::Hit300
+  } else if ratio >= 0.9 {
+    HitResult::Hit100
+  } else if ratio >= 0.75 {
+    HitResult::Hit50
+  } else {
+    HitResult::Miss
+  }
+}
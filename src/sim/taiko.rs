@@ -0,0 +1,481 @@
+use crate::beatmap::{difficulty_range, Beatmap};
+use crate::data::{HitCounts, Mods};
+use crate::hitobject::HitObjectKind;
+use crate::hitsounds::Additions;
+use crate::replay::{Buttons, ReplayActionData, Timeline};
+
+use super::{
+  HitResult, HitWindows, Judgement, JudgementKind, SimulationResult,
+};
+
+/// The second key press on a big note has to come this soon after the first one.
+const STRONG_HIT_WINDOW: f64 = 30.0;
+
+/// Swells need this many times more hits than spinners need rotations.
+const SWELL_HIT_MULTIPLIER: f64 = 1.65;
+
+/// Every key, and whether it's a kat (rim) key.
+const KEYS: [(Buttons, bool); 4] = [
+  (Buttons::M1, false),
+  (Buttons::K1, false),
+  (Buttons::M2, true),
+  (Buttons::K2, true),
+];
+
+/// Reproduces osu!taiko's judgement of a replay.
+///
+/// The left buttons of a replay (M1 and K1) are the don (centre) keys, and the right buttons
+/// (M2 and K2) are the kat (rim) keys. Circles with a whistle or clap are kats, the other ones
+/// are dons, and circles with a finish are big notes. Sliders are drumrolls and spinners are
+/// swells. The simulator follows the rules of osu!stable:
+///
+/// - Each key press hits the earliest note that hasn't been judged yet. Hitting it with the
+///   wrong colour, or pressing before it but outside of its good window, counts as a miss.
+/// - Hitting a big note with the second key of the same colour within 30ms of the first one is
+///   a strong hit, which is counted as a geki (for greats) or katu (for goods).
+/// - Key presses that don't hit a note hit the next drumroll tick within half a tick of the
+///   press, or count towards the active swell if they alternate between don and kat.
+///
+/// Drumrolls and swells don't affect the combo or the hit counts. Only maps made for osu!taiko
+/// are supported, converted maps aren't.
+#[derive(Clone, Debug)]
+pub struct TaikoSimulator {
+  pub(crate) objects: Vec<Object>,
+  pub(crate) windows: HitWindows,
+  max_tick_window: f64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Object {
+  pub(crate) start_time: f64,
+  pub(crate) kind: ObjectKind,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum ObjectKind {
+  Note {
+    kat: bool,
+    big: bool,
+  },
+  DrumRoll {
+    end_time: f64,
+    ticks: Vec<f64>,
+    tick_window: f64,
+  },
+  Swell {
+    end_time: f64,
+    required_hits: u32,
+  },
+}
+
+/// The mutable state of a single object during the simulation.
+#[derive(Clone, Debug, Default)]
+struct ObjectState {
+  finished: bool,
+
+  // big notes
+  strong: bool,
+
+  // drumrolls
+  next_tick: usize,
+
+  // swells
+  hits: u32,
+  last_was_kat: Option<bool>,
+}
+
+/// A big note that can still be hit a second time.
+#[derive(Clone, Debug)]
+struct StrongCandidate {
+  index: usize,
+  kat: bool,
+  time: f64,
+  key: Buttons,
+}
+
+struct Simulation<'a> {
+  sim: &'a TaikoSimulator,
+  states: Vec<ObjectState>,
+  judgements: Vec<Judgement>,
+  results: Vec<Option<HitResult>>,
+
+  /// Every note before this one has been judged (drumrolls and swells are skipped)
+  next_note: usize,
+
+  /// Drumrolls and swells before this one are finished
+  first_unfinished: usize,
+
+  strong_candidate: Option<StrongCandidate>,
+}
+
+impl TaikoSimulator {
+  /// Prepares a simulator for the given beatmap played with the given mods.
+  pub fn new(beatmap: &Beatmap, mods: Mods) -> TaikoSimulator {
+    let difficulty = beatmap.difficulty.apply_mods(mods);
+    let windows = HitWindows::taiko(difficulty.overall_difficulty);
+    let swell_hits_per_second =
+      difficulty_range(difficulty.overall_difficulty as f64, 3.0, 5.0, 7.5)
+        * SWELL_HIT_MULTIPLIER;
+    let tick_rate = if difficulty.slider_tick_rate == 3.0 {
+      3.0
+    } else {
+      4.0
+    };
+
+    let objects = beatmap
+      .hit_objects
+      .iter()
+      .map(|ho| {
+        let start_time = ho.start_time.0 as f64;
+        let kind = match &ho.kind {
+          HitObjectKind::Slider(_) => {
+            let end_time = beatmap
+              .slider_timing(ho)
+              .map(|timing| timing.end_time())
+              .unwrap_or(start_time);
+            let beat_length = beatmap
+              .get_bpm_at_time(ho.start_time)
+              .map(|bpm| 60_000.0 / bpm)
+              .unwrap_or_default();
+            let tick_spacing = beat_length / tick_rate;
+
+            let mut ticks = Vec::new();
+            if tick_spacing > 0.0 {
+              let mut time = start_time;
+              while time < end_time + tick_spacing / 2.0 {
+                ticks.push(time);
+                time += tick_spacing;
+              }
+            }
+            ObjectKind::DrumRoll {
+              end_time,
+              ticks,
+              tick_window: tick_spacing / 2.0,
+            }
+          }
+          HitObjectKind::Spinner(info) => {
+            let end_time = info.end_time.0 as f64;
+            let required_hits = ((end_time - start_time) / 1000.0
+              * swell_hits_per_second)
+              .floor()
+              .max(1.0) as u32;
+            ObjectKind::Swell {
+              end_time,
+              required_hits,
+            }
+          }
+          _ => ObjectKind::Note {
+            kat: ho
+              .additions
+              .intersects(Additions::WHISTLE | Additions::CLAP),
+            big: ho.additions.contains(Additions::FINISH),
+          },
+        };
+        Object { start_time, kind }
+      })
+      .collect::<Vec<_>>();
+
+    let max_tick_window = objects
+      .iter()
+      .map(|object| match object.kind {
+        ObjectKind::DrumRoll { tick_window, .. } => tick_window,
+        _ => 0.0,
+      })
+      .fold(0.0, f64::max);
+
+    TaikoSimulator {
+      objects,
+      windows,
+      max_tick_window,
+    }
+  }
+
+  /// The hit windows used by this simulator, after mods have been applied.
+  pub fn hit_windows(&self) -> HitWindows {
+    self.windows
+  }
+
+  /// Simulates the given replay actions.
+  pub fn simulate(&self, actions: &ReplayActionData) -> SimulationResult {
+    let mut sim = Simulation {
+      sim: self,
+      states: vec![ObjectState::default(); self.objects.len()],
+      judgements: Vec::new(),
+      results: vec![None; self.objects.len()],
+      next_note: 0,
+      first_unfinished: 0,
+      strong_candidate: None,
+    };
+    sim.skip_to_next_note();
+
+    let mut held = Buttons::empty();
+    for frame in Timeline::new(actions).frames() {
+      let time = frame.time as f64;
+      sim.expire(time);
+
+      for (key, kat) in KEYS.iter() {
+        if frame.buttons.contains(*key) && !held.contains(*key) {
+          sim.press(time, *kat, *key);
+        }
+      }
+      held = frame.buttons;
+    }
+    sim.expire(f64::INFINITY);
+
+    let counts = sim.counts();
+    SimulationResult::new(sim.judgements, counts)
+  }
+}
+
+impl Simulation<'_> {
+  fn judge(
+    &mut self,
+    index: usize,
+    kind: JudgementKind,
+    result: HitResult,
+    time: f64,
+    offset: Option<f64>,
+  ) {
+    if kind.is_whole_object() {
+      self.results[index] = Some(result);
+      self.states[index].finished = true;
+    }
+    self.judgements.push(Judgement {
+      object_index: index,
+      kind,
+      result,
+      time,
+      offset,
+    });
+  }
+
+  fn skip_to_next_note(&mut self) {
+    let sim = self.sim;
+    while let Some(object) = sim.objects.get(self.next_note) {
+      if matches!(object.kind, ObjectKind::Note { .. })
+        && !self.states[self.next_note].finished
+      {
+        break;
+      }
+      self.next_note += 1;
+    }
+  }
+
+  /// Judges everything that can't be affected by key presses anymore.
+  fn expire(&mut self, time: f64) {
+    let sim = self.sim;
+    let late_limit = sim.windows.late_limit();
+    while let Some(object) = sim.objects.get(self.next_note) {
+      let expires = object.start_time + late_limit;
+      if expires > time {
+        break;
+      }
+      self.judge(
+        self.next_note,
+        JudgementKind::Object,
+        HitResult::Miss,
+        expires,
+        None,
+      );
+      self.skip_to_next_note();
+    }
+
+    for index in self.first_unfinished..sim.objects.len() {
+      let object = &sim.objects[index];
+      if object.start_time > time {
+        break;
+      }
+      if self.states[index].finished {
+        continue;
+      }
+
+      match &object.kind {
+        ObjectKind::DrumRoll {
+          end_time,
+          ticks,
+          tick_window,
+        } => {
+          while let Some(tick) = ticks.get(self.states[index].next_tick) {
+            if tick + tick_window >= time {
+              break;
+            }
+            self.states[index].next_tick += 1;
+            self.judge(
+              index,
+              JudgementKind::DrumRollTick,
+              HitResult::Miss,
+              tick + tick_window,
+              None,
+            );
+          }
+          if self.states[index].next_tick == ticks.len() && *end_time <= time {
+            self.judge(
+              index,
+              JudgementKind::Bonus,
+              HitResult::Hit,
+              *end_time,
+              None,
+            );
+          }
+        }
+        ObjectKind::Swell { end_time, .. } => {
+          if *end_time <= time {
+            self.judge(
+              index,
+              JudgementKind::Bonus,
+              HitResult::Miss,
+              *end_time,
+              None,
+            );
+          }
+        }
+        ObjectKind::Note { .. } => {}
+      }
+    }
+
+    while let Some(state) = self.states.get(self.first_unfinished) {
+      let is_note = matches!(
+        sim.objects[self.first_unfinished].kind,
+        ObjectKind::Note { .. }
+      );
+      if !is_note && !state.finished {
+        break;
+      }
+      self.first_unfinished += 1;
+    }
+  }
+
+  fn press(&mut self, time: f64, kat: bool, key: Buttons) {
+    let sim = self.sim;
+
+    if let Some(candidate) = self.strong_candidate.take() {
+      if candidate.key != key
+        && candidate.kat == kat
+        && time - candidate.time <= STRONG_HIT_WINDOW
+      {
+        self.states[candidate.index].strong = true;
+        self.judge(
+          candidate.index,
+          JudgementKind::StrongHit,
+          HitResult::Hit,
+          time,
+          Some(time - candidate.time),
+        );
+        return;
+      }
+    }
+
+    if let Some(object) = sim.objects.get(self.next_note) {
+      let offset = time - object.start_time;
+      if let (Some(result), ObjectKind::Note { kat: note_kat, big }) =
+        (sim.windows.result_for(offset), &object.kind)
+      {
+        let index = self.next_note;
+        let result = if *note_kat == kat {
+          result
+        } else {
+          HitResult::Miss
+        };
+        self.judge(index, JudgementKind::Object, result, time, Some(offset));
+        self.skip_to_next_note();
+
+        if *big && !result.is_miss() {
+          self.strong_candidate = Some(StrongCandidate {
+            index,
+            kat,
+            time,
+            key,
+          });
+        }
+        return;
+      }
+    }
+
+    for index in self.first_unfinished..sim.objects.len() {
+      let object = &sim.objects[index];
+      if object.start_time > time + sim.max_tick_window {
+        break;
+      }
+      if self.states[index].finished {
+        continue;
+      }
+
+      match &object.kind {
+        ObjectKind::DrumRoll {
+          ticks, tick_window, ..
+        } => {
+          let next_tick = self.states[index].next_tick;
+          if let Some(tick) = ticks.get(next_tick) {
+            if (time - tick).abs() <= *tick_window {
+              self.states[index].next_tick += 1;
+              self.judge(
+                index,
+                JudgementKind::DrumRollTick,
+                HitResult::Hit,
+                time,
+                Some(time - tick),
+              );
+              return;
+            }
+          }
+        }
+        ObjectKind::Swell {
+          end_time,
+          required_hits,
+        } => {
+          if object.start_time > time || *end_time <= time {
+            continue;
+          }
+
+          // don and kat have to alternate
+          let state = &mut self.states[index];
+          if state.last_was_kat == Some(kat) {
+            return;
+          }
+          state.last_was_kat = Some(kat);
+          state.hits += 1;
+          let cleared = state.hits >= *required_hits;
+          self.judge(
+            index,
+            JudgementKind::SwellTick,
+            HitResult::Hit,
+            time,
+            None,
+          );
+          if cleared {
+            self.judge(index, JudgementKind::Bonus, HitResult::Hit, time, None);
+          }
+          return;
+        }
+        ObjectKind::Note { .. } => {}
+      }
+    }
+  }
+
+  fn counts(&self) -> HitCounts {
+    let mut counts = HitCounts::default();
+    for (index, object) in self.sim.objects.iter().enumerate() {
+      if !matches!(object.kind, ObjectKind::Note { .. }) {
+        continue;
+      }
+
+      let strong = self.states[index].strong;
+      match self.results[index].unwrap_or(HitResult::Miss) {
+        HitResult::Hit300 => {
+          counts.count_300 += 1;
+          if strong {
+            counts.count_geki += 1;
+          }
+        }
+        HitResult::Hit100 => {
+          counts.count_100 += 1;
+          if strong {
+            counts.count_katu += 1;
+          }
+        }
+        _ => counts.count_miss += 1,
+      }
+    }
+    counts
+  }
+}
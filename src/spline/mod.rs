@@ -60,10 +60,17 @@ impl Spline {
     let spline_points = match kind {
       SliderSplineKind::Linear => {
         let start = points[0];
-        let end = if let Some(pixel_length) = pixel_length {
-          Math::point_on_line(points[0], points[1], pixel_length)
-        } else {
-          points[1]
+        // old maps sometimes repeat the start as the first control point
+        let next = points[1..]
+          .iter()
+          .find(|p| **p != start)
+          .copied()
+          .unwrap_or(points[1]);
+        let end = match pixel_length {
+          Some(pixel_length) if next != start => {
+            Math::point_on_line(start, next, pixel_length)
+          }
+          _ => next,
         };
         vec![start, end]
       }
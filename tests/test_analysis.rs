@@ -0,0 +1,394 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use libosu::{
+  analysis::{
+    AimAssistStats, AimErrorStats, HitErrorStats, HitOffset, RelaxStats,
+    ReplaySimilarity, SpinnerStats, TimewarpStats,
+  },
+  autoplay::Autoplay,
+  beatmap::Beatmap,
+  data::Mods,
+  replay::{Buttons, ReplayAction, ReplayActionData},
+  sim::{HitResult, JudgementKind, OsuSimulator},
+  timing::Millis,
+};
+
+const OSU_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,100,1000,1,0,0:0:0:0:
+200,100,1500,1,0,0:0:0:0:
+300,100,2000,1,0,0:0:0:0:
+"#;
+
+/// Builds action data that taps at the given absolute times.
+fn taps(taps: &[(i32, f32, f32)]) -> ReplayActionData {
+  let mut frames = Vec::new();
+  let mut last = 0;
+  for (time, x, y) in taps {
+    for (time, buttons) in [(*time, Buttons::K1), (time + 20, Buttons::empty())]
+    {
+      frames.push(ReplayAction {
+        time: Millis(time - last),
+        x: *x,
+        y: *y,
+        buttons,
+      });
+      last = time;
+    }
+  }
+  ReplayActionData {
+    frames,
+    rng_seed: None,
+  }
+}
+
+fn offsets(offsets: &[f64]) -> Vec<HitOffset> {
+  offsets
+    .iter()
+    .enumerate()
+    .map(|(i, offset)| HitOffset {
+      object_index: i,
+      time: 0.0,
+      offset: *offset,
+    })
+    .collect()
+}
+
+#[test]
+fn test_hit_error_stats() {
+  let stats = HitErrorStats::from_offsets(offsets(&[-10.0, 10.0, 20.0]));
+  assert!((stats.mean - 20.0 / 3.0).abs() < 1e-9);
+  assert_eq!(stats.early, Some(-10.0));
+  assert_eq!(stats.late, Some(15.0));
+  assert!((stats.unstable_rate - 124.72191).abs() < 1e-4);
+
+  let histogram = stats.histogram(8.0);
+  assert_eq!(histogram.start, -16.0);
+  assert_eq!(histogram.counts, vec![1, 0, 0, 1, 1]);
+
+  let stats = HitErrorStats::from_offsets(Vec::new());
+  assert_eq!(stats.unstable_rate, 0.0);
+  assert_eq!(stats.early, None);
+  assert!(stats.histogram(8.0).counts.is_empty());
+}
+
+#[test]
+fn test_hit_errors_from_simulation() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let actions = taps(&[
+    (985, 100.0, 100.0),
+    (1515, 200.0, 100.0),
+    (2000, 0.0, 100.0),
+  ]);
+
+  let result = OsuSimulator::new(&beatmap, Mods::None).simulate(&actions);
+  let stats = HitErrorStats::from_simulation(&result, Mods::None);
+  let hits = stats
+    .offsets
+    .iter()
+    .map(|hit| (hit.object_index, hit.offset))
+    .collect::<Vec<_>>();
+  assert_eq!(hits, vec![(0, -15.0), (1, 15.0)]);
+  assert_eq!(stats.unstable_rate, 150.0);
+
+  // offsets are measured in real time
+  let result = OsuSimulator::new(&beatmap, Mods::DoubleTime).simulate(&actions);
+  let stats = HitErrorStats::from_simulation(&result, Mods::DoubleTime);
+  assert_eq!(stats.mean, 0.0);
+  assert_eq!(stats.early, Some(-10.0));
+  assert_eq!(stats.unstable_rate, 100.0);
+  Ok(())
+}
+
+#[test]
+fn test_aim_error_stats() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+  let actions = taps(&[
+    (1000, 110.0, 100.0),
+    (1500, 190.0, 110.0),
+    (2000, 310.0, 95.0),
+  ]);
+  let result = sim.simulate(&actions);
+  let stats = AimErrorStats::from_simulation(&sim, &result, &actions);
+
+  let offsets = stats
+    .offsets
+    .iter()
+    .map(|hit| (hit.offset.x, hit.offset.y, hit.along, hit.perpendicular))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    offsets,
+    vec![
+      (10.0, 0.0, None, None),
+      (-10.0, 10.0, Some(-10.0), Some(10.0)),
+      (10.0, -5.0, Some(10.0), Some(-5.0)),
+    ]
+  );
+
+  let along = stats.along.unwrap();
+  assert_eq!((along.mean, along.std_dev), (0.0, 10.0));
+  let perpendicular = stats.perpendicular.unwrap();
+  assert_eq!((perpendicular.mean, perpendicular.std_dev), (2.5, 7.5));
+
+  let distribution = stats.distribution(4, 1.0);
+  assert_eq!(
+    distribution.counts,
+    vec![
+      vec![0, 0, 0, 0],
+      vec![0, 0, 1, 0],
+      vec![0, 1, 1, 0],
+      vec![0; 4]
+    ]
+  );
+  assert_eq!(distribution.outside, 0);
+  Ok(())
+}
+
+/// Builds action data out of frames with absolute times.
+fn frames(frames: &[(i32, f32, f32, Buttons)]) -> ReplayActionData {
+  let mut last = 0;
+  let frames = frames
+    .iter()
+    .map(|(time, x, y, buttons)| {
+      let action = ReplayAction {
+        time: Millis(time - last),
+        x: *x,
+        y: *y,
+        buttons: *buttons,
+      };
+      last = *time;
+      action
+    })
+    .collect();
+  ReplayActionData {
+    frames,
+    rng_seed: None,
+  }
+}
+
+/// A cursor path that wanders around the playfield, sampled every 16ms.
+fn wander(shift: i32, flip: bool, speed: f64) -> ReplayActionData {
+  let path = (0..500)
+    .map(|i| {
+      let time = i * 16;
+      let t = (time - shift) as f64 * speed;
+      let x = 256.0 + 200.0 * (t / 300.0).sin();
+      let y = 192.0 + 150.0 * (t / 470.0).cos();
+      let y = if flip { 384.0 - y } else { y };
+      (time, x as f32, y as f32, Buttons::empty())
+    })
+    .collect::<Vec<_>>();
+  frames(&path)
+}
+
+#[test]
+fn test_replay_similarity() {
+  let original = wander(0, false, 1.0);
+
+  // the copy is 30ms late and flipped, as if HardRock was added to it
+  let copy = wander(30, true, 1.0);
+  let similarity = ReplaySimilarity::compare(&original, &copy).unwrap();
+  assert_eq!(similarity.offset, -30);
+  assert!(similarity.mirrored);
+  assert!(similarity.correlation > 0.999);
+  assert!(similarity.mean_distance < 1.0);
+
+  let other = wander(0, false, 1.7);
+  let similarity = ReplaySimilarity::compare(&original, &other).unwrap();
+  assert!(similarity.correlation < 0.5);
+  assert!(similarity.mean_distance > 50.0);
+
+  assert!(ReplaySimilarity::compare(&original, &frames(&[])).is_none());
+}
+
+#[test]
+fn test_timewarp_stats() {
+  // frames 12-13ms apart, like the game running at 75% speed
+  let slow = frames(
+    &(0..300)
+      .map(|i| (i * 25 / 2, 0.0, 0.0, Buttons::empty()))
+      .collect::<Vec<_>>(),
+  );
+  let stats = TimewarpStats::from_actions(&slow, Mods::None).unwrap();
+  assert!((stats.rate - 0.75).abs() < 0.01);
+  assert!(stats.is_slowed_down(0.1));
+
+  // with DoubleTime, frames are 25ms apart in map time
+  let double_time = frames(
+    &(0..300)
+      .map(|i| (i * 25, 0.0, 0.0, Buttons::empty()))
+      .collect::<Vec<_>>(),
+  );
+  let stats = TimewarpStats::from_actions(&double_time, Mods::DoubleTime);
+  assert!(!stats.unwrap().is_slowed_down(0.1));
+  let stats = TimewarpStats::from_actions(&double_time, Mods::None);
+  assert!(!stats.unwrap().is_slowed_down(0.1));
+  assert!(TimewarpStats::from_actions(&frames(&[]), Mods::None).is_none());
+}
+
+#[test]
+fn test_relax_stats() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let mut actions = taps(&[
+    (1000, 100.0, 100.0),
+    (1500, 200.0, 100.0),
+    (2000, 300.0, 100.0),
+  ]);
+  actions.frames.push(ReplayAction {
+    time: Millis(500),
+    x: 0.0,
+    y: 0.0,
+    buttons: Buttons::K2,
+  });
+  actions.frames.push(ReplayAction {
+    time: Millis(50),
+    x: 0.0,
+    y: 0.0,
+    buttons: Buttons::empty(),
+  });
+
+  let result = OsuSimulator::new(&beatmap, Mods::None).simulate(&actions);
+  let stats = RelaxStats::from_simulation(&result, &actions, Mods::None);
+  assert_eq!((stats.presses, stats.hits), (4, 3));
+  assert_eq!(stats.unstable_rate, 0.0);
+  let hold_time = stats.hold_time.unwrap();
+  assert_eq!(hold_time.mean, 27.5);
+  assert!(!stats.is_suspicious());
+  Ok(())
+}
+
+#[test]
+fn test_aim_assist_stats() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+  let none = Buttons::empty();
+  let actions = frames(&[
+    // drifting away from the first circle, then jumping onto it
+    (950, 300.0, 300.0, none),
+    (966, 310.0, 290.0, none),
+    (983, 320.0, 280.0, none),
+    (1000, 100.0, 100.0, Buttons::K1),
+    (1020, 100.0, 100.0, none),
+    // moving onto the second circle smoothly
+    (1450, 150.0, 100.0, none),
+    (1466, 180.0, 100.0, none),
+    (1483, 195.0, 100.0, none),
+    (1500, 200.0, 100.0, Buttons::K1),
+    (1520, 200.0, 100.0, none),
+  ]);
+  let result = sim.simulate(&actions);
+  let stats = AimAssistStats::from_simulation(&sim, &result, &actions);
+  assert_eq!(stats.hits, 2);
+  assert_eq!(stats.snaps, vec![0]);
+  assert_eq!(stats.snap_ratio(), 0.5);
+  assert!(!stats.is_suspicious());
+  Ok(())
+}
+
+const SPINNER_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:5
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+256,192,1000,12,0,4000,0:0:0:0:
+"#;
+
+#[test]
+fn test_spinner_stats() -> Result<()> {
+  let beatmap = Beatmap::from_str(SPINNER_MAP)?;
+  let spinner = &beatmap.hit_objects[0];
+  let spin = |spinner_rpm| {
+    Autoplay {
+      spinner_rpm,
+      ..Autoplay::default()
+    }
+    .generate(&beatmap, Mods::None)
+  };
+
+  // 3 seconds at 400 RPM is 20 rotations, and OD 5 needs 15
+  let actions = spin(400.0);
+  let stats =
+    SpinnerStats::new(spinner, &beatmap.difficulty, Mods::None, &actions)
+      .unwrap();
+  assert_eq!(stats.required_rotations, 15);
+  assert!((stats.rotations - 20.0).abs() < 0.1);
+  assert!((stats.peak_rpm - 400.0).abs() < 5.0);
+  assert!((stats.rpm_at(2500.0) - 400.0).abs() < 5.0);
+  assert_eq!(stats.rpm_at(0.0), 0.0);
+  assert!(stats.cleared);
+  // bonus spins start after two more rotations than needed
+  assert_eq!(stats.bonus_spins, stats.rotations.floor() as u32 - 17);
+  assert_eq!(stats.result, HitResult::Hit300);
+
+  let result = OsuSimulator::new(&beatmap, Mods::None).simulate(&actions);
+  assert_eq!(
+    result.object_judgements().next().unwrap().result,
+    stats.result
+  );
+  let count = |kind| {
+    result
+      .judgements
+      .iter()
+      .filter(|judgement| judgement.kind == kind)
+      .count() as u32
+  };
+  assert_eq!(
+    count(JudgementKind::SpinnerSpin),
+    stats.rotations.floor() as u32
+  );
+  assert_eq!(count(JudgementKind::SpinnerBonus), stats.bonus_spins);
+
+  // Hard Rock raises the OD to 7, which needs 6 rotations per second
+  let stats =
+    SpinnerStats::new(spinner, &beatmap.difficulty, Mods::HardRock, &actions)
+      .unwrap();
+  assert_eq!(stats.required_rotations, 18);
+  assert_eq!(stats.bonus_spins, 0);
+
+  let actions = spin(200.0);
+  let stats =
+    SpinnerStats::new(spinner, &beatmap.difficulty, Mods::None, &actions)
+      .unwrap();
+  assert!(!stats.cleared);
+  assert_eq!(stats.bonus_spins, 0);
+  assert_eq!(stats.result, HitResult::Miss);
+
+  let circle = Beatmap::from_str(OSU_MAP)?;
+  assert!(SpinnerStats::new(
+    &circle.hit_objects[0],
+    &circle.difficulty,
+    Mods::None,
+    &actions
+  )
+  .is_none());
+  Ok(())
+}
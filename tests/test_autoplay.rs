@@ -0,0 +1,228 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::str::FromStr;
+
+use anyhow::Result;
+use libosu::{
+  autoplay::{Autoplay, Easing},
+  beatmap::Beatmap,
+  data::Mods,
+  replay::Timeline,
+  sim::{
+    CatchSimulator, HitResult, JudgementKind, ManiaSimulator, OsuSimulator,
+    TaikoSimulator,
+  },
+};
+
+const MANIA_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 3
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:5
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1000,1,0,0:0:0:0:
+64,192,1030,1,0,0:0:0:0:
+448,192,1400,128,0,2000:0:0:0:0:
+448,192,2001,1,0,0:0:0:0:
+"#;
+
+const TAIKO_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 1
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:5
+OverallDifficulty:5
+ApproachRate:5
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,192,1125,1,0,0:0:0:0:
+256,192,1250,1,0,0:0:0:0:
+256,192,1375,1,8,0:0:0:0:
+256,192,1500,1,4,0:0:0:0:
+256,192,2000,2,0,L|456:192,1,140
+256,192,2750,1,12,0:0:0:0:
+256,192,3000,12,0,4000,0:0:0:0:
+256,192,4500,1,0,0:0:0:0:
+"#;
+
+const CATCH_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 2
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:5
+ApproachRate:8
+SliderMultiplier:1.4
+SliderTickRate:2
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,192,1000,1,0,0:0:0:0:
+500,192,1200,1,0,0:0:0:0:
+200,192,2000,6,0,P|300:100|400:192,2,280
+20,192,4500,1,0,0:0:0:0:
+256,192,5000,12,0,6000,0:0:0:0:
+"#;
+
+fn load_beatmap(path: &str) -> Result<Beatmap> {
+  let mut contents = String::new();
+  File::open(path)?.read_to_string(&mut contents)?;
+  Ok(Beatmap::from_str(&contents)?)
+}
+
+#[test]
+fn test_osu_autoplay() -> Result<()> {
+  let mut paths = fs::read_dir("tests/files")?
+    .map(|entry| Ok(entry?.path()))
+    .collect::<Result<Vec<_>>>()?;
+  paths.retain(|path| path.extension().is_some_and(|ext| ext == "osu"));
+  paths.sort();
+  assert!(!paths.is_empty());
+
+  for path in paths {
+    let beatmap = load_beatmap(path.to_str().unwrap())?;
+    for mods in [Mods::None, Mods::HardRock | Mods::DoubleTime] {
+      let actions = Autoplay::default().generate(&beatmap, mods);
+      let result = OsuSimulator::new(&beatmap, mods).simulate(&actions);
+
+      let counts = result.counts;
+      assert_eq!(
+        (counts.count_100, counts.count_50, counts.count_miss),
+        (0, 0, 0),
+        "{:?} with {:?}",
+        path,
+        mods
+      );
+      assert_eq!(counts.count_300 as usize, beatmap.hit_objects.len());
+      assert!(result.judgements.iter().all(|j| !j.result.is_miss()));
+    }
+  }
+  Ok(())
+}
+
+#[test]
+fn test_osu_autoplay_settings() -> Result<()> {
+  let beatmap = load_beatmap("tests/files/774965.osu")?;
+  for easing in [Easing::Linear, Easing::InOutCubic] {
+    let autoplay = Autoplay {
+      easing,
+      spinner_rpm: 450.0,
+      frame_interval: 5.0,
+      key_up_delay: 10.0,
+    };
+    let actions = autoplay.generate(&beatmap, Mods::None);
+    let result = OsuSimulator::new(&beatmap, Mods::None).simulate(&actions);
+    assert_eq!(result.counts.count_miss, 0);
+  }
+  Ok(())
+}
+
+#[test]
+fn test_mania_autoplay() -> Result<()> {
+  let beatmap = Beatmap::from_str(MANIA_MAP)?;
+  let actions = Autoplay::default().generate(&beatmap, Mods::None);
+  let result = ManiaSimulator::new(&beatmap, Mods::None).simulate(&actions);
+
+  assert_eq!(result.counts.count_geki, 5);
+  assert!(result
+    .judgements
+    .iter()
+    .filter(|j| j.kind != JudgementKind::HoldNoteTail)
+    .all(|j| j.result == HitResult::Hit320));
+
+  // the key has to be let go in between notes, but the hold note is held until it ends
+  let timeline = Timeline::new(&actions);
+  let column_3 = |time| {
+    let frames = timeline.frames();
+    let index = frames.partition_point(|frame| frame.time <= time);
+    frames[index - 1].pos.x as u32 & 8
+  };
+  assert_eq!(column_3(1999), 8);
+  assert_eq!(column_3(2000), 0);
+  assert_eq!(column_3(2001), 8);
+  Ok(())
+}
+
+#[test]
+fn test_taiko_autoplay() -> Result<()> {
+  let beatmap = Beatmap::from_str(TAIKO_MAP)?;
+  let actions = Autoplay::default().generate(&beatmap, Mods::None);
+  let result = TaikoSimulator::new(&beatmap, Mods::None).simulate(&actions);
+
+  assert_eq!(result.counts.count_300, 7);
+  assert_eq!(result.counts.count_geki, 2);
+  assert_eq!(result.counts.count_miss, 0);
+  assert!(result.judgements.iter().all(|j| !j.result.is_miss()));
+
+  let swell = result
+    .judgements
+    .iter()
+    .find(|j| j.object_index == 7 && j.kind == JudgementKind::Bonus)
+    .unwrap();
+  assert_eq!(swell.result, HitResult::Hit);
+  Ok(())
+}
+
+#[test]
+fn test_catch_autoplay() -> Result<()> {
+  let beatmap = Beatmap::from_str(CATCH_MAP)?;
+  for mods in [Mods::None, Mods::HardRock] {
+    let actions = Autoplay::default().generate(&beatmap, mods);
+    let sim = CatchSimulator::new(&beatmap, mods);
+    let result = sim.simulate(&actions);
+
+    assert_eq!(result.counts.count_miss, 0);
+    assert_eq!(result.counts.count_katu, 0);
+    assert!(result.judgements.iter().all(|j| !j.result.is_miss()));
+  }
+  Ok(())
+}
+
+#[test]
+fn test_easing() {
+  let easings = [
+    Easing::Linear,
+    Easing::InQuad,
+    Easing::OutQuad,
+    Easing::InOutQuad,
+    Easing::InCubic,
+    Easing::OutCubic,
+    Easing::InOutCubic,
+    Easing::InOutSine,
+  ];
+  for easing in easings {
+    assert_eq!(easing.apply(0.0), 0.0);
+    assert!((easing.apply(1.0) - 1.0).abs() < 1e-9);
+    assert!((0..100).all(|i| {
+      easing.apply(i as f64 / 100.0) <= easing.apply((i + 1) as f64 / 100.0)
+    }));
+  }
+  assert_eq!(Easing::InOutQuad.apply(0.5), 0.5);
+  assert!(Easing::OutQuad.apply(0.5) > 0.5);
+}
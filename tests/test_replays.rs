@@ -1,15 +1,28 @@
 use std::fs::File;
-use std::io::{self, Cursor, Read, Write};
+#[cfg(feature = "replay-data")]
+use std::io;
+#[cfg(feature = "replay-data-xz2")]
+use std::io::Write;
+use std::io::{Cursor, Read};
 use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
 
 use anyhow::Result;
+#[cfg(feature = "replay-data")]
+use libosu::replay::{Buttons, Timeline};
 use libosu::{
   data::{Mode, Mods},
-  replay::{Buttons, Replay, ReplayActionData},
+  replay::{
+    system_time_to_ticks, ticks_to_system_time, Replay, DEFAULT_REPLAY_VERSION,
+  },
+};
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+use libosu::{
+  replay::{ReplayActionData, ReplayError, ReplayFrames},
   timing::Millis,
 };
 
-#[cfg(feature = "replay-data")]
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
 fn compare_action_data(replay: &Replay, replay2: &Replay) -> Result<()> {
   let action_data = replay.parse_action_data()?;
   let action_data2 = replay2.parse_action_data()?;
@@ -61,6 +74,20 @@ fn test_replay_writer_with(path: impl AsRef<Path>) -> Result<()> {
   Ok(())
 }
 
+#[test]
+fn test_replay_writer_perfect() -> Result<()> {
+  let mut osr = File::open("tests/files/replay-osu_1816113_2892542031.osr")?;
+  let mut replay = Replay::parse(&mut osr)?;
+  for perfect in [false, true] {
+    replay.perfect = perfect;
+    let mut buf = Vec::new();
+    replay.write(&mut buf)?;
+    let replay2 = Replay::parse(&mut buf.as_slice())?;
+    assert_eq!(replay2.perfect, perfect);
+  }
+  Ok(())
+}
+
 #[cfg(feature = "replay-data")]
 #[test]
 fn test_replay_action_update() -> Result<()> {
@@ -144,6 +171,48 @@ fn test_parse_after_actions() -> Result<()> {
   Ok(())
 }
 
+#[test]
+fn test_parse_skip_actions() -> Result<()> {
+  for path in &[
+    "tests/files/ - nekodex - new beginnings [tutorial] (2020-12-16) Osu.osr",
+    "tests/files/replay-osu_2058788_3017707256.osr",
+    "tests/files/replay_with_life.osr",
+  ] {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    let replay = Replay::parse(&mut data.as_slice())?;
+
+    let mut cursor = Cursor::new(&data);
+    let skipped = Replay::parse_skip_actions(&mut cursor)?;
+    assert_eq!(cursor.position() as usize, data.len());
+
+    // slices can be read from, but not seeked
+    let mut reader = data.as_slice();
+    let unseekable = Replay::parse_skip_actions_unseekable(&mut reader)?;
+    assert!(reader.is_empty());
+
+    for other in &[skipped, unseekable] {
+      assert!(other.action_data.is_empty());
+      assert_eq!(other.replay_hash, replay.replay_hash);
+      assert_eq!(other.life_graph, replay.life_graph);
+      assert_eq!(other.timestamp, replay.timestamp);
+      assert_eq!(other.score_id, replay.score_id);
+      assert_eq!(
+        other.target_practice_total_accuracy,
+        replay.target_practice_total_accuracy
+      );
+    }
+  }
+
+  // the action data is cut off
+  let mut data = Vec::new();
+  File::open("tests/files/replay_with_life.osr")?.read_to_end(&mut data)?;
+  data.truncate(data.len() / 2);
+  assert!(Replay::parse_skip_actions_unseekable(&mut data.as_slice()).is_err());
+
+  Ok(())
+}
+
 #[cfg(feature = "replay-data-xz2")]
 fn lzma_encode(data: &[u8]) -> Result<Vec<u8>> {
   use xz2::{
@@ -192,6 +261,164 @@ fn test_replay_action_parser() -> Result<()> {
   Ok(())
 }
 
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[test]
+fn test_action_frames() -> Result<()> {
+  let mut osr = File::open("tests/files/replay-osu_1816113_2892542031.osr")?;
+  let replay = Replay::parse(&mut osr)?;
+  let action_data = replay.parse_action_data()?;
+
+  let mut frames = replay.action_frames()?;
+  let mut count = 0;
+  for (a, b) in frames.by_ref().zip(action_data.frames.iter()) {
+    let a = a?;
+    assert_eq!(a.time, b.time);
+    assert_eq!(a.x, b.x);
+    assert_eq!(a.y, b.y);
+    assert_eq!(a.buttons, b.buttons);
+    count += 1;
+  }
+  assert!(frames.next().is_none());
+  assert_eq!(count, action_data.frames.len());
+  assert_eq!(frames.rng_seed(), action_data.rng_seed);
+  Ok(())
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[test]
+fn test_action_frames_malformed() -> Result<()> {
+  let actions_text = "1|32|300|0,5|x|3|0,,7|1,-12345|0|0|734243";
+  let data = lzma_encode(actions_text.as_bytes())?;
+  let mut frames = ReplayFrames::new(data.as_slice())?;
+
+  assert_eq!(frames.next().unwrap()?.time, Millis(1));
+  match frames.next() {
+    Some(Err(ReplayError::InvalidFrame { index: 1, source })) => {
+      assert!(matches!(*source, ReplayError::ParseFloat(_)))
+    }
+    other => panic!("unexpected frame: {:?}", other),
+  }
+  match frames.next() {
+    Some(Err(ReplayError::InvalidFrame { index: 2, source })) => {
+      assert!(matches!(*source, ReplayError::FrameMissing))
+    }
+    other => panic!("unexpected frame: {:?}", other),
+  }
+  assert!(frames.next().is_none());
+  assert_eq!(frames.rng_seed(), Some(734243));
+
+  assert!(ReplayActionData::parse(data.as_slice()).is_err());
+  Ok(())
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[test]
+fn test_action_frames_truncated() -> Result<()> {
+  let mut osr = File::open("tests/files/replay-osu_1816113_2892542031.osr")?;
+  let replay = Replay::parse(&mut osr)?;
+  let data = &replay.action_data[..replay.action_data.len() / 2];
+
+  // the frames before the cut might have been decompressed already, depending on the backend
+  let results = ReplayFrames::new(data)?.collect::<Vec<_>>();
+  assert!(!results.is_empty());
+  assert!(results[..results.len() - 1]
+    .iter()
+    .all(|frame| frame.is_ok()));
+  assert!(matches!(
+    results.last(),
+    Some(Err(ReplayError::InvalidFrame { .. }))
+  ));
+  Ok(())
+}
+
+#[test]
+fn test_score_info_round_trip() -> Result<()> {
+  let mut osr = File::open("tests/files/replay-osu_2058788_3017707256.osr")?;
+  let mut replay = Replay::parse(&mut osr)?;
+  assert_eq!(replay.score_info, None);
+
+  replay.version = 30000016;
+  for score_info in [None, Some(vec![1, 2, 3])] {
+    replay.score_info = score_info;
+    let mut buf = Vec::new();
+    replay.write(&mut buf)?;
+    let replay2 = Replay::parse(&mut buf.as_slice())?;
+    assert_eq!(replay2.score_info, replay.score_info);
+    assert_eq!(replay2.score_id, replay.score_id);
+  }
+
+  Ok(())
+}
+
+#[cfg(all(
+  feature = "serde",
+  any(feature = "replay-data", feature = "replay-data-xz2")
+))]
+#[test]
+fn test_lazer_score_info() -> Result<()> {
+  use libosu::replay::{LazerMod, LazerScoreInfo};
+
+  let json = r#"{
+    "online_id": 3456789012,
+    "mods": [
+      { "acronym": "DT", "settings": { "speed_change": 1.3 } },
+      { "acronym": "HD" }
+    ],
+    "statistics": { "great": 330, "ok": 24, "miss": 2, "slider_tail_hit": 50 },
+    "maximum_statistics": { "great": 356, "slider_tail_hit": 52 },
+    "client_version": "2024.906.2",
+    "rank": "A",
+    "user_id": 1234,
+    "total_score_without_mods": 812345,
+    "pauses": [1500]
+  }"#;
+  let score_info = serde_json::from_str::<LazerScoreInfo>(json)?;
+  assert_eq!(score_info.mods.len(), 2);
+  assert_eq!(score_info.mods[1], LazerMod::new("HD"));
+  assert_eq!(score_info.mods[0].settings["speed_change"], 1.3);
+  assert_eq!(score_info.statistics["ok"], 24);
+  assert_eq!(score_info.extra["pauses"], serde_json::json!([1500]));
+
+  let mut osr = File::open("tests/files/replay-osu_2058788_3017707256.osr")?;
+  let mut replay = Replay::parse(&mut osr)?;
+  assert!(replay.parse_score_info()?.is_none());
+
+  replay.version = 30000016;
+  replay.update_score_info(&score_info)?;
+  let mut buf = Vec::new();
+  replay.write(&mut buf)?;
+
+  let replay2 = Replay::parse(&mut buf.as_slice())?;
+  assert_eq!(replay2.parse_score_info()?, Some(score_info));
+  compare_action_data(&replay, &replay2)?;
+
+  let mut buf2 = Vec::new();
+  replay2.write(&mut buf2)?;
+  assert_eq!(buf, buf2);
+  Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_lazer_score_info_missing_fields() -> Result<()> {
+  use libosu::replay::LazerScoreInfo;
+
+  let json = serde_json::json!({
+    "online_id": -1,
+    "mods": [],
+    "statistics": { "great": 10 },
+    "maximum_statistics": { "great": 10 },
+    "user_id": 1234,
+  });
+  let score_info = serde_json::from_value::<LazerScoreInfo>(json.clone())?;
+  assert_eq!(score_info.client_version, None);
+  assert_eq!(score_info.rank, None);
+  assert_eq!(score_info.total_score_without_mods, None);
+  assert!(score_info.extra.is_empty());
+  assert_eq!(serde_json::to_value(&score_info)?, json);
+  Ok(())
+}
+
 #[test]
 fn test_replay_parse() -> Result<()> {
   let mut osr = File::open("tests/files/replay-osu_1816113_2892542031.osr")?;
@@ -231,3 +458,116 @@ fn test_replay_parse() -> Result<()> {
   assert_eq!(replay.target_practice_total_accuracy, None);
   Ok(())
 }
+
+#[test]
+fn test_replay_builder() -> Result<()> {
+  for path in [
+    "tests/files/replay-osu_1816113_2892542031.osr",
+    "tests/files/replay-osu_2058788_3017707256.osr",
+  ] {
+    let original = Replay::parse(&mut File::open(path)?)?;
+    let replay = Replay::builder(original.mode, &original.beatmap_hash)
+      .version(original.version)
+      .player_username(&original.player_username)
+      .hit_counts(original.hit_counts())
+      .score(original.score)
+      .max_combo(original.max_combo)
+      .perfect(original.perfect)
+      .mods(original.mods)
+      .timestamp(original.time())
+      .build()?;
+
+    assert_eq!(replay.replay_hash, original.replay_hash);
+    assert_eq!(replay.timestamp, original.timestamp);
+    assert_eq!(replay.hit_counts(), original.hit_counts());
+  }
+
+  // 2020-01-01 00:00:00 UTC
+  let time = UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+  assert_eq!(system_time_to_ticks(time), 637_134_336_000_000_000);
+  assert_eq!(ticks_to_system_time(637_134_336_000_000_000), time);
+
+  let replay = Replay::builder(Mode::Osu, "").build()?;
+  assert_eq!(replay.version, DEFAULT_REPLAY_VERSION);
+  assert!(replay.perfect);
+  Ok(())
+}
+
+#[cfg(any(feature = "replay-data", feature = "replay-data-xz2"))]
+#[test]
+fn test_replay_builder_action_data() -> Result<()> {
+  let original = Replay::parse(&mut File::open(
+    "tests/files/replay-osu_2058788_3017707256.osr",
+  )?)?;
+  let action_data = original.parse_action_data()?;
+  let replay = Replay::builder(Mode::Osu, &original.beatmap_hash)
+    .action_data(action_data)
+    .build()?;
+
+  let mut buf = Vec::new();
+  replay.write(&mut buf)?;
+  let replay = Replay::parse(&mut buf.as_slice())?;
+  compare_action_data(&original, &replay)?;
+  Ok(())
+}
+
+#[test]
+fn test_retarget() -> Result<()> {
+  let original = Replay::parse(&mut File::open(
+    "tests/files/replay-osu_1816113_2892542031.osr",
+  )?)?;
+  let replay = original.retarget("0123456789abcdef0123456789abcdef");
+  assert_eq!(replay.beatmap_hash, "0123456789abcdef0123456789abcdef");
+  assert_ne!(replay.replay_hash, original.replay_hash);
+
+  let replay = replay.retarget(&original.beatmap_hash);
+  assert_eq!(replay.replay_hash, original.replay_hash);
+  Ok(())
+}
+
+#[cfg(feature = "replay-data")]
+#[test]
+fn test_replay_transforms() -> Result<()> {
+  let original = Replay::parse(&mut File::open(
+    "tests/files/replay-osu_2058788_3017707256.osr",
+  )?)?;
+  let action_data = original.parse_action_data()?;
+  let timeline = Timeline::new(&action_data);
+  let frames = timeline.frames();
+
+  let no_hard_rock = original.with_hard_rock(false)?;
+  assert!(!no_hard_rock.mods.contains(Mods::HardRock));
+  let mirrored = Timeline::new(&no_hard_rock.parse_action_data()?);
+  for (a, b) in frames.iter().zip(mirrored.frames()) {
+    assert_eq!(a.time, b.time);
+    assert!((a.pos.y - (384.0 - b.pos.y)).abs() < 0.001);
+  }
+  compare_action_data(&original, &no_hard_rock.with_hard_rock(true)?)?;
+
+  let slower = original.scale_time(1.5)?;
+  assert_eq!(slower.parse_action_data()?.rng_seed, action_data.rng_seed);
+  let slower = Timeline::new(&slower.parse_action_data()?);
+  assert_eq!(slower.frames().len(), frames.len());
+  for (a, b) in frames.iter().zip(slower.frames()) {
+    assert_eq!((a.time as f64 * 1.5).round() as i32, b.time);
+  }
+
+  let later = original.offset(1000)?;
+  let later = Timeline::new(&later.parse_action_data()?);
+  for (a, b) in frames.iter().zip(later.frames()) {
+    assert_eq!(a.time + 1000, b.time);
+  }
+
+  let trimmed = original.trim(10_000, 20_000)?;
+  let trimmed = Timeline::new(&trimmed.parse_action_data()?);
+  let expected = frames
+    .iter()
+    .filter(|frame| (10_000..=20_000).contains(&frame.time))
+    .collect::<Vec<_>>();
+  assert_eq!(trimmed.frames().len(), expected.len());
+  for (a, b) in expected.iter().zip(trimmed.frames()) {
+    assert_eq!(a.time, b.time);
+    assert_eq!(a.pos, b.pos);
+  }
+  Ok(())
+}
@@ -0,0 +1,384 @@
+use std::fs::File;
+use std::str::FromStr;
+
+use anyhow::Result;
+use libosu::{
+  autoplay::Autoplay,
+  beatmap::Beatmap,
+  data::{Grade, HitCounts, Mode, Mods},
+  replay::Replay,
+  score::{
+    classic_to_standardised, score_v1_mod_multiplier, standardised_to_classic,
+    ScoreCalculator, ScoreStatistics, StandardisedScoreEstimate,
+  },
+  sim::{HitResult, OsuSimulator},
+};
+
+fn counts(
+  [count_geki, count_300, count_katu, count_100, count_50, count_miss]: [u16;
+    6],
+) -> HitCounts {
+  HitCounts {
+    count_300,
+    count_100,
+    count_50,
+    count_geki,
+    count_katu,
+    count_miss,
+  }
+}
+
+fn assert_accuracy(stats: ScoreStatistics, accuracy: f64) {
+  assert!(
+    (stats.accuracy() - accuracy).abs() < 1e-9,
+    "expected {}, got {}",
+    accuracy,
+    stats.accuracy()
+  );
+}
+
+#[test]
+fn test_accuracy() {
+  let stats =
+    ScoreStatistics::new(Mode::Osu, counts([0, 330, 0, 24, 0, 2]), Mods::None);
+  assert_eq!(stats.total_judgements(), 356);
+  assert_accuracy(stats, (330.0 * 300.0 + 24.0 * 100.0) / (356.0 * 300.0));
+
+  // taiko 100s (shown as GOOD) count for half, and 50s don't exist
+  let stats =
+    ScoreStatistics::new(Mode::Taiko, counts([0, 90, 0, 8, 0, 2]), Mods::None);
+  assert_accuracy(stats, 0.94);
+
+  // missed droplets count against catch accuracy
+  let stats = ScoreStatistics::new(
+    Mode::Catch,
+    counts([0, 100, 5, 20, 75, 0]),
+    Mods::None,
+  );
+  assert_eq!(stats.total_judgements(), 200);
+  assert_accuracy(stats, 0.975);
+
+  // mania MAX and 300 are worth the same
+  let stats = ScoreStatistics::new(
+    Mode::Mania,
+    counts([50, 40, 10, 0, 0, 0]),
+    Mods::None,
+  );
+  assert_accuracy(stats, (90.0 * 300.0 + 10.0 * 200.0) / (100.0 * 300.0));
+
+  let stats = ScoreStatistics::new(Mode::Osu, HitCounts::default(), Mods::None);
+  assert_accuracy(stats, 1.0);
+}
+
+#[test]
+fn test_grade() {
+  let grade = |mode, judgements, mods| {
+    ScoreStatistics::new(mode, counts(judgements), mods).grade()
+  };
+
+  assert_eq!(
+    grade(Mode::Osu, [0, 100, 0, 0, 0, 0], Mods::None),
+    Grade::SS
+  );
+  assert_eq!(
+    grade(Mode::Osu, [0, 100, 0, 0, 0, 0], Mods::Hidden),
+    Grade::SSH
+  );
+  assert_eq!(grade(Mode::Osu, [0, 95, 0, 5, 0, 0], Mods::None), Grade::S);
+  assert_eq!(
+    grade(Mode::Osu, [0, 95, 0, 5, 0, 0], Mods::Flashlight),
+    Grade::SH
+  );
+  // a miss or too many 50s knock an S down to an A
+  assert_eq!(grade(Mode::Osu, [0, 95, 0, 4, 0, 1], Mods::None), Grade::A);
+  assert_eq!(grade(Mode::Osu, [0, 95, 0, 3, 2, 0], Mods::None), Grade::A);
+  assert_eq!(grade(Mode::Osu, [0, 85, 0, 15, 0, 0], Mods::None), Grade::A);
+  assert_eq!(grade(Mode::Osu, [0, 75, 0, 25, 0, 0], Mods::None), Grade::B);
+  assert_eq!(grade(Mode::Osu, [0, 65, 0, 30, 0, 5], Mods::None), Grade::C);
+  assert_eq!(grade(Mode::Osu, [0, 50, 0, 50, 0, 0], Mods::None), Grade::D);
+
+  assert_eq!(
+    grade(Mode::Catch, [0, 99, 0, 0, 0, 1], Mods::None),
+    Grade::S
+  );
+  assert_eq!(
+    grade(Mode::Catch, [0, 97, 0, 0, 0, 3], Mods::None),
+    Grade::A
+  );
+
+  // fade in only gives silver grades in mania
+  assert_eq!(
+    grade(Mode::Mania, [100, 0, 0, 0, 0, 0], Mods::FadeIn),
+    Grade::SSH
+  );
+  assert_eq!(
+    grade(Mode::Osu, [0, 100, 0, 0, 0, 0], Mods::FadeIn),
+    Grade::SS
+  );
+  assert_eq!(
+    grade(Mode::Mania, [50, 46, 4, 0, 0, 0], Mods::None),
+    Grade::S
+  );
+  assert_eq!(
+    grade(Mode::Mania, [50, 40, 0, 0, 0, 10], Mods::None),
+    Grade::B
+  );
+}
+
+#[test]
+fn test_grade_names() -> Result<()> {
+  for (grade, name) in [
+    (Grade::SSH, "XH"),
+    (Grade::SH, "SH"),
+    (Grade::SS, "X"),
+    (Grade::S, "S"),
+    (Grade::D, "D"),
+    (Grade::F, "F"),
+  ] {
+    assert_eq!(grade.to_string(), name);
+    assert_eq!(name.parse::<Grade>()?, grade);
+  }
+  assert!(Grade::SSH.is_silver() && Grade::SSH.is_ss());
+  assert!(!Grade::SS.is_silver() && Grade::SS.is_ss());
+  assert!(Grade::SH.is_silver() && !Grade::SH.is_ss());
+
+  // the game stores grades by their number, with the silver SS first
+  let numbered = (0..10)
+    .map(|n| num::FromPrimitive::from_u8(n).unwrap())
+    .collect::<Vec<Grade>>();
+  assert_eq!(numbered[..4], [Grade::SSH, Grade::SH, Grade::SS, Grade::S]);
+  assert_eq!(numbered[9], Grade::None);
+  assert_eq!(Grade::SS as u8, 2);
+  assert!("SSS".parse::<Grade>().is_err());
+  Ok(())
+}
+
+#[test]
+fn test_named_counts() {
+  let stats =
+    ScoreStatistics::new(Mode::Mania, counts([1, 2, 3, 4, 5, 6]), Mods::None);
+  assert_eq!(
+    stats.named_counts(),
+    vec![
+      ("MAX", 1),
+      ("300", 2),
+      ("200", 3),
+      ("100", 4),
+      ("50", 5),
+      ("Miss", 6)
+    ]
+  );
+
+  let stats =
+    ScoreStatistics::new(Mode::Catch, counts([1, 2, 3, 4, 5, 6]), Mods::None);
+  let named = stats.named_counts();
+  assert!(named.contains(&("Droplets", 5)));
+  assert!(named.contains(&("Missed droplets", 3)));
+}
+
+#[test]
+fn test_replay_statistics() -> Result<()> {
+  let replay = Replay::parse(&mut File::open(
+    "tests/files/replay-osu_2058788_3017707256.osr",
+  )?)?;
+  let stats = replay.statistics();
+  assert_eq!(stats.counts, replay.hit_counts());
+  assert_eq!(stats.mods, replay.mods);
+  assert_eq!(stats.grade(), Grade::A);
+  assert_accuracy(stats, (330.0 * 300.0 + 24.0 * 100.0) / (356.0 * 300.0));
+  Ok(())
+}
+
+const CIRCLES_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:5
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,100,1000,1,0,0:0:0:0:
+150,100,1500,1,0,0:0:0:0:
+200,100,2000,1,0,0:0:0:0:
+250,100,2500,1,0,0:0:0:0:
+300,100,3000,1,0,0:0:0:0:
+350,100,3500,1,0,0:0:0:0:
+400,100,4000,1,0,0:0:0:0:
+450,100,4500,1,0,0:0:0:0:
+400,200,5000,1,0,0:0:0:0:
+350,200,5500,1,0,0:0:0:0:
+"#;
+
+#[test]
+fn test_score_v1() -> Result<()> {
+  let beatmap = Beatmap::from_str(CIRCLES_MAP)?;
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::None);
+  // (5 HP + 5 OD + 4 CS + 16 for the density) / 38 * 5, rounded
+  assert_eq!(calculator.difficulty_multiplier(), 4);
+
+  // every circle is worth 300 plus (combo - 1) * 300 / 25 * 4
+  let perfect = OsuSimulator::new(&beatmap, Mods::None).perfect_result();
+  assert_eq!(
+    calculator.score_v1(&perfect.judgements),
+    Some(3000 + 48 * 36)
+  );
+
+  let actions = Autoplay::default().generate(&beatmap, Mods::None);
+  let result = OsuSimulator::new(&beatmap, Mods::None).simulate(&actions);
+  assert_eq!(
+    calculator.score_v1(&result.judgements),
+    calculator.score_v1(&perfect.judgements)
+  );
+
+  // the combo bonus is rounded down on each hit
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::Hidden);
+  assert_eq!(calculator.score_v1(&perfect.judgements), Some(3000 + 1828));
+
+  // missing the fifth circle restarts the combo
+  let mut judgements = perfect.judgements.clone();
+  judgements[4].result = HitResult::Miss;
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::None);
+  assert_eq!(
+    calculator.score_v1(&judgements),
+    Some(2700 + 48 * (1 + 2) + 48 * (1 + 2 + 3))
+  );
+
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Taiko, Mods::None);
+  assert_eq!(calculator.score_v1(&perfect.judgements), None);
+  Ok(())
+}
+
+#[test]
+fn test_score_v2() -> Result<()> {
+  let beatmap = Beatmap::from_str(CIRCLES_MAP)?;
+  let perfect = OsuSimulator::new(&beatmap, Mods::None).perfect_result();
+
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::None);
+  assert_eq!(calculator.score_v2(&perfect.judgements), Some(1_000_000));
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::NoFail);
+  assert_eq!(calculator.score_v2(&perfect.judgements), Some(1_000_000));
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::HardRock);
+  assert_eq!(calculator.score_v2(&perfect.judgements), Some(1_100_000));
+
+  // a play that was failed halfway only gets half of the accuracy portion
+  let calculator = ScoreCalculator::new(&beatmap, Mode::Osu, Mods::None);
+  let score = calculator.score_v2(&perfect.judgements[..5]).unwrap();
+  let combo_portion = (1..=5).map(|combo| 10 + combo).sum::<u32>() as f64
+    / (1..=10).map(|combo| 10 + combo).sum::<u32>() as f64;
+  assert_eq!(
+    score,
+    (700_000.0 * combo_portion + 150_000.0).round() as u32
+  );
+
+  let mut judgements = perfect.judgements.clone();
+  judgements[9].result = HitResult::Hit100;
+  // the 100 costs two thirds of the combo score of the last circle, and an accuracy of 28/30
+  let combo_portion = (4650.0 - 200.0 * 2.0) / 4650.0;
+  let accuracy = 28.0f64 / 30.0;
+  assert_eq!(
+    calculator.score_v2(&judgements),
+    Some(
+      (700_000.0 * combo_portion + 300_000.0 * accuracy.powi(10)).round()
+        as u32
+    )
+  );
+  Ok(())
+}
+
+#[test]
+fn test_standardised_score() {
+  let stats =
+    ScoreStatistics::new(Mode::Osu, counts([0, 100, 0, 0, 0, 0]), Mods::None);
+  assert_eq!(
+    stats.estimate_standardised_score(150, 150),
+    StandardisedScoreEstimate {
+      base: 1_000_000,
+      mod_multiplier: 1.0
+    }
+  );
+  let stats =
+    ScoreStatistics::new(Mode::Osu, counts([0, 100, 0, 0, 0, 0]), Mods::Hidden);
+  let estimate = stats.estimate_standardised_score(150, 150);
+  assert_eq!(estimate.base, 1_000_000);
+  assert_eq!(estimate.score(), 1_060_000);
+
+  // the 74 hits outside of the longest combo are assumed to be in one combo after the miss
+  let stats =
+    ScoreStatistics::new(Mode::Osu, counts([0, 99, 0, 0, 0, 1]), Mods::None);
+  let combo_progress = (75f64.powf(1.5) + 74f64.powf(1.5)) / 150f64.powf(1.5);
+  let expected = 700_000.0 * combo_progress + 300_000.0 * 0.99f64.powi(10);
+  assert_eq!(
+    stats.estimate_standardised_score(75, 150).score(),
+    expected.round() as u32
+  );
+
+  // osu!lazer counts MAX judgements as slightly better than 300s
+  let stats =
+    ScoreStatistics::new(Mode::Mania, counts([0, 10, 0, 0, 0, 0]), Mods::None);
+  assert_accuracy(stats, 1.0);
+  assert!((stats.lazer_accuracy() - 300.0 / 305.0).abs() < 1e-9);
+  let stats =
+    ScoreStatistics::new(Mode::Mania, counts([10, 0, 0, 0, 0, 0]), Mods::None);
+  assert_eq!(stats.estimate_standardised_score(10, 10).score(), 1_000_000);
+}
+
+#[test]
+fn test_classic_score() {
+  assert_eq!(
+    standardised_to_classic(Mode::Taiko, 1_000_000, 100),
+    210_900
+  );
+  // 21.62 * 100² + 100,000
+  assert_eq!(
+    standardised_to_classic(Mode::Catch, 1_000_000, 100),
+    316_200
+  );
+
+  for mode in [Mode::Osu, Mode::Taiko, Mode::Catch, Mode::Mania] {
+    for standardised in [0, 123_456, 1_000_000, 1_120_000] {
+      let classic = standardised_to_classic(mode, standardised, 500);
+      let back = classic_to_standardised(mode, classic, 500);
+      assert!(
+        (back as i64 - standardised as i64).abs() <= 1,
+        "{:?}: {} became {} and then {}",
+        mode,
+        standardised,
+        classic,
+        back
+      );
+    }
+  }
+
+  // droplets don't count as objects in osu!catch
+  let stats = ScoreStatistics::new(
+    Mode::Catch,
+    counts([0, 100, 5, 20, 75, 0]),
+    Mods::None,
+  );
+  assert_eq!(stats.object_count(), 100);
+  assert_eq!(stats.classic_score(1_000_000), 316_200);
+}
+
+#[test]
+fn test_replay_standardised_score() -> Result<()> {
+  let replay = Replay::parse(&mut File::open(
+    "tests/files/replay-osu_2058788_3017707256.osr",
+  )?)?;
+  let full_combo = replay.estimate_standardised_score(replay.max_combo as u32);
+  let broken_combo =
+    replay.estimate_standardised_score(replay.max_combo as u32 * 2);
+  let multiplier = score_v1_mod_multiplier(replay.mode, replay.mods);
+  assert_eq!(full_combo.mod_multiplier, multiplier);
+  assert!(full_combo.base < 1_000_000);
+  assert!(broken_combo.base < full_combo.base);
+  Ok(())
+}
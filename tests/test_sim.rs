@@ -0,0 +1,736 @@
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+use anyhow::Result;
+use libosu::{
+  beatmap::Beatmap,
+  data::{HitCounts, Mods},
+  hitobject::HitObject,
+  replay::{Buttons, ReplayAction, ReplayActionData},
+  sim::{
+    CatchObjectKind, CatchSimulator, HealthSimulator, HitResult, JudgementKind,
+    ManiaSimulator, OsuSimulator, TaikoSimulator,
+  },
+  timing::Millis,
+};
+
+const OSU_MAP: &str = r#"osu file format v14
+
+[General]
+StackLeniency: 0.7
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:2
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,100,1000,1,0,0:0:0:0:
+200,200,2000,2,0,L|400:200,1,140
+256,192,4000,12,0,6000,0:0:0:0:
+"#;
+
+const MANIA_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 3
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:5
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1200,1,0,0:0:0:0:
+448,192,1400,128,0,2000:0:0:0:0:
+"#;
+
+const TAIKO_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 1
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:5
+OverallDifficulty:5
+ApproachRate:5
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,192,1200,1,8,0:0:0:0:
+256,192,1400,1,4,0:0:0:0:
+256,192,2000,2,0,L|456:192,1,140
+256,192,3000,12,0,4000,0:0:0:0:
+"#;
+
+const CATCH_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 2
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:5
+ApproachRate:8
+SliderMultiplier:1.4
+SliderTickRate:2
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,192,1000,1,0,0:0:0:0:
+200,192,2000,2,0,L|400:192,1,140
+256,192,3000,12,0,3400,0:0:0:0:
+"#;
+
+/// Builds action data out of frames with absolute timestamps.
+fn actions(frames: &[(i32, f32, f32, Buttons)]) -> ReplayActionData {
+  let mut last = 0;
+  let frames = frames
+    .iter()
+    .map(|(time, x, y, buttons)| {
+      let delta = time - last;
+      last = *time;
+      ReplayAction {
+        time: Millis(delta),
+        x: *x,
+        y: *y,
+        buttons: *buttons,
+      }
+    })
+    .collect();
+  ReplayActionData {
+    frames,
+    rng_seed: None,
+  }
+}
+
+fn perfect_play() -> Vec<(i32, f32, f32, Buttons)> {
+  let k1 = Buttons::K1 | Buttons::M1;
+  let k2 = Buttons::K2 | Buttons::M2;
+
+  let mut frames = vec![
+    (900, 100.0, 100.0, Buttons::empty()),
+    (1005, 100.0, 100.0, k1),
+    (1050, 100.0, 100.0, Buttons::empty()),
+    (1995, 200.0, 200.0, k2),
+  ];
+
+  // follow the slider ball
+  for time in (2000..=2500).step_by(20) {
+    let x = 200.0 + 0.28 * (time - 2000) as f32;
+    frames.push((time, x, 200.0, k2));
+  }
+  frames.push((2550, 340.0, 200.0, Buttons::empty()));
+
+  // spin at 400 rpm
+  for time in (4000..=6000).step_by(16) {
+    let angle = (time - 4000) as f64 / 60_000.0 * 400.0 * TAU;
+    let x = 256.0 + 50.0 * angle.cos();
+    let y = 192.0 + 50.0 * angle.sin();
+    frames.push((time, x as f32, y as f32, k1));
+  }
+  frames
+}
+
+#[test]
+fn test_osu_perfect_play() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+  let result = sim.simulate(&actions(&perfect_play()));
+
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 3,
+      count_geki: 2,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 5);
+
+  let offsets = result
+    .judgements
+    .iter()
+    .filter_map(|judgement| judgement.offset)
+    .collect::<Vec<_>>();
+  assert_eq!(offsets, vec![5.0, -5.0]);
+
+  let kinds = result
+    .judgements
+    .iter()
+    .filter(|judgement| judgement.object_index == 1)
+    .map(|judgement| judgement.kind)
+    .collect::<Vec<_>>();
+  assert_eq!(
+    kinds,
+    vec![
+      JudgementKind::SliderHead,
+      JudgementKind::SliderTick,
+      JudgementKind::SliderTail,
+      JudgementKind::Object,
+    ]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_osu_no_input() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+  let result = sim.simulate(&actions(&[]));
+
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_miss: 3,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 0);
+  Ok(())
+}
+
+#[test]
+fn test_osu_slider_break() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+
+  // let go of the slider before the tick
+  let mut frames = perfect_play();
+  for frame in frames.iter_mut() {
+    if frame.0 >= 2200 && frame.0 <= 2500 {
+      frame.3 = Buttons::empty();
+    }
+  }
+  let result = sim.simulate(&actions(&frames));
+
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 2,
+      count_50: 1,
+      count_geki: 1,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 2);
+  Ok(())
+}
+
+#[test]
+fn test_osu_note_lock() -> Result<()> {
+  let beatmap = Beatmap::from_str(&OSU_MAP.replace(
+    "200,200,2000,2,0,L|400:200,1,140",
+    "300,100,1100,1,0,0:0:0:0:",
+  ))?;
+  let sim = OsuSimulator::new(&beatmap, Mods::None);
+  let k1 = Buttons::K1 | Buttons::M1;
+  let result = sim.simulate(&actions(&[
+    // the first circle hasn't been hit yet, so this press is ignored
+    (1050, 300.0, 100.0, k1),
+    (1060, 300.0, 100.0, Buttons::empty()),
+    // the first circle has been missed by now
+    (1130, 300.0, 100.0, k1),
+  ]));
+
+  let results = result
+    .object_judgements()
+    .map(|judgement| (judgement.object_index, judgement.result))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    results,
+    vec![
+      (0, HitResult::Miss),
+      (1, HitResult::Hit300),
+      (2, HitResult::Miss)
+    ]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_osu_hard_rock() -> Result<()> {
+  let beatmap = Beatmap::from_str(OSU_MAP)?;
+  let sim = OsuSimulator::new(&beatmap, Mods::HardRock);
+
+  // objects are flipped, so the unflipped play misses the circle
+  let result = sim.simulate(&actions(&perfect_play()));
+  let first = result.object_judgements().next().unwrap();
+  assert_eq!(first.result, HitResult::Miss);
+
+  let flipped = perfect_play()
+    .into_iter()
+    .map(|(time, x, y, buttons)| (time, x, 384.0 - y, buttons))
+    .collect::<Vec<_>>();
+  let result = sim.simulate(&actions(&flipped));
+  assert_eq!(result.counts.count_miss, 0);
+  Ok(())
+}
+
+#[test]
+fn test_stack_heights() -> Result<()> {
+  let beatmap = Beatmap::from_str(&OSU_MAP.replace(
+    "200,200,2000,2,0,L|400:200,1,140",
+    "100,100,1100,1,0,0:0:0:0:\n101,100,1200,1,0,0:0:0:0:",
+  ))?;
+  assert_eq!(beatmap.stack_heights(), vec![2, 1, 0, 0]);
+
+  // a slider going back and forth ends at its head, but maps older than v6 stack against the end
+  // of its path anyway
+  let map = OSU_MAP.replace(
+    "200,200,2000,2,0,L|400:200,1,140",
+    "200,200,2000,2,0,L|300:200,2,100\n300,200,2800,1,0,0:0:0:0:",
+  );
+  let beatmap = Beatmap::from_str(&map)?;
+  assert_eq!(beatmap.stack_heights(), vec![0, 0, 0, 0]);
+  let old = Beatmap::from_str(&map.replace("format v14", "format v5"))?;
+  assert_eq!(old.version, 5);
+  assert_eq!(old.stack_heights(), vec![0, 0, -1, 0]);
+
+  let old =
+    Beatmap::from_str(&OSU_MAP.replace("format v14", "format v5").replace(
+      "200,200,2000,2,0,L|400:200,1,140",
+      "100,100,1100,1,0,0:0:0:0:\n101,100,1200,1,0,0:0:0:0:",
+    ))?;
+  assert_eq!(old.stack_heights(), vec![2, 1, 0, 0]);
+  Ok(())
+}
+
+/// Builds osu!mania action data out of the pressed columns at absolute timestamps.
+fn mania_actions(frames: &[(i32, u32)]) -> ReplayActionData {
+  let frames = frames
+    .iter()
+    .map(|(time, columns)| (*time, *columns as f32, 0.0, Buttons::empty()))
+    .collect::<Vec<_>>();
+  actions(&frames)
+}
+
+fn mania_play() -> Vec<(i32, u32)> {
+  vec![
+    (995, 0b0001),
+    (1050, 0),
+    (1230, 0b0010),
+    (1260, 0),
+    (1410, 0b1000),
+    (2005, 0),
+  ]
+}
+
+#[test]
+fn test_hold_note_roundtrip() -> Result<()> {
+  let line = "448,192,1400,128,0,2000:0:0:0:0:";
+  let ho = HitObject::from_str(line)?;
+  assert!(ho.kind.is_hold_note());
+  assert_eq!(ho.to_string(), line);
+  Ok(())
+}
+
+#[test]
+fn test_mania_play() -> Result<()> {
+  let beatmap = Beatmap::from_str(MANIA_MAP)?;
+  let sim = ManiaSimulator::new(&beatmap, Mods::None);
+  assert_eq!(sim.keys(), 4);
+  assert_eq!(sim.hit_windows().hit300, 40.0);
+
+  let result = sim.simulate(&mania_actions(&mania_play()));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_geki: 2,
+      count_300: 1,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 4);
+  assert_eq!(
+    sim.column_hit_errors(&result),
+    vec![vec![-5.0], vec![30.0], vec![], vec![10.0]]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_mania_hold_note_released_early() -> Result<()> {
+  let beatmap = Beatmap::from_str(MANIA_MAP)?;
+  let sim = ManiaSimulator::new(&beatmap, Mods::None);
+
+  let mut frames = mania_play();
+  frames.last_mut().unwrap().0 = 1700;
+  let result = sim.simulate(&mania_actions(&frames));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_geki: 1,
+      count_300: 1,
+      count_50: 1,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 3);
+
+  let result = sim.simulate(&mania_actions(&[]));
+  assert_eq!(result.counts.count_miss, 3);
+  Ok(())
+}
+
+#[test]
+fn test_mania_rate_adjust() -> Result<()> {
+  let beatmap = Beatmap::from_str(MANIA_MAP)?;
+  let windows = ManiaSimulator::new(&beatmap, Mods::HardRock).hit_windows();
+  assert_eq!(windows.hit300, 28.0);
+
+  // the windows don't change in real time, so they're longer in map time
+  let windows = ManiaSimulator::new(&beatmap, Mods::DoubleTime).hit_windows();
+  assert_eq!(windows.hit300, 60.0);
+  Ok(())
+}
+
+fn taiko_play() -> Vec<(i32, f32, f32, Buttons)> {
+  let mut frames = vec![
+    (995, 0.0, 0.0, Buttons::M1),
+    (1000, 0.0, 0.0, Buttons::empty()),
+    (1210, 0.0, 0.0, Buttons::M2),
+    (1220, 0.0, 0.0, Buttons::empty()),
+    // both don keys on the big note
+    (1405, 0.0, 0.0, Buttons::M1),
+    (1415, 0.0, 0.0, Buttons::M1 | Buttons::K1),
+    (1430, 0.0, 0.0, Buttons::empty()),
+  ];
+
+  // drumroll ticks are 125ms apart
+  for time in (2000..=2500).step_by(125) {
+    frames.push((time, 0.0, 0.0, Buttons::K1));
+    frames.push((time + 30, 0.0, 0.0, Buttons::empty()));
+  }
+
+  // the swell needs 8 alternating hits
+  for i in 0..10 {
+    let time = 3050 + i * 50;
+    let key = if i % 2 == 0 { Buttons::M1 } else { Buttons::M2 };
+    frames.push((time, 0.0, 0.0, key));
+    frames.push((time + 20, 0.0, 0.0, Buttons::empty()));
+  }
+  frames
+}
+
+#[test]
+fn test_taiko_play() -> Result<()> {
+  let beatmap = Beatmap::from_str(TAIKO_MAP)?;
+  let sim = TaikoSimulator::new(&beatmap, Mods::None);
+  assert_eq!(sim.hit_windows().hit300, 35.0);
+
+  let result = sim.simulate(&actions(&taiko_play()));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 3,
+      count_geki: 1,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 3);
+  assert_eq!(
+    result.hit_errors().collect::<Vec<_>>(),
+    vec![-5.0, 10.0, 5.0]
+  );
+
+  let ticks = result
+    .judgements
+    .iter()
+    .filter(|judgement| judgement.kind == JudgementKind::DrumRollTick)
+    .map(|judgement| judgement.result)
+    .collect::<Vec<_>>();
+  assert_eq!(ticks, vec![HitResult::Hit; 5]);
+
+  let bonus = result
+    .object_judgements()
+    .filter(|judgement| judgement.kind == JudgementKind::Bonus)
+    .map(|judgement| judgement.result)
+    .collect::<Vec<_>>();
+  assert_eq!(bonus, vec![HitResult::Hit, HitResult::Hit]);
+  Ok(())
+}
+
+#[test]
+fn test_taiko_wrong_colour() -> Result<()> {
+  let beatmap = Beatmap::from_str(TAIKO_MAP)?;
+  let sim = TaikoSimulator::new(&beatmap, Mods::None);
+
+  let mut frames = taiko_play();
+  frames[2].3 = Buttons::K1;
+  let result = sim.simulate(&actions(&frames));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 2,
+      count_geki: 1,
+      count_miss: 1,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 1);
+
+  // drumrolls and swells don't break the combo
+  let result = sim.simulate(&actions(&frames[..7]));
+  assert_eq!(result.counts.count_miss, 1);
+  assert_eq!(result.max_combo, 1);
+  Ok(())
+}
+
+#[test]
+fn test_catch_objects() -> Result<()> {
+  let beatmap = Beatmap::from_str(CATCH_MAP)?;
+  let sim = CatchSimulator::new(&beatmap, Mods::None);
+  assert!((sim.catch_width() - 97.356).abs() < 1e-9);
+
+  let count = |kind| {
+    sim
+      .objects()
+      .iter()
+      .filter(|object| object.kind == kind)
+      .count()
+  };
+  assert_eq!(count(CatchObjectKind::Fruit), 3);
+  assert_eq!(count(CatchObjectKind::Droplet), 1);
+  assert_eq!(count(CatchObjectKind::TinyDroplet), 6);
+  assert_eq!(count(CatchObjectKind::Banana), 5);
+
+  // the droplet is half way along the juice stream
+  let droplet = sim
+    .objects()
+    .iter()
+    .find(|object| object.kind == CatchObjectKind::Droplet)
+    .unwrap();
+  assert_eq!((droplet.time, droplet.x), (2250.0, 270.0));
+  Ok(())
+}
+
+#[test]
+fn test_catch_play() -> Result<()> {
+  let beatmap = Beatmap::from_str(CATCH_MAP)?;
+  let sim = CatchSimulator::new(&beatmap, Mods::None);
+
+  // move the catcher directly under every object
+  let frames = sim
+    .objects()
+    .iter()
+    .map(|object| (object.time as i32, object.x as f32, 0.0, Buttons::empty()))
+    .collect::<Vec<_>>();
+  let result = sim.simulate(&actions(&frames));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 3,
+      count_100: 1,
+      count_50: 6,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 4);
+
+  // standing still only catches the first fruit
+  let result = sim.simulate(&actions(&[(0, 100.0, 0.0, Buttons::empty())]));
+  assert_eq!(
+    result.counts,
+    HitCounts {
+      count_300: 1,
+      count_miss: 3,
+      count_katu: 6,
+      ..HitCounts::default()
+    }
+  );
+  assert_eq!(result.max_combo, 1);
+  Ok(())
+}
+
+#[test]
+fn test_catch_hyper_dash() -> Result<()> {
+  let beatmap = Beatmap::from_str(&CATCH_MAP.replace(
+    "200,192,2000,2,0,L|400:192,1,140",
+    "500,192,1100,1,0,0:0:0:0:",
+  ))?;
+  let sim = CatchSimulator::new(&beatmap, Mods::None);
+  let hyper_dashes = sim
+    .objects()
+    .iter()
+    .filter(|object| object.kind == CatchObjectKind::Fruit)
+    .map(|object| object.hyper_dash)
+    .collect::<Vec<_>>();
+  assert_eq!(hyper_dashes, vec![true, false]);
+
+  let hard_rock = CatchSimulator::new(&beatmap, Mods::HardRock);
+  assert!(hard_rock.catch_width() < sim.catch_width());
+  Ok(())
+}
+
+#[test]
+fn test_catch_hard_rock_offsets() -> Result<()> {
+  // fruits in the same place get random offsets, seeded the same way as in stable
+  let fruits = [0, 61, 83, 103, 150, 250, 399]
+    .iter()
+    .map(|offset| format!("256,192,{},1,0,0:0:0:0:", 1000 + offset))
+    .collect::<Vec<_>>()
+    .join("\n");
+  let beatmap = Beatmap::from_str(&CATCH_MAP.replace(
+    "100,192,1000,1,0,0:0:0:0:\n200,192,2000,2,0,L|400:192,1,140\n256,192,3000,12,0,3400,0:0:0:0:",
+    &fruits,
+  ))?;
+  let sim = CatchSimulator::new(&beatmap, Mods::HardRock);
+  let positions = sim
+    .objects()
+    .iter()
+    .map(|object| object.x)
+    .collect::<Vec<_>>();
+  // 83ms after the first fruit the offset is scaled by 83 / 4 = 20 rather than 20.75
+  assert_eq!(
+    positions,
+    vec![256.0, 253.0, 248.0, 236.0, 236.0, 276.0, 236.0]
+  );
+  Ok(())
+}
+
+const HEALTH_MAP: &str = r#"osu file format v14
+
+[General]
+Mode: 0
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:5
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[Events]
+2,11000,20000
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+
+[HitObjects]
+100,100,1000,1,0,0:0:0:0:
+140,100,1500,1,0,0:0:0:0:
+180,100,2000,1,0,0:0:0:0:
+220,100,2500,1,0,0:0:0:0:
+260,100,3000,1,0,0:0:0:0:
+300,100,3500,1,0,0:0:0:0:
+340,100,4000,1,0,0:0:0:0:
+380,100,4500,1,0,0:0:0:0:
+100,140,5000,1,0,0:0:0:0:
+140,140,5500,1,0,0:0:0:0:
+180,140,6000,1,0,0:0:0:0:
+220,140,6500,1,0,0:0:0:0:
+260,140,7000,1,0,0:0:0:0:
+300,140,7500,1,0,0:0:0:0:
+340,140,8000,1,0,0:0:0:0:
+380,140,8500,1,0,0:0:0:0:
+100,180,9000,1,0,0:0:0:0:
+140,180,9500,1,0,0:0:0:0:
+180,180,10000,1,0,0:0:0:0:
+220,180,10500,1,0,0:0:0:0:
+100,300,21000,1,0,0:0:0:0:
+140,300,21500,1,0,0:0:0:0:
+180,300,22000,1,0,0:0:0:0:
+220,300,22500,1,0,0:0:0:0:
+260,300,23000,1,0,0:0:0:0:
+300,300,23500,1,0,0:0:0:0:
+340,300,24000,1,0,0:0:0:0:
+380,300,24500,1,0,0:0:0:0:
+100,300,25000,1,0,0:0:0:0:
+140,300,25500,1,0,0:0:0:0:
+"#;
+
+#[test]
+fn test_health() -> Result<()> {
+  let beatmap = Beatmap::from_str(HEALTH_MAP)?;
+  let perfect = OsuSimulator::new(&beatmap, Mods::None).perfect_result();
+  let sim = HealthSimulator::new(&beatmap, Mods::None);
+
+  // the drain rate is lowered until a perfect play stays above 80% at HP 5
+  let result = sim.simulate(&perfect.judgements);
+  assert_eq!(result.fail_time, None);
+  assert!(result.min_health > 0.8);
+  let times = result
+    .life_graph
+    .iter()
+    .map(|(time, _)| *time)
+    .collect::<Vec<_>>();
+  assert_eq!(times, (3000..=25000).step_by(2000).collect::<Vec<_>>());
+
+  // without any hits, life only goes down, except during the break
+  let result = sim.simulate(&[]);
+  let life = |time| {
+    result
+      .life_graph
+      .iter()
+      .find(|(t, _)| *t == time)
+      .map(|(_, life)| *life)
+      .unwrap()
+  };
+  assert!(life(3000) > life(9000));
+  assert_eq!(life(11000), life(19000));
+  assert!(life(19000) > life(25000));
+  // the map is 24.5 seconds long, 9 of which are a break
+  assert!(
+    (result.final_health - (1.0 - 15_500.0 * sim.drain_rate())).abs() < 1e-9
+  );
+
+  let mut misses = perfect.judgements.clone();
+  misses
+    .iter_mut()
+    .for_each(|judgement| judgement.result = HitResult::Miss);
+  let result = sim.simulate(&misses);
+  let fail_time = result.fail_time.unwrap();
+  assert!(misses.iter().any(|judgement| judgement.time == fail_time));
+  assert_eq!(result.life_graph.last(), Some(&(fail_time as i32, 0.0)));
+  assert_eq!(result.final_health, 0.0);
+
+  let sim = HealthSimulator::new(&beatmap, Mods::NoFail);
+  let result = sim.simulate(&misses);
+  assert_eq!(result.fail_time, None);
+  assert_eq!(result.min_health, 0.0);
+  Ok(())
+}
+
+#[test]
+fn test_health_sudden_death() -> Result<()> {
+  let beatmap = Beatmap::from_str(HEALTH_MAP)?;
+  let perfect = OsuSimulator::new(&beatmap, Mods::None).perfect_result();
+  let fail_time = |mods, result| {
+    let mut judgements = perfect.judgements.clone();
+    judgements[3].result = result;
+    HealthSimulator::new(&beatmap, mods)
+      .simulate(&judgements)
+      .fail_time
+  };
+
+  assert_eq!(fail_time(Mods::None, HitResult::Miss), None);
+  assert_eq!(fail_time(Mods::SuddenDeath, HitResult::Miss), Some(2500.0));
+  assert_eq!(fail_time(Mods::SuddenDeath, HitResult::Hit100), None);
+  assert_eq!(fail_time(Mods::Perfect, HitResult::Hit100), Some(2500.0));
+  Ok(())
+}
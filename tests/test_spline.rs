@@ -102,3 +102,30 @@ fn test_spline_points() -> Result<()> {
 
   Ok(())
 }
+
+#[test]
+fn test_linear_repeated_start() {
+  // old maps sometimes repeat the head of a linear slider as its first control point
+  let spline = Spline::from_control(
+    SliderSplineKind::Linear,
+    &[
+      Point { x: 100, y: 100 },
+      Point { x: 100, y: 100 },
+      Point { x: 200, y: 100 },
+      Point { x: 200, y: 200 },
+    ],
+    Some(50.0),
+  );
+  assert_eq!(
+    spline.spline_points,
+    [Point::new(100.0, 100.0), Point::new(150.0, 100.0)]
+  );
+
+  // a slider that never leaves its head stays there
+  let spline = Spline::from_control(
+    SliderSplineKind::Linear,
+    &[Point { x: 100, y: 100 }; 4],
+    Some(50.0),
+  );
+  assert_eq!(spline.end_point(), Point::new(100.0, 100.0));
+}
@@ -0,0 +1,135 @@
+use libosu::{
+  replay::{
+    Buttons, Key, KeyEventKind, ReplayAction, ReplayActionData, Timeline,
+  },
+  timing::Millis,
+};
+
+fn frame(time: i32, x: f32, buttons: Buttons) -> ReplayAction {
+  ReplayAction {
+    time: Millis(time),
+    x,
+    y: 0.0,
+    buttons,
+  }
+}
+
+fn timeline(frames: Vec<ReplayAction>) -> Timeline {
+  Timeline::new(&ReplayActionData {
+    frames,
+    rng_seed: None,
+  })
+}
+
+#[test]
+fn test_skips_leading_and_backwards_frames() {
+  let timeline = timeline(vec![
+    ReplayAction {
+      time: Millis(0),
+      x: 256.0,
+      y: -500.0,
+      buttons: Buttons::empty(),
+    },
+    ReplayAction {
+      time: Millis(-1),
+      x: 256.0,
+      y: -500.0,
+      buttons: Buttons::empty(),
+    },
+    frame(101, 10.0, Buttons::empty()),
+    frame(-50, 20.0, Buttons::empty()),
+    frame(60, 30.0, Buttons::empty()),
+    frame(0, 40.0, Buttons::empty()),
+  ]);
+
+  let frames = timeline
+    .frames()
+    .iter()
+    .map(|frame| (frame.time, frame.pos.x))
+    .collect::<Vec<_>>();
+  assert_eq!(frames, vec![(100, 10.0), (110, 30.0), (110, 40.0)]);
+}
+
+#[test]
+fn test_skips_frames_before_the_last_kept_one() {
+  let timeline = timeline(vec![
+    frame(100, 10.0, Buttons::K1),
+    frame(-50, 20.0, Buttons::K2),
+    frame(10, 30.0, Buttons::K2),
+    frame(100, 40.0, Buttons::empty()),
+  ]);
+
+  let frames = timeline
+    .frames()
+    .iter()
+    .map(|frame| (frame.time, frame.pos.x))
+    .collect::<Vec<_>>();
+  assert_eq!(frames, vec![(100, 10.0), (160, 40.0)]);
+  assert_eq!(timeline.buttons_at(150.0), Buttons::K1);
+  assert_eq!(timeline.position_at(130.0).unwrap().x, 25.0);
+}
+
+#[test]
+fn test_position_at() {
+  let timeline = timeline(vec![
+    frame(100, 0.0, Buttons::empty()),
+    frame(100, 100.0, Buttons::empty()),
+    frame(0, 200.0, Buttons::empty()),
+  ]);
+
+  assert_eq!(timeline.position_at(0.0).unwrap().x, 0.0);
+  assert_eq!(timeline.position_at(125.0).unwrap().x, 25.0);
+  assert_eq!(timeline.position_at(200.0).unwrap().x, 200.0);
+  assert_eq!(timeline.position_at(500.0).unwrap().x, 200.0);
+  assert!(Timeline::default().position_at(0.0).is_none());
+}
+
+#[test]
+fn test_key_events() {
+  let timeline = timeline(vec![
+    frame(10, 0.0, Buttons::empty()),
+    frame(10, 0.0, Buttons::K1 | Buttons::M1),
+    frame(10, 0.0, Buttons::M1),
+    frame(10, 0.0, Buttons::M1 | Buttons::M2 | Buttons::K2),
+    frame(10, 0.0, Buttons::empty()),
+  ]);
+
+  let events = timeline
+    .key_events()
+    .iter()
+    .map(|event| (event.time, event.key, event.kind))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    events,
+    vec![
+      (20, Key::K1, KeyEventKind::Press),
+      (30, Key::K1, KeyEventKind::Release),
+      (30, Key::M1, KeyEventKind::Press),
+      (40, Key::K2, KeyEventKind::Press),
+      (50, Key::K2, KeyEventKind::Release),
+      (50, Key::M1, KeyEventKind::Release),
+    ]
+  );
+
+  let counts = timeline.press_counts();
+  assert_eq!((counts.k1, counts.k2, counts.m1, counts.m2), (1, 1, 1, 0));
+  assert_eq!(counts.total(), 3);
+  assert_eq!(timeline.buttons_at(35.0), Buttons::M1);
+}
+
+#[test]
+fn test_kps() {
+  let mut frames = Vec::new();
+  for _ in 0..10 {
+    frames.push(frame(50, 0.0, Buttons::K1 | Buttons::M1));
+    frames.push(frame(50, 0.0, Buttons::empty()));
+  }
+  frames.push(frame(2000, 0.0, Buttons::K2 | Buttons::M2));
+  let timeline = timeline(frames);
+
+  assert_eq!(timeline.press_counts().k1, 10);
+  assert_eq!(timeline.max_kps(), 10);
+  assert_eq!(timeline.kps_at(950.0), 10);
+  assert_eq!(timeline.kps_at(1100.0), 9);
+  assert_eq!(timeline.kps_at(3000.0), 1);
+}